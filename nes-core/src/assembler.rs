@@ -0,0 +1,757 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::mem;
+
+use crate::opcodes::AddressingMode::{self, *};
+use crate::opcodes::OPCODES;
+use crate::CpuBus;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssemblerError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for AssemblerError {}
+
+// a run of bytes that starts at an .org
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub origin: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Program {
+    pub segments: Vec<Segment>,
+    // labels and constants, local labels are named like global@local
+    pub symbols: HashMap<String, u16>,
+}
+
+impl Program {
+    pub fn symbol(&self, name: &str) -> Option<u16> {
+        self.symbols.get(name).copied()
+    }
+
+    // pokes the program in so loading it doesn't take any cycles and works for prg rom too
+    pub fn write_to_bus<B: CpuBus>(&self, bus: &mut B) {
+        for segment in &self.segments {
+            for (offset, &data) in segment.data.iter().enumerate() {
+                bus.poke(segment.origin.wrapping_add(offset as u16), data);
+            }
+        }
+    }
+
+    // builds size bytes of prg rom that get mapped in at origin, or none if some of the program
+    // is outside of it
+    pub fn prg_image(&self, origin: u16, size: usize) -> Option<Vec<u8>> {
+        let mut image = vec![0; size];
+        for segment in &self.segments {
+            let start = (segment.origin as usize).checked_sub(origin as usize)?;
+            image
+                .get_mut(start..start + segment.data.len())?
+                .copy_from_slice(&segment.data);
+        }
+
+        Some(image)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum UnaryOp {
+    Negate,
+    LowByte,
+    HighByte,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BinaryOp {
+    Or,
+    Xor,
+    And,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Number(i64),
+    Symbol(String),
+    // the address of the current instruction
+    Pc,
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, symbols: &HashMap<String, u16>, pc: u16) -> Result<i64, String> {
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Symbol(name) => match symbols.get(name) {
+                Some(&value) => value as i64,
+                None => return Err(format!("Undefined symbol '{}'", name)),
+            },
+            Expr::Pc => pc as i64,
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(symbols, pc)?;
+                match op {
+                    UnaryOp::Negate => value.wrapping_neg(),
+                    UnaryOp::LowByte => value & 0xff,
+                    UnaryOp::HighByte => (value >> 8) & 0xff,
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left = left.eval(symbols, pc)?;
+                let right = right.eval(symbols, pc)?;
+                match op {
+                    BinaryOp::Or => left | right,
+                    BinaryOp::Xor => left ^ right,
+                    BinaryOp::And => left & right,
+                    BinaryOp::ShiftLeft => left.wrapping_shl(right as u32),
+                    BinaryOp::ShiftRight => left.wrapping_shr(right as u32),
+                    BinaryOp::Add => left.wrapping_add(right),
+                    BinaryOp::Subtract => left.wrapping_sub(right),
+                    BinaryOp::Multiply => left.wrapping_mul(right),
+                    BinaryOp::Divide if right == 0 => return Err("Division by zero".to_string()),
+                    // the only division that overflows is the most negative number by -1
+                    BinaryOp::Divide => left
+                        .checked_div(right)
+                        .ok_or_else(|| "Division overflow".to_string())?,
+                }
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Index {
+    X,
+    Y,
+}
+
+// ca65 uses z: and a: in front of an address to pick the zero page or absolute version
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Width {
+    ZeroPage,
+    Absolute,
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    None,
+    Accumulator,
+    Immediate(Expr),
+    Address(Expr, Option<Index>, Option<Width>),
+    Indirect(Expr),
+    IndirectX(Expr),
+    IndirectY(Expr),
+}
+
+#[derive(Clone, Debug)]
+enum ByteItem {
+    Expr(Expr),
+    Text(String),
+}
+
+#[derive(Clone, Debug)]
+enum Statement {
+    // the opcode gets picked on the first pass so the size can't change on the second
+    Instruction(u8, Operand),
+    Byte(Vec<ByteItem>),
+    Word(Vec<Expr>),
+    Org(u16),
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    // the last global label which local labels are attached to
+    scope: &'a str,
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '@'
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &str, scope: &'a str) -> Self {
+        Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+            scope,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_str(&mut self, text: &str) -> bool {
+        self.skip_whitespace();
+        text.chars()
+            .enumerate()
+            .all(|(offset, c)| self.chars.get(self.pos + offset) == Some(&c))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}'", c))
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.peek().is_none()
+    }
+
+    fn rest(&self) -> String {
+        self.chars[self.pos..].iter().collect()
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if !self
+            .chars
+            .get(self.pos)
+            .is_some_and(|&c| is_identifier_start(c))
+        {
+            return None;
+        }
+
+        self.pos += 1;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|&c| is_identifier_char(c))
+        {
+            self.pos += 1;
+        }
+
+        Some(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn symbol_name(&self, name: &str) -> String {
+        if name.starts_with('@') {
+            format!("{}{}", self.scope, name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn number(&mut self, radix: u32) -> Result<i64, String> {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_digit(radix)) {
+            self.pos += 1;
+        }
+
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        i64::from_str_radix(&digits, radix).map_err(|_| "Invalid number".to_string())
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        self.binary(0)
+    }
+
+    // lowest precedence first
+    fn binary_op(&mut self, level: usize) -> Option<BinaryOp> {
+        let ops: &[(&str, BinaryOp)] = match level {
+            0 => &[("|", BinaryOp::Or)],
+            1 => &[("^", BinaryOp::Xor)],
+            2 => &[("&", BinaryOp::And)],
+            3 => &[("<<", BinaryOp::ShiftLeft), (">>", BinaryOp::ShiftRight)],
+            4 => &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)],
+            _ => &[("*", BinaryOp::Multiply), ("/", BinaryOp::Divide)],
+        };
+
+        let &(text, op) = ops.iter().find(|(text, _)| self.peek_str(text))?;
+        self.pos += text.len();
+        Some(op)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        if level > 5 {
+            return self.unary();
+        }
+
+        let mut expr = self.binary(level + 1)?;
+        while let Some(op) = self.binary_op(level) {
+            let right = self.binary(level + 1)?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let op = if self.eat('-') {
+            UnaryOp::Negate
+        } else if self.eat('<') {
+            UnaryOp::LowByte
+        } else if self.eat('>') {
+            UnaryOp::HighByte
+        } else {
+            return self.primary();
+        };
+
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some('$') => {
+                self.pos += 1;
+                Ok(Expr::Number(self.number(16)?))
+            }
+            Some('%') => {
+                self.pos += 1;
+                Ok(Expr::Number(self.number(2)?))
+            }
+            Some(c) if c.is_ascii_digit() => Ok(Expr::Number(self.number(10)?)),
+            Some('\'') => {
+                let c = *self
+                    .chars
+                    .get(self.pos + 1)
+                    .ok_or("Unterminated character")?;
+                if self.chars.get(self.pos + 2) != Some(&'\'') {
+                    return Err("Unterminated character".to_string());
+                }
+
+                self.pos += 3;
+                Ok(Expr::Number(c as i64))
+            }
+            Some('*') => {
+                self.pos += 1;
+                Ok(Expr::Pc)
+            }
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.expression()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(c) if is_identifier_start(c) => {
+                let name = self.identifier().unwrap_or_default();
+                Ok(Expr::Symbol(self.symbol_name(&name)))
+            }
+            Some(c) => Err(format!("Unexpected '{}'", c)),
+            None => Err("Expected an expression".to_string()),
+        }
+    }
+
+    fn index(&mut self) -> Result<Option<Index>, String> {
+        if !self.eat(',') {
+            return Ok(None);
+        }
+
+        match self.identifier().map(|name| name.to_ascii_lowercase()) {
+            Some(ref name) if name == "x" => Ok(Some(Index::X)),
+            Some(ref name) if name == "y" => Ok(Some(Index::Y)),
+            _ => Err("Expected x or y".to_string()),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        if self.at_end() {
+            return Ok(Operand::None);
+        }
+
+        if self.eat('#') {
+            return Ok(Operand::Immediate(self.expression()?));
+        }
+
+        if self.eat('(') {
+            let expr = self.expression()?;
+            return match self.index()? {
+                Some(Index::X) => {
+                    self.expect(')')?;
+                    Ok(Operand::IndirectX(expr))
+                }
+                Some(Index::Y) => Err("Expected x".to_string()),
+                None => {
+                    self.expect(')')?;
+                    match self.index()? {
+                        Some(Index::Y) => Ok(Operand::IndirectY(expr)),
+                        Some(Index::X) => Err("Expected y".to_string()),
+                        None => Ok(Operand::Indirect(expr)),
+                    }
+                }
+            };
+        }
+
+        let start = self.pos;
+        let mut width = None;
+        if let Some(name) = self.identifier() {
+            let name = name.to_ascii_lowercase();
+            if name == "a" && self.at_end() {
+                return Ok(Operand::Accumulator);
+            } else if name == "a" && self.eat(':') {
+                width = Some(Width::Absolute);
+            } else if name == "z" && self.eat(':') {
+                width = Some(Width::ZeroPage);
+            } else {
+                self.pos = start;
+            }
+        }
+
+        let expr = self.expression()?;
+        let index = self.index()?;
+        Ok(Operand::Address(expr, index, width))
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|&c| c != '"') {
+            self.pos += 1;
+        }
+
+        let text = self.chars[start..self.pos].iter().collect();
+        self.expect('"')?;
+        Ok(text)
+    }
+
+    fn list<T, F: FnMut(&mut Self) -> Result<T, String>>(
+        &mut self,
+        mut item: F,
+    ) -> Result<Vec<T>, String> {
+        let mut items = vec![item(self)?];
+        while self.eat(',') {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+}
+
+// strips the comment while leaving any ; inside of quotes alone
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match (c, quote) {
+            (';', None) => return &line[..index],
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            _ => (),
+        }
+    }
+    line
+}
+
+// ca65 calls isb isc
+fn opcode_name(name: &str) -> String {
+    match name.to_ascii_uppercase().as_str() {
+        "ISC" => "ISB".to_string(),
+        name => name.to_string(),
+    }
+}
+
+// official opcodes are picked over unofficial duplicates like sbc #$xx
+fn find_opcode(name: &str, mode: AddressingMode) -> Option<u8> {
+    let candidates = || {
+        (0..=0xffu8).filter(move |&opcode| {
+            let info = OPCODES[opcode as usize];
+            info.mnemonic.name() == name && info.mode == mode
+        })
+    };
+
+    candidates()
+        .find(|&opcode| OPCODES[opcode as usize].official)
+        .or_else(|| candidates().next())
+}
+
+fn has_mode(name: &str, mode: AddressingMode) -> bool {
+    find_opcode(name, mode).is_some()
+}
+
+// picks the addressing mode that fits the operand, addresses that aren't known yet are assumed
+// to be absolute
+fn select_opcode(
+    name: &str,
+    operand: &Operand,
+    symbols: &HashMap<String, u16>,
+    pc: u16,
+) -> Result<u8, String> {
+    let mode = match operand {
+        Operand::None if has_mode(name, Implied) => Implied,
+        Operand::None | Operand::Accumulator => Accumulator,
+        Operand::Immediate(_) => Immediate,
+        Operand::Indirect(_) => Indirect,
+        Operand::IndirectX(_) => IndirectX,
+        Operand::IndirectY(_) => IndirectY,
+        Operand::Address(_, None, _) if has_mode(name, Relative) => Relative,
+        Operand::Address(expr, index, width) => {
+            let (zero_page, absolute) = match index {
+                None => (ZeroPage, Absolute),
+                Some(Index::X) => (ZeroPageX, AbsoluteX),
+                Some(Index::Y) => (ZeroPageY, AbsoluteY),
+            };
+
+            let fits_zero_page = match width {
+                Some(width) => *width == Width::ZeroPage,
+                None => matches!(expr.eval(symbols, pc), Ok(0..=0xff)),
+            };
+
+            if (fits_zero_page || !has_mode(name, absolute)) && has_mode(name, zero_page) {
+                zero_page
+            } else {
+                absolute
+            }
+        }
+    };
+
+    find_opcode(name, mode).ok_or_else(|| format!("{} doesn't support {:?} addressing", name, mode))
+}
+
+fn byte_value(expr: &Expr, symbols: &HashMap<String, u16>, pc: u16) -> Result<u8, String> {
+    match expr.eval(symbols, pc)? {
+        value @ -0x80..=0xff => Ok(value as u8),
+        value => Err(format!("Value {} doesn't fit in a byte", value)),
+    }
+}
+
+fn word_value(expr: &Expr, symbols: &HashMap<String, u16>, pc: u16) -> Result<u16, String> {
+    match expr.eval(symbols, pc)? {
+        value @ -0x8000..=0xffff => Ok(value as u16),
+        value => Err(format!("Value {} doesn't fit in a word", value)),
+    }
+}
+
+fn statement_size(statement: &Statement) -> u16 {
+    match statement {
+        Statement::Instruction(opcode, _) => OPCODES[*opcode as usize].size as u16,
+        Statement::Byte(items) => items
+            .iter()
+            .map(|item| match item {
+                ByteItem::Expr(_) => 1,
+                ByteItem::Text(text) => text.len() as u16,
+            })
+            .sum(),
+        Statement::Word(exprs) => exprs.len() as u16 * 2,
+        Statement::Org(_) => 0,
+    }
+}
+
+fn encode(
+    statement: &Statement,
+    symbols: &HashMap<String, u16>,
+    pc: u16,
+    data: &mut Vec<u8>,
+) -> Result<(), String> {
+    match statement {
+        Statement::Instruction(opcode, operand) => {
+            data.push(*opcode);
+
+            let expr = match operand {
+                Operand::None | Operand::Accumulator => return Ok(()),
+                Operand::Immediate(expr)
+                | Operand::Address(expr, _, _)
+                | Operand::Indirect(expr)
+                | Operand::IndirectX(expr)
+                | Operand::IndirectY(expr) => expr,
+            };
+
+            let info = OPCODES[*opcode as usize];
+            if info.mode == Relative {
+                let target = word_value(expr, symbols, pc)?;
+                let offset = target.wrapping_sub(pc.wrapping_add(2)) as i16;
+                if !(-0x80..=0x7f).contains(&offset) {
+                    return Err(format!("Branch to ${:04X} is out of range", target));
+                }
+                data.push(offset as u8);
+            } else if info.size == 2 {
+                data.push(byte_value(expr, symbols, pc)?);
+            } else {
+                let value = word_value(expr, symbols, pc)?;
+                data.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+            }
+        }
+        Statement::Byte(items) => {
+            for item in items {
+                match item {
+                    ByteItem::Expr(expr) => data.push(byte_value(expr, symbols, pc)?),
+                    ByteItem::Text(text) => data.extend(text.bytes()),
+                }
+            }
+        }
+        Statement::Word(exprs) => {
+            for expr in exprs {
+                let value = word_value(expr, symbols, pc)?;
+                data.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+            }
+        }
+        Statement::Org(_) => (),
+    }
+
+    Ok(())
+}
+
+fn define(symbols: &mut HashMap<String, u16>, name: String, value: u16) -> Result<(), String> {
+    if symbols.contains_key(&name) {
+        return Err(format!("Symbol '{}' is already defined", name));
+    }
+
+    symbols.insert(name, value);
+    Ok(())
+}
+
+// parses the line, defines its labels and constants and works out how big it is
+fn parse_line(
+    line: &str,
+    scope: &mut String,
+    symbols: &mut HashMap<String, u16>,
+    pc: u16,
+) -> Result<Option<Statement>, String> {
+    let scope_name = scope.clone();
+    let mut parser = Parser::new(strip_comment(line), &scope_name);
+
+    // labels can share the line with whatever follows them
+    let mut statement_start = parser.pos;
+    while let Some(name) = parser.identifier() {
+        if parser.eat(':') {
+            let name = if name.starts_with('@') {
+                format!("{}{}", scope, name)
+            } else {
+                *scope = name.clone();
+                name
+            };
+
+            define(symbols, name, pc)?;
+            statement_start = parser.pos;
+        } else if parser.eat('=') {
+            let name = parser.symbol_name(&name);
+            let value = word_value(&parser.expression()?, symbols, pc)?;
+            define(symbols, name, value)?;
+            return if parser.at_end() {
+                Ok(None)
+            } else {
+                Err(format!("Unexpected '{}'", parser.rest().trim()))
+            };
+        } else {
+            break;
+        }
+    }
+    parser.pos = statement_start;
+
+    if parser.at_end() {
+        return Ok(None);
+    }
+
+    let statement = if parser.eat('.') {
+        let directive = parser
+            .identifier()
+            .ok_or("Expected a directive")?
+            .to_ascii_lowercase();
+
+        match directive.as_str() {
+            "org" => Statement::Org(word_value(&parser.expression()?, symbols, pc)?),
+            "byte" | "byt" => Statement::Byte(parser.list(|parser| {
+                if parser.peek() == Some('"') {
+                    parser.text().map(ByteItem::Text)
+                } else {
+                    parser.expression().map(ByteItem::Expr)
+                }
+            })?),
+            "word" | "addr" => Statement::Word(parser.list(Parser::expression)?),
+            _ => return Err(format!("Unknown directive '.{}'", directive)),
+        }
+    } else {
+        let name = parser.identifier().ok_or("Expected an instruction")?;
+        let name = opcode_name(&name);
+        if !OPCODES.iter().any(|info| info.mnemonic.name() == name) {
+            return Err(format!("Unknown instruction '{}'", name));
+        }
+
+        let operand = parser.operand()?;
+        Statement::Instruction(select_opcode(&name, &operand, symbols, pc)?, operand)
+    };
+
+    if !parser.at_end() {
+        return Err(format!("Unexpected '{}'", parser.rest().trim()));
+    }
+
+    Ok(Some(statement))
+}
+
+pub fn assemble(source: &str) -> Result<Program, AssemblerError> {
+    let mut symbols = HashMap::new();
+    let mut statements = Vec::new();
+    let mut scope = String::new();
+    let mut pc: u16 = 0;
+
+    // the first pass finds where all the labels are
+    for (index, line) in source.lines().enumerate() {
+        let error = |message| AssemblerError {
+            line: index + 1,
+            message,
+        };
+
+        if let Some(statement) = parse_line(line, &mut scope, &mut symbols, pc).map_err(error)? {
+            pc = match statement {
+                Statement::Org(origin) => origin,
+                _ => pc.wrapping_add(statement_size(&statement)),
+            };
+            statements.push((index + 1, statement));
+        }
+    }
+
+    // the second pass fills in the bytes now that every label is known
+    let mut segments = Vec::new();
+    let mut segment = Segment {
+        origin: 0,
+        data: Vec::new(),
+    };
+    let mut pc: u16 = 0;
+    for (line, statement) in &statements {
+        if let Statement::Org(origin) = statement {
+            let next = Segment {
+                origin: *origin,
+                data: Vec::new(),
+            };
+            segments.push(mem::replace(&mut segment, next));
+            pc = *origin;
+            continue;
+        }
+
+        encode(statement, &symbols, pc, &mut segment.data).map_err(|message| AssemblerError {
+            line: *line,
+            message,
+        })?;
+        pc = pc.wrapping_add(statement_size(statement));
+    }
+
+    segments.push(segment);
+    segments.retain(|segment| !segment.data.is_empty());
+    Ok(Program { segments, symbols })
+}
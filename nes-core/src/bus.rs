@@ -1,4 +1,76 @@
-use crate::{Catridge, PPU};
+use crate::{Catridge, Error, PPU};
+
+// what happened on the bus during a single cpu cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusCycle {
+    Read { address: u16, data: u8 },
+    Write { address: u16, data: u8 },
+    Internal,
+}
+
+// everything the cpu needs from the machine it's in, every read, write and tick takes one cycle
+pub trait CpuBus {
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, data: u8);
+    // a cycle where the cpu doesn't use the bus
+    fn tick(&mut self);
+
+    // reads without clocking or any side effects for tools like the tracer
+    fn peek(&self, address: u16) -> u8;
+    // writes without clocking or any side effects for debuggers
+    fn poke(&mut self, address: u16, data: u8);
+    fn cycles_count(&self) -> u32;
+
+    // errors can't be returned in the middle of an instruction so they get held until the cpu
+    // finishes it
+    fn take_error(&mut self) -> Option<Error> {
+        None
+    }
+
+    // where the address currently lands in prg rom so tools can follow the mapper's banking
+    fn prg_rom_offset(&self, _address: u16) -> Option<usize> {
+        None
+    }
+
+    // the nmi output of whatever is on the bus, the ppu on the nes
+    fn nmi_line(&self) -> bool {
+        false
+    }
+
+    // runs one cycle of a dma that has the cpu halted, false when there's none pending
+    fn dma_cycle(&mut self) -> bool {
+        false
+    }
+
+    // the scanline and dot the ppu is on for traces, without a ppu it's worked out as if one ran
+    // 3 dots for every cycle since both started
+    fn ppu_position(&self) -> (u16, u16) {
+        let dots = self.cycles_count() as u64 * 3;
+        ((dots / 341 % 262) as u16, (dots % 341) as u16)
+    }
+
+    fn tick_multiple(&mut self, times: u8) {
+        for _ in 0..times {
+            self.tick();
+        }
+    }
+
+    fn read_word(&mut self, address: u16) -> u16 {
+        let low = self.read(address) as u16;
+        let high = self.read(address.wrapping_add(1)) as u16;
+        (high << 8) | low
+    }
+}
+
+// what the next cycle of an oam dma does
+#[derive(Clone, Copy, Debug)]
+enum OamDma {
+    Halt { page: u8 },
+    // waits for an even cycle to line up with
+    Align { page: u8 },
+    Read { page: u8, offset: u8 },
+    Write { page: u8, offset: u8, data: u8 },
+}
 
 // this is technically the cpu bus since only the cpu reads and writes to it
 pub struct Bus {
@@ -6,53 +78,216 @@ pub struct Bus {
     pub ppu: PPU,
     pub catridge: Option<Catridge>,
     pub cycles_count: u32,
+
+    // when set every cycle gets recorded, the debugger uses this for watchpoints
+    pub cycle_log: Option<Vec<BusCycle>>,
+
+    oam_dma: Option<OamDma>,
+    error: Option<Error>,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Bus {
     pub fn new() -> Self {
-        return Bus {
+        Bus {
             ram: [0; 2048],
             ppu: PPU::new(),
             catridge: None,
             cycles_count: 0,
-        };
+            cycle_log: None,
+            oam_dma: None,
+            error: None,
+        }
     }
 
     pub fn connect_catridge(&mut self, catridge: Catridge) {
         self.catridge = Some(catridge);
     }
 
-    pub fn clock(&mut self) {
-        self.cycles_count += 1;
+    fn store_error(&mut self, error: Error) {
+        // keep the first error since the later ones are probably caused by it
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
     }
 
-    pub fn clock_multiple(&mut self, times: u8) {
-        for _ in 0..times {
-            self.clock();
+    fn log_cycle(&mut self, cycle: BusCycle) {
+        if let Some(cycle_log) = &mut self.cycle_log {
+            cycle_log.push(cycle);
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.cycles_count = self.cycles_count.wrapping_add(1);
+        for _ in 0..3 {
+            self.ppu.clock(self.catridge.as_ref());
         }
     }
 
     pub fn read_byte(&mut self, address: u16) -> u8 {
         self.clock();
+        let data = self.read_memory(address);
+        self.log_cycle(BusCycle::Read { address, data });
+        data
+    }
+
+    // the side effects of a read without the cycle, shared with dma
+    fn read_memory(&mut self, address: u16) -> u8 {
         match address {
             0x0000..=0x1fff => self.ram[address as usize & 0x07ff],
-            0x2000..=0x3fff => self.ppu.read_register(address & 0x0007),
+            0x2000..=0x3fff => match self
+                .ppu
+                .read_register(address & 0x0007, self.catridge.as_ref())
+            {
+                Ok(data) => data,
+                Err(error) => {
+                    self.store_error(error);
+                    0
+                }
+            },
+            0x4020..=0xffff => self
+                .catridge
+                .as_ref()
+                .and_then(|catridge| catridge.cpu_read(address))
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    // reads what the cpu would see without taking a cycle or any side effects so tools can look
+    // at memory without disturbing the emulation
+    pub fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x1fff => self.ram[address as usize & 0x07ff],
+            0x2000..=0x3fff => self.ppu.peek_register(address & 0x0007).unwrap_or(0),
+            0x4020..=0xffff => self
+                .catridge
+                .as_ref()
+                .and_then(|catridge| catridge.cpu_peek(address))
+                .unwrap_or(0),
             _ => 0,
         }
     }
 
     pub fn write_byte(&mut self, address: u16, data: u8) {
         self.clock();
+        self.log_cycle(BusCycle::Write { address, data });
+        self.write_memory(address, data);
+    }
+
+    fn write_memory(&mut self, address: u16, data: u8) {
         match address {
             0x0000..=0x1fff => self.ram[address as usize & 0x07ff] = data,
-            0x2000..=0x3fff => self.ppu.write_register(address & 0x0007, data),
+            0x2000..=0x3fff => {
+                if let Err(error) =
+                    self.ppu
+                        .write_register(address & 0x0007, data, self.catridge.as_mut())
+                {
+                    self.store_error(error);
+                }
+            }
+            0x4014 => self.oam_dma = Some(OamDma::Halt { page: data }),
+            0x4020..=0xffff => {
+                if let Some(catridge) = &mut self.catridge {
+                    catridge.cpu_write(address, data);
+                }
+            }
             _ => (),
         }
     }
 
-    pub fn read_word(&mut self, address: u16) -> u16 {
-        let low = self.read_byte(address) as u16;
-        let high = self.read_byte(address + 1) as u16;
-        (high << 8) | low
+    // changes memory for debugging without taking a cycle, this can also patch prg rom
+    pub fn poke(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1fff => self.ram[address as usize & 0x07ff] = data,
+            0x2000..=0x3fff => {
+                let _ = self
+                    .ppu
+                    .poke_register(address & 0x0007, data, self.catridge.as_mut());
+            }
+            0x4020..=0xffff => {
+                if let Some(catridge) = &mut self.catridge {
+                    catridge.cpu_poke(address, data);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl CpuBus for Bus {
+    fn read(&mut self, address: u16) -> u8 {
+        self.read_byte(address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.write_byte(address, data);
+    }
+
+    fn tick(&mut self) {
+        self.clock();
+        self.log_cycle(BusCycle::Internal);
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        Bus::peek(self, address)
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        Bus::poke(self, address, data);
+    }
+
+    fn cycles_count(&self) -> u32 {
+        self.cycles_count
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> Option<usize> {
+        self.catridge.as_ref()?.prg_rom_offset(address)
+    }
+
+    fn nmi_line(&self) -> bool {
+        self.ppu.nmi_line()
+    }
+
+    // copies a page into oam through oamdata while the cpu is halted for 513 cycles, or 514 when
+    // it has to wait for an even cycle to line up with, the cpu didn't make these accesses so they
+    // stay out of the cycle log
+    fn dma_cycle(&mut self) -> bool {
+        let dma = match self.oam_dma.take() {
+            Some(dma) => dma,
+            None => return false,
+        };
+
+        self.clock();
+        self.oam_dma = match dma {
+            OamDma::Halt { page } if self.cycles_count % 2 == 1 => Some(OamDma::Align { page }),
+            OamDma::Halt { page } | OamDma::Align { page } => {
+                Some(OamDma::Read { page, offset: 0 })
+            }
+            OamDma::Read { page, offset } => {
+                let data = self.read_memory((page as u16) << 8 | offset as u16);
+                Some(OamDma::Write { page, offset, data })
+            }
+            OamDma::Write { page, offset, data } => {
+                self.write_memory(0x2004, data);
+                offset
+                    .checked_add(1)
+                    .map(|offset| OamDma::Read { page, offset })
+            }
+        };
+        true
+    }
+
+    fn ppu_position(&self) -> (u16, u16) {
+        (self.ppu.scanline(), self.ppu.dot())
     }
 }
@@ -1,21 +1,315 @@
+use std::fmt;
+
 use crate::mappers::*;
 
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+pub(crate) const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CartridgeError {
+    InvalidMagic,
+    UnexpectedEof { expected: usize, found: usize },
+    UnsupportedMapper(u16),
+    // the header claims rom sizes that don't fit in memory
+    InvalidRomSize,
+    MissingPrgRom,
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::InvalidMagic => write!(f, "Missing 'NES\\x1a' magic in header!"),
+            CartridgeError::UnexpectedEof { expected, found } => write!(
+                f,
+                "Catridge data ended early! Expected {} bytes but found {}",
+                expected, found
+            ),
+            CartridgeError::UnsupportedMapper(id) => write!(f, "Mapper {} not supported!", id),
+            CartridgeError::InvalidRomSize => write!(f, "Header has an invalid rom size!"),
+            CartridgeError::MissingPrgRom => write!(f, "Catridge has no prg rom!"),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
 pub struct Catridge {
     mapper: Box<dyn Mapper>,
-    prg_memory: Box<u8>,
-    chr_memory: Box<u8>,
+    info: MapperInfo,
+    prg_memory: Vec<u8>,
+    chr_memory: Vec<u8>,
+    prg_ram: Vec<u8>,
 }
 
 impl Catridge {
-    pub fn new(data: &[u8]) {
-        let mapper_id = data[7] & 0xf0 | data[6] >> 4;
+    pub fn new(data: &[u8]) -> Result<Catridge, CartridgeError> {
+        if data.len() < HEADER_SIZE {
+            return Err(CartridgeError::UnexpectedEof {
+                expected: HEADER_SIZE,
+                found: data.len(),
+            });
+        }
+
+        if &data[0..4] != b"NES\x1a" {
+            return Err(CartridgeError::InvalidMagic);
+        }
+
+        let is_nes2 = data[7] & 0x0c == 0x08;
+        let mirroring = if data[6] & 0x08 != 0 {
+            Mirroring::FourScreen
+        } else if data[6] & 0x01 != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+
+        let (mapper_id, submapper_id, prg_size, chr_size, prg_ram_size, chr_ram_size, timing) =
+            if is_nes2 {
+                let timing = match data[12] & 0x03 {
+                    0 => Timing::Ntsc,
+                    1 => Timing::Pal,
+                    2 => Timing::MultiRegion,
+                    _ => Timing::Dendy,
+                };
+
+                (
+                    (data[8] as u16 & 0x0f) << 8 | (data[7] & 0xf0 | data[6] >> 4) as u16,
+                    data[8] >> 4,
+                    nes2_rom_size(data[4], data[9] & 0x0f, PRG_BANK_SIZE),
+                    nes2_rom_size(data[5], data[9] >> 4, CHR_BANK_SIZE),
+                    nes2_ram_size(data[10] & 0x0f) + nes2_ram_size(data[10] >> 4),
+                    nes2_ram_size(data[11] & 0x0f) + nes2_ram_size(data[11] >> 4),
+                    timing,
+                )
+            } else {
+                // some old dumping tools wrote junk like "DiskDude!" over the end of the header
+                // so the upper mapper nibble can't be trusted in that case
+                let upper_nibble = if data[12..16].iter().all(|&byte| byte == 0) {
+                    data[7] & 0xf0
+                } else {
+                    0
+                };
+                let timing = if data[9] & 0x01 != 0 {
+                    Timing::Pal
+                } else {
+                    Timing::Ntsc
+                };
+                let chr_size = data[5] as usize * CHR_BANK_SIZE;
+
+                (
+                    (upper_nibble | data[6] >> 4) as u16,
+                    0,
+                    data[4] as usize * PRG_BANK_SIZE,
+                    chr_size,
+                    // a size of 0 implies 8kb for compatibility
+                    data[8].max(1) as usize * 8 * 1024,
+                    if chr_size == 0 { CHR_BANK_SIZE } else { 0 },
+                    timing,
+                )
+            };
+
+        let info = MapperInfo {
+            mapper_id,
+            submapper_id,
+            prg_banks: prg_size.div_ceil(PRG_BANK_SIZE) as u16,
+            chr_banks: chr_size.div_ceil(CHR_BANK_SIZE) as u16,
+            prg_ram_size,
+            chr_ram_size,
+            mirroring,
+            has_battery: data[6] & 0x02 != 0,
+            timing,
+        };
 
-        let mapper = match mapper_id {
-            0 => Mapper0::new(),
+        if prg_size == 0 {
+            return Err(CartridgeError::MissingPrgRom);
         }
 
-        // Catridge {
-        //     header
-        // }
+        let has_trainer = data[6] & 0x04 != 0;
+        let prg_start = HEADER_SIZE + if has_trainer { TRAINER_SIZE } else { 0 };
+        // exponent-multiplier sizes can be far larger than anything that could be loaded
+        let chr_start = prg_start
+            .checked_add(prg_size)
+            .ok_or(CartridgeError::InvalidRomSize)?;
+        let expected = chr_start
+            .checked_add(chr_size)
+            .ok_or(CartridgeError::InvalidRomSize)?;
+        if data.len() < expected {
+            return Err(CartridgeError::UnexpectedEof {
+                expected,
+                found: data.len(),
+            });
+        }
+
+        let mut prg_ram = vec![0; prg_ram_size];
+        // the trainer gets loaded into $7000-$71ff
+        if has_trainer && prg_ram.len() >= 0x1000 + TRAINER_SIZE {
+            prg_ram[0x1000..0x1000 + TRAINER_SIZE].copy_from_slice(&data[HEADER_SIZE..prg_start]);
+        }
+
+        let chr_memory = if chr_size == 0 {
+            vec![0; chr_ram_size]
+        } else {
+            data[chr_start..expected].to_vec()
+        };
+
+        let mapper: Box<dyn Mapper> = match mapper_id {
+            0 => Box::new(Mapper0::new(info)),
+            _ => return Err(CartridgeError::UnsupportedMapper(mapper_id)),
+        };
+
+        Ok(Catridge {
+            mapper,
+            info,
+            prg_memory: data[prg_start..chr_start].to_vec(),
+            chr_memory,
+            prg_ram,
+        })
+    }
+
+    pub fn cpu_read(&self, address: u16) -> Option<u8> {
+        let mut mapped_address = 0;
+        if self.mapper.map_prg_ram_read(address, &mut mapped_address) {
+            Some(self.prg_ram[mapped_address as usize])
+        } else if self.mapper.map_prg_read(address, &mut mapped_address) {
+            Some(self.prg_memory[self.prg_index(mapped_address)])
+        } else {
+            None
+        }
+    }
+
+    pub fn cpu_write(&mut self, address: u16, data: u8) -> bool {
+        let mut mapped_address = 0;
+        if self.mapper.map_prg_ram_write(address, &mut mapped_address) {
+            self.prg_ram[mapped_address as usize] = data;
+            true
+        } else {
+            // prg rom can't be written to but the mapper might still claim the address
+            self.mapper.map_prg_write(address, &mut mapped_address)
+        }
+    }
+
+    // reads what the cpu would see without any side effects on the mapper
+    pub fn cpu_peek(&self, address: u16) -> Option<u8> {
+        let mut mapped_address = 0;
+        if self.mapper.map_prg_ram_read(address, &mut mapped_address) {
+            Some(self.prg_ram[mapped_address as usize])
+        } else if self.mapper.map_prg_peek(address, &mut mapped_address) {
+            Some(self.prg_memory[self.prg_index(mapped_address)])
+        } else {
+            None
+        }
+    }
+
+    // changes the memory mapped in at the address, prg rom included, without writing to any of the
+    // mapper's registers
+    pub fn cpu_poke(&mut self, address: u16, data: u8) -> bool {
+        let mut mapped_address = 0;
+        if self.mapper.map_prg_ram_write(address, &mut mapped_address) {
+            self.prg_ram[mapped_address as usize] = data;
+            true
+        } else if self.mapper.map_prg_peek(address, &mut mapped_address) {
+            let index = self.prg_index(mapped_address);
+            self.prg_memory[index] = data;
+            true
+        } else {
+            false
+        }
+    }
+
+    // where the address currently lands in prg rom, none if it isn't mapped there
+    pub fn prg_rom_offset(&self, address: u16) -> Option<usize> {
+        let mut mapped_address = 0;
+        if self.mapper.map_prg_ram_read(address, &mut mapped_address) {
+            None
+        } else if self.mapper.map_prg_peek(address, &mut mapped_address) {
+            Some(self.prg_index(mapped_address))
+        } else {
+            None
+        }
+    }
+
+    // prg roms smaller than what the mapper expects (like 8kb nes 2.0 ones) get mirrored
+    fn prg_index(&self, mapped_address: u16) -> usize {
+        mapped_address as usize % self.prg_memory.len()
+    }
+
+    // the ppu side only covers the pattern tables at $0000-$1fff, nametables are in the ppu
+    pub fn ppu_read(&self, address: u16) -> Option<u8> {
+        let mut mapped_address = 0;
+        if self.mapper.map_chr_read(address, &mut mapped_address) {
+            self.chr_memory.get(mapped_address as usize).copied()
+        } else {
+            None
+        }
+    }
+
+    pub fn ppu_write(&mut self, address: u16, data: u8) -> bool {
+        let mut mapped_address = 0;
+        if self.mapper.map_chr_write(address, &mut mapped_address) {
+            if let Some(byte) = self.chr_memory.get_mut(mapped_address as usize) {
+                *byte = data;
+                return true;
+            }
+        }
+        false
+    }
+
+    // changes chr memory even when it's rom
+    pub fn ppu_poke(&mut self, address: u16, data: u8) -> bool {
+        let mut mapped_address = 0;
+        if self.mapper.map_chr_read(address, &mut mapped_address) {
+            if let Some(byte) = self.chr_memory.get_mut(mapped_address as usize) {
+                *byte = data;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        self.info.mirroring
+    }
+
+    pub fn info(&self) -> &MapperInfo {
+        &self.info
+    }
+
+    pub fn mapper(&self) -> &dyn Mapper {
+        self.mapper.as_ref()
+    }
+
+    pub fn prg_rom(&self) -> &[u8] {
+        &self.prg_memory
+    }
+
+    pub fn chr_memory(&self) -> &[u8] {
+        &self.chr_memory
+    }
+
+    pub fn prg_ram(&self) -> &[u8] {
+        &self.prg_ram
+    }
+}
+
+// size is in the lsb byte and the msb nibble unless the msb nibble is 0xf which means the lsb
+// byte is in exponent-multiplier form (EEEEEEMM) with the size being 2^E * (MM * 2 + 1)
+fn nes2_rom_size(lsb: u8, msb: u8, unit_size: usize) -> usize {
+    if msb == 0x0f {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+        2usize.saturating_pow(exponent).saturating_mul(multiplier)
+    } else {
+        ((msb as usize) << 8 | lsb as usize) * unit_size
+    }
+}
+
+// ram sizes are stored as shift counts with 0 meaning no ram
+fn nes2_ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
     }
 }
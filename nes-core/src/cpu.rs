@@ -1,6 +1,14 @@
-use crate::bus::Bus;
+use std::fmt;
+use std::rc::Rc;
 
-enum Flag {
+use crate::bus::{Bus, CpuBus};
+use crate::opcodes::{AddressingMode, Mnemonic, Opcode, OPCODES};
+use crate::profiler::{Profiler, RoutineKind};
+use crate::trace::{TraceEntry, TraceSink};
+use crate::{Error, Symbols};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flag {
     Carry = 1 << 0,
     Zero = 1 << 1,
     InterruptDisable = 1 << 2,
@@ -11,24 +19,75 @@ enum Flag {
     Negative = 1 << 7,
 }
 
-enum Mode {
-    Immediate,
-    ZeroPage,
-    ZeroPageX,
-    ZeroPageY,
-    Absolute,
-    AbsoluteX,
-    AbsoluteXForceClock,
-    AbsoluteY,
-    AbsoluteYForceClock,
-    Indirect,
-    IndirectX,
-    IndirectY,
-    IndirectYForceClock,
+const FLAG_LETTERS: [(Flag, char); 8] = [
+    (Flag::Negative, 'N'),
+    (Flag::Overflow, 'V'),
+    (Flag::Unused, 'U'),
+    (Flag::Break, 'B'),
+    (Flag::DecimalMode, 'D'),
+    (Flag::InterruptDisable, 'I'),
+    (Flag::Zero, 'Z'),
+    (Flag::Carry, 'C'),
+];
+
+// what xaa and lxa or into the accumulator before the and
+const UNSTABLE_MAGIC: u8 = 0xee;
+
+// the processor status register
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatusFlags(u8);
+
+impl StatusFlags {
+    pub fn from_bits(bits: u8) -> Self {
+        StatusFlags(bits)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn get(self, flag: Flag) -> bool {
+        self.0 & (flag as u8) != 0
+    }
+
+    pub fn set(&mut self, flag: Flag, status: bool) {
+        if status {
+            self.0 |= flag as u8;
+        } else {
+            self.0 &= !(flag as u8);
+        }
+    }
+}
+
+// formats as NVUBDIZC with lowercase letters for cleared flags
+impl fmt::Display for StatusFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (flag, letter) in FLAG_LETTERS {
+            let letter = if self.get(flag) {
+                letter
+            } else {
+                letter.to_ascii_lowercase()
+            };
+            write!(f, "{}", letter)?;
+        }
+
+        Ok(())
+    }
+}
+
+// a snapshot of all the cpu registers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuState {
+    pub pc: u16,
+    pub sp: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub flags: StatusFlags,
 }
 
-pub struct CPU {
-    pub bus: Bus,
+pub struct CPU<B: CpuBus = Bus> {
+    pub bus: B,
 
     // registers
     pc: u16,
@@ -37,46 +96,352 @@ pub struct CPU {
     x: u8,
     y: u8,
     flags: u8,
+
+    // the instruction being executed and which of its cycles comes next, step 0 fetches a new
+    // opcode
+    opcode: u8,
+    step: u8,
+
+    // values carried between the cycles of an instruction
+    address: u16,
+    pointer: u8,
+    data: u8,
+    // how many cycles have been spent on the operand after its address was worked out
+    operand_cycles: u8,
+
+    // interrupt inputs, nmi triggers on the line becoming active while irq stays active until
+    // it's acknowledged
+    nmi_line: bool,
+    prev_nmi_line: bool,
+    irq_line: bool,
+
+    // interrupts get polled at the end of every cycle but the cpu only acts on what it saw one
+    // cycle before the end of an instruction
+    need_nmi: bool,
+    prev_need_nmi: bool,
+    // an nmi a branch held back, it gets seen again on the next poll
+    delayed_nmi: bool,
+    run_irq: bool,
+    prev_run_irq: bool,
+    // the current instruction is an interrupt going through the brk sequence
+    in_interrupt: bool,
+
+    // set to the jam opcode that locked up the cpu and only cleared by a reset
+    jammed: Option<u8>,
+
+    // the 2a03 in the nes has decimal mode disconnected but other 6502s don't
+    decimal_mode_enabled: bool,
+
+    trace_sink: Option<Box<dyn TraceSink>>,
+    // names addresses in the trace
+    symbols: Option<Rc<Symbols>>,
+    profiler: Option<Profiler>,
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl CPU {
+impl CPU<Bus> {
     pub fn new() -> Self {
+        CPU::with_bus(Bus::new())
+    }
+}
+
+impl<B: CpuBus> CPU<B> {
+    pub fn with_bus(bus: B) -> Self {
         CPU {
-            bus: Bus::new(),
+            bus,
             pc: 0,
             sp: 0,
             a: 0,
             x: 0,
             y: 0,
             flags: 0,
+            opcode: 0,
+            step: 0,
+            address: 0,
+            pointer: 0,
+            data: 0,
+            operand_cycles: 0,
+            nmi_line: false,
+            prev_nmi_line: false,
+            irq_line: false,
+            need_nmi: false,
+            prev_need_nmi: false,
+            delayed_nmi: false,
+            run_irq: false,
+            prev_run_irq: false,
+            in_interrupt: false,
+            jammed: None,
+            decimal_mode_enabled: false,
+            trace_sink: None,
+            symbols: None,
+            profiler: None,
         }
     }
 
     pub fn reset(&mut self) {
-        self.sp = 0xff;
-        self.a = 0;
-        self.x = 0;
-        self.y = 0;
-        self.flags = 0;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.clear_stack(self.bus.cycles_count());
+        }
+
+        self.jammed = None;
+        self.step = 0;
+        self.need_nmi = false;
+        self.prev_need_nmi = false;
+        self.delayed_nmi = false;
+        self.run_irq = false;
+        self.prev_run_irq = false;
+
+        // reset goes through the interrupt sequence but with the stack writes turned into reads
+        self.bus.read(self.pc);
+        self.bus.read(self.pc);
+        for _ in 0..3 {
+            self.bus.read(0x100 + self.sp as u16);
+            self.sp = self.sp.wrapping_sub(1);
+        }
+
+        self.set_flag(Flag::InterruptDisable, true);
         self.set_flag(Flag::Unused, true);
-        self.bus.clock_multiple(5);
         self.pc = self.bus.read_word(0xfffc);
     }
 
-    pub fn irq(&mut self) {
-        if !self.get_flag(Flag::InterruptDisable) {
-            self.do_interrupt();
+    // an nmi happens once each time the line becomes active
+    pub fn set_nmi_line(&mut self, active: bool) {
+        self.nmi_line = active;
+    }
+
+    // irqs keep happening while the line is active and interrupts are enabled
+    pub fn set_irq_line(&mut self, active: bool) {
+        self.irq_line = active;
+    }
+
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            pc: self.pc,
+            sp: self.sp,
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            flags: StatusFlags(self.flags),
+        }
+    }
+
+    pub fn set_state(&mut self, state: CpuState) {
+        self.pc = state.pc;
+        self.sp = state.sp;
+        self.a = state.a;
+        self.x = state.x;
+        self.y = state.y;
+        self.flags = state.flags.bits();
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    pub fn set_sp(&mut self, sp: u8) {
+        self.sp = sp;
+    }
+
+    pub fn a(&self) -> u8 {
+        self.a
+    }
+
+    pub fn set_a(&mut self, a: u8) {
+        self.a = a;
+    }
+
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn set_x(&mut self, x: u8) {
+        self.x = x;
+    }
+
+    pub fn y(&self) -> u8 {
+        self.y
+    }
+
+    pub fn set_y(&mut self, y: u8) {
+        self.y = y;
+    }
+
+    pub fn flags(&self) -> StatusFlags {
+        StatusFlags(self.flags)
+    }
+
+    pub fn set_flags(&mut self, flags: StatusFlags) {
+        self.flags = flags.bits();
+    }
+
+    pub fn set_decimal_mode_enabled(&mut self, enabled: bool) {
+        self.decimal_mode_enabled = enabled;
+    }
+
+    // the sink receives an entry before every executed instruction
+    pub fn set_trace_sink(&mut self, trace_sink: Option<Box<dyn TraceSink>>) {
+        self.trace_sink = trace_sink;
+    }
+
+    // shared since tools like the debugger usually want the same labels
+    pub fn set_symbols(&mut self, symbols: Option<Rc<Symbols>>) {
+        self.symbols = symbols;
+    }
+
+    // the profiler starts counting from the current bus cycle
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>) {
+        self.profiler = profiler;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.reset(self.bus.cycles_count());
         }
     }
 
-    pub fn nmi(&mut self) {
-        self.do_interrupt();
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    pub fn profiler_mut(&mut self) -> Option<&mut Profiler> {
+        self.profiler.as_mut()
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    pub fn is_jammed(&self) -> bool {
+        self.jammed.is_some()
     }
 
-    pub fn execute_next_instruction(&mut self) {
-        let opcode = self.bus.read_byte(self.pc);
-        self.pc += 1;
-        self.execute_instruction(opcode);
+    // true when the next clock starts a new instruction
+    pub fn is_instruction_boundary(&self) -> bool {
+        self.step == 0
+    }
+
+    // runs a single cycle and returns true if it was the last cycle of an instruction
+    pub fn clock(&mut self) -> Result<bool, Error> {
+        // a jammed cpu doesn't fetch anything but time still passes for the rest of the system
+        if let Some(opcode) = self.jammed {
+            self.bus.tick();
+            return Err(Error::CpuJam {
+                opcode,
+                pc: self.pc,
+            });
+        }
+
+        if self.step == 0 {
+            // a dma halts the cpu before it gets to the next instruction
+            if self.bus.dma_cycle() {
+                return Ok(false);
+            }
+
+            self.start_instruction();
+            self.poll_interrupts();
+            self.step = 1;
+            return Ok(false);
+        }
+
+        let finished = match self.execute_instruction(self.opcode) {
+            Ok(finished) => finished,
+            Err(error) => {
+                self.step = 0;
+                return Err(error);
+            }
+        };
+        self.poll_interrupts();
+
+        if !finished {
+            self.step += 1;
+            return Ok(false);
+        }
+
+        self.step = 0;
+        if self.profiler.is_some() {
+            self.profile_instruction();
+        }
+
+        match self.bus.take_error() {
+            Some(error) => Err(error),
+            None => Ok(true),
+        }
+    }
+
+    // runs the rest of the current instruction or a whole new one when between instructions
+    pub fn execute_next_instruction(&mut self) -> Result<(), Error> {
+        while !self.clock()? {}
+        Ok(())
+    }
+
+    // tells the profiler about calls and returns once their instruction is done
+    fn profile_instruction(&mut self) {
+        let kind = match OPCODES[self.opcode as usize].mnemonic {
+            Mnemonic::Jsr => RoutineKind::Subroutine,
+            // the vector tells an nmi that took over a brk or an irq apart
+            Mnemonic::Brk if self.address == 0xfffa => RoutineKind::Nmi,
+            Mnemonic::Brk if self.in_interrupt => RoutineKind::Irq,
+            Mnemonic::Brk => RoutineKind::Brk,
+            Mnemonic::Rts | Mnemonic::Rti => {
+                let cycles = self.bus.cycles_count();
+                if let Some(profiler) = &mut self.profiler {
+                    profiler.leave(self.sp, cycles);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        let prg_offset = self.bus.prg_rom_offset(self.pc);
+        let cycles = self.bus.cycles_count();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(kind, self.pc, prg_offset, self.sp, cycles);
+        }
+    }
+
+    fn start_instruction(&mut self) {
+        self.operand_cycles = 0;
+        self.in_interrupt = self.prev_need_nmi || self.prev_run_irq;
+
+        if self.in_interrupt {
+            // the opcode gets fetched but thrown away and a brk runs instead
+            self.bus.read(self.pc);
+            self.opcode = 0x00;
+            return;
+        }
+
+        if self.trace_sink.is_some() {
+            let entry = TraceEntry::with_symbols(self.state(), &self.bus, self.symbols.as_deref());
+            if let Some(trace_sink) = &mut self.trace_sink {
+                trace_sink.trace(&entry);
+            }
+        }
+
+        self.opcode = self.fetch_byte();
+    }
+
+    fn poll_interrupts(&mut self) {
+        // the line is shared so either the bus or whoever called set_nmi_line can pull it
+        let nmi_line = self.nmi_line || self.bus.nmi_line();
+        self.prev_need_nmi = self.need_nmi;
+        if nmi_line && !self.prev_nmi_line || self.delayed_nmi {
+            self.need_nmi = true;
+            self.delayed_nmi = false;
+        }
+        self.prev_nmi_line = nmi_line;
+
+        self.prev_run_irq = self.run_irq;
+        self.run_irq = self.irq_line && !self.get_flag(Flag::InterruptDisable);
     }
 
     // helper flag funtions
@@ -97,536 +462,490 @@ impl CPU {
         self.set_flag(Flag::Negative, value & 0x80 != 0);
     }
 
-    // stack helper functions
-    fn push_byte(&mut self, value: u8) {
-        self.bus.write_byte(0x100 + self.sp as u16, value);
-        self.sp -= 1;
+    fn fetch_byte(&mut self) -> u8 {
+        let data = self.bus.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        data
     }
 
-    fn push_word(&mut self, value: u16) {
-        self.push_byte((value >> 8) as u8);
-        self.push_byte(value as u8);
+    // stack helper functions
+    fn push_byte(&mut self, value: u8) {
+        self.bus.write(0x100 + self.sp as u16, value);
+        self.sp = self.sp.wrapping_sub(1);
     }
 
     fn pop_byte(&mut self) -> u8 {
-        self.sp += 1;
-        self.bus.read_byte(0x100 + self.sp as u16)
+        self.sp = self.sp.wrapping_add(1);
+        self.bus.read(0x100 + self.sp as u16)
     }
 
-    fn pop_word(&mut self) -> u16 {
-        self.pop_byte() as u16 | (self.pop_byte() as u16) << 8
+    // the cpu reads the top of the stack while it increments the stack pointer
+    fn read_stack(&mut self) {
+        self.bus.read(0x100 + self.sp as u16);
     }
 
-    fn do_interrupt(&mut self) {
-        self.push_word(self.pc);
-        self.set_flag(Flag::InterruptDisable, true);
-        self.push_byte(self.flags);
-        self.bus.clock_multiple(2);
-        self.pc = self.bus.read_word(0xfffe);
+    // the break flag only exists in the copy of the flags pushed onto the stack
+    fn pop_flags(&mut self) {
+        self.flags = self.pop_byte() & !(Flag::Break as u8) | Flag::Unused as u8;
     }
 
-    fn read_operand_address(&mut self, mode: Mode) -> u16 {
-        match mode {
-            Mode::Immediate => {
-                let address = self.pc;
-                self.pc += 1;
-                address
+    // runs the addressing cycle for the current step and returns true once the address is ready,
+    // the operand gets accessed on that same cycle
+    fn resolve_address(&mut self, mode: AddressingMode, always_fix_page: bool) -> bool {
+        match (mode, self.step) {
+            (AddressingMode::Immediate, _) => {
+                self.address = self.pc;
+                self.pc = self.pc.wrapping_add(1);
+                true
             }
 
-            Mode::ZeroPage => {
-                let address = self.bus.read_byte(self.pc) as u16;
-                self.pc += 1;
-                address
+            (AddressingMode::ZeroPage, 1) => {
+                self.address = self.fetch_byte() as u16;
+                false
             }
+            (AddressingMode::ZeroPage, _) => true,
 
-            Mode::ZeroPageX => {
-                let address = (self.bus.read_byte(self.pc) + self.x) as u16;
-                self.pc += 1;
-                self.bus.clock();
-                address
+            (AddressingMode::ZeroPageX, 1) | (AddressingMode::ZeroPageY, 1) => {
+                self.address = self.fetch_byte() as u16;
+                false
             }
-
-            Mode::ZeroPageY => {
-                let address = (self.bus.read_byte(self.pc) + self.y) as u16;
-                self.pc += 1;
-                self.bus.clock();
-                address
+            (AddressingMode::ZeroPageX, 2) | (AddressingMode::ZeroPageY, 2) => {
+                // the unindexed address gets read while the index is added
+                self.bus.read(self.address);
+                let index = if let AddressingMode::ZeroPageX = mode {
+                    self.x
+                } else {
+                    self.y
+                };
+                self.address = (self.address as u8).wrapping_add(index) as u16;
+                false
             }
+            (AddressingMode::ZeroPageX, _) | (AddressingMode::ZeroPageY, _) => true,
 
-            Mode::Absolute => {
-                let address = self.bus.read_word(self.pc);
-                self.pc += 2;
-                address
+            (AddressingMode::Absolute, 1)
+            | (AddressingMode::AbsoluteX, 1)
+            | (AddressingMode::AbsoluteY, 1) => {
+                self.address = self.fetch_byte() as u16;
+                false
             }
-
-            Mode::AbsoluteX | Mode::AbsoluteXForceClock => {
-                let address_abs = self.bus.read_word(self.pc);
-                let address = address_abs + self.x as u16;
-                self.pc += 2;
-
-                // some instructions need additional clock cycle when changing page
-                if matches!(mode, Mode::AbsoluteXForceClock)
-                    || address & 0xff00 != address_abs & 0xff00
-                {
-                    self.bus.clock()
-                }
-
-                address
+            (AddressingMode::Absolute, 2)
+            | (AddressingMode::AbsoluteX, 2)
+            | (AddressingMode::AbsoluteY, 2) => {
+                self.address |= (self.fetch_byte() as u16) << 8;
+                false
             }
+            (AddressingMode::AbsoluteX, 3) => self.add_index(self.x, always_fix_page),
+            (AddressingMode::AbsoluteY, 3) => self.add_index(self.y, always_fix_page),
+            (AddressingMode::Absolute, _)
+            | (AddressingMode::AbsoluteX, _)
+            | (AddressingMode::AbsoluteY, _) => true,
+
+            (AddressingMode::IndirectX, 1) => {
+                self.pointer = self.fetch_byte();
+                false
+            }
+            (AddressingMode::IndirectX, 2) => {
+                self.bus.read(self.pointer as u16);
+                self.pointer = self.pointer.wrapping_add(self.x);
+                false
+            }
+            (AddressingMode::IndirectX, 3) => {
+                self.address = self.bus.read(self.pointer as u16) as u16;
+                false
+            }
+            (AddressingMode::IndirectX, 4) => {
+                // the pointer wraps around the zero page instead of crossing into page 1
+                let high = self.bus.read(self.pointer.wrapping_add(1) as u16);
+                self.address |= (high as u16) << 8;
+                false
+            }
+            (AddressingMode::IndirectX, _) => true,
 
-            Mode::AbsoluteY | Mode::AbsoluteYForceClock => {
-                let address_abs = self.bus.read_word(self.pc);
-                let address = address_abs + self.y as u16;
-                self.pc += 2;
-
-                if matches!(mode, Mode::AbsoluteYForceClock)
-                    || address & 0xff00 != address_abs & 0xff00
-                {
-                    self.bus.clock()
-                }
-
-                address
+            (AddressingMode::IndirectY, 1) => {
+                self.pointer = self.fetch_byte();
+                false
+            }
+            (AddressingMode::IndirectY, 2) => {
+                self.address = self.bus.read(self.pointer as u16) as u16;
+                false
             }
+            (AddressingMode::IndirectY, 3) => {
+                let high = self.bus.read(self.pointer.wrapping_add(1) as u16);
+                self.address |= (high as u16) << 8;
+                false
+            }
+            (AddressingMode::IndirectY, 4) => self.add_index(self.y, always_fix_page),
+            (AddressingMode::IndirectY, _) => true,
+
+            // instructions using these modes handle their own operands
+            (AddressingMode::Implied, _)
+            | (AddressingMode::Accumulator, _)
+            | (AddressingMode::Indirect, _)
+            | (AddressingMode::Relative, _) => true,
+        }
+    }
 
-            Mode::Indirect => {
-                let pointer = self.bus.read_word(self.pc);
-                self.pc += 2;
+    // the index only gets added to the low byte at first so there's an extra cycle reading from
+    // the wrong page while the high byte gets fixed, writes always take that cycle
+    fn add_index(&mut self, index: u8, always_fix_page: bool) -> bool {
+        let address = self.address.wrapping_add(index as u16);
+        let page_crossed = address & 0xff00 != self.address & 0xff00;
 
-                let address: u16;
-                // emulate page boundary crossing bug
-                if pointer & 0x00ff == 0x00ff {
-                    let low = self.bus.read_byte(pointer) as u16;
-                    let high = self.bus.read_byte(pointer & 0xff00) as u16;
-                    address = (high << 8) | low;
-                } else {
-                    address = self.bus.read_word(self.pc);
-                }
+        if page_crossed || always_fix_page {
+            self.bus.read(self.address & 0xff00 | address & 0x00ff);
+            self.address = address;
+            false
+        } else {
+            self.address = address;
+            true
+        }
+    }
 
-                address
-            }
+    // the different kinds of instructions, each call runs one cycle and returns true when done
+    fn read_op(&mut self, mode: AddressingMode, operation: fn(&mut Self, u8)) -> bool {
+        if !self.resolve_address(mode, false) {
+            return false;
+        }
 
-            Mode::IndirectX => {
-                let pointer = (self.bus.read_byte(self.pc) + self.x) as u16;
-                let address = self.bus.read_word(pointer);
-                self.pc += 1;
-                self.bus.clock();
-                address
-            }
+        let data = self.bus.read(self.address);
+        operation(self, data);
+        true
+    }
 
-            Mode::IndirectY | Mode::IndirectYForceClock => {
-                let pointer = self.bus.read_byte(self.pc) as u16;
-                let address_abs = self.bus.read_word(pointer);
-                let address = address_abs + self.y as u16;
-                self.pc += 1;
+    fn write_op(&mut self, mode: AddressingMode, operation: fn(&mut Self) -> u8) -> bool {
+        if !self.resolve_address(mode, true) {
+            return false;
+        }
 
-                if matches!(mode, Mode::IndirectYForceClock)
-                    || address & 0xff00 != address_abs & 0xff00
-                {
-                    self.bus.clock()
+        let data = operation(self);
+        self.bus.write(self.address, data);
+        true
+    }
+
+    // the unmodified value gets written back while the new one is worked out
+    fn read_modify_write_op(
+        &mut self,
+        mode: AddressingMode,
+        operation: fn(&mut Self, u8) -> u8,
+    ) -> bool {
+        match self.operand_cycles {
+            0 => {
+                if self.resolve_address(mode, true) {
+                    self.data = self.bus.read(self.address);
+                    self.operand_cycles = 1;
                 }
-
-                address
+                false
+            }
+            1 => {
+                self.bus.write(self.address, self.data);
+                self.data = operation(self, self.data);
+                self.operand_cycles = 2;
+                false
+            }
+            _ => {
+                self.bus.write(self.address, self.data);
+                true
             }
         }
     }
 
-    // returns (data, address)
-    fn read_operand(&mut self, mode: Mode) -> (u8, u16) {
-        let address = self.read_operand_address(mode);
-        let data = self.bus.read_byte(address);
-        (data, address)
+    // the byte after the opcode gets read and ignored
+    fn implied_op(&mut self, operation: fn(&mut Self)) -> bool {
+        self.bus.read(self.pc);
+        operation(self);
+        true
     }
 
-    fn execute_instruction(&mut self, opcode: u8) {
-        println!("Opcode: {:x}", opcode);
-        match opcode {
-            // register loads
-            0xa9 => self.lda(Mode::Immediate),
-            0xad => self.lda(Mode::Absolute),
-            0xbd => self.lda(Mode::AbsoluteX),
-            0xb9 => self.lda(Mode::AbsoluteY),
-            0xa5 => self.lda(Mode::ZeroPage),
-            0xb5 => self.lda(Mode::ZeroPageX),
-            0xa1 => self.lda(Mode::IndirectX),
-            0xb1 => self.lda(Mode::IndirectY),
-
-            0xa2 => self.ldx(Mode::Immediate),
-            0xae => self.ldx(Mode::Absolute),
-            0xbe => self.ldx(Mode::AbsoluteY),
-            0xa6 => self.ldx(Mode::ZeroPage),
-            0xb6 => self.ldx(Mode::ZeroPageY),
-
-            0xa0 => self.ldy(Mode::Immediate),
-            0xac => self.ldy(Mode::Absolute),
-            0xbc => self.ldy(Mode::AbsoluteX),
-            0xa4 => self.ldy(Mode::ZeroPage),
-            0xb4 => self.ldy(Mode::ZeroPageX),
-
-            // register stores
-            0x8d => self.sta(Mode::Absolute),
-            0x9d => self.sta(Mode::AbsoluteXForceClock),
-            0x99 => self.sta(Mode::AbsoluteYForceClock),
-            0x85 => self.sta(Mode::ZeroPage),
-            0x95 => self.sta(Mode::ZeroPageX),
-            0x81 => self.sta(Mode::IndirectX),
-            0x91 => self.sta(Mode::IndirectYForceClock),
+    // runs the cycle for the current step of the instruction and returns true when it's finished
+    fn execute_instruction(&mut self, opcode: u8) -> Result<bool, Error> {
+        let Opcode { mnemonic, mode, .. } = OPCODES[opcode as usize];
 
-            0x8e => self.stx(Mode::Absolute),
-            0x86 => self.stx(Mode::ZeroPage),
-            0x96 => self.stx(Mode::ZeroPageY),
-
-            0x8c => self.sty(Mode::Absolute),
-            0x84 => self.sty(Mode::ZeroPage),
-            0x94 => self.sty(Mode::ZeroPageX),
+        let finished = match mnemonic {
+            // register loads and stores
+            Mnemonic::Lda => self.read_op(mode, Self::lda),
+            Mnemonic::Ldx => self.read_op(mode, Self::ldx),
+            Mnemonic::Ldy => self.read_op(mode, Self::ldy),
+            Mnemonic::Sta => self.write_op(mode, Self::sta),
+            Mnemonic::Stx => self.write_op(mode, Self::stx),
+            Mnemonic::Sty => self.write_op(mode, Self::sty),
 
             // register transfers
-            0xaa => self.tax(),
-            0xa8 => self.tay(),
-            0xba => self.tsx(),
-            0x8a => self.txa(),
-            0x9a => self.txs(),
-            0x98 => self.tya(),
+            Mnemonic::Tax => self.implied_op(Self::tax),
+            Mnemonic::Tay => self.implied_op(Self::tay),
+            Mnemonic::Tsx => self.implied_op(Self::tsx),
+            Mnemonic::Txa => self.implied_op(Self::txa),
+            Mnemonic::Txs => self.implied_op(Self::txs),
+            Mnemonic::Tya => self.implied_op(Self::tya),
 
             // stack
-            0x48 => self.pha(),
-            0x08 => self.php(),
-            0x68 => self.pla(),
-            0x28 => self.plp(),
+            Mnemonic::Pha => self.pha(),
+            Mnemonic::Php => self.php(),
+            Mnemonic::Pla => self.pla(),
+            Mnemonic::Plp => self.plp(),
 
             // shift and rotate
-            0x0a => self.asl_a(),
-            0x0e => self.asl(Mode::Absolute),
-            0x1e => self.asl(Mode::AbsoluteXForceClock),
-            0x06 => self.asl(Mode::ZeroPage),
-            0x16 => self.asl(Mode::ZeroPageX),
-
-            0x4a => self.lsr_a(),
-            0x4e => self.lsr(Mode::Absolute),
-            0x5e => self.lsr(Mode::AbsoluteXForceClock),
-            0x46 => self.lsr(Mode::ZeroPage),
-            0x56 => self.lsr(Mode::ZeroPageX),
-
-            0x2a => self.rol_a(),
-            0x2e => self.rol(Mode::Absolute),
-            0x3e => self.rol(Mode::AbsoluteXForceClock),
-            0x26 => self.rol(Mode::ZeroPage),
-            0x36 => self.rol(Mode::ZeroPageX),
-
-            0x6a => self.ror_a(),
-            0x6e => self.ror(Mode::Absolute),
-            0x7e => self.ror(Mode::AbsoluteXForceClock),
-            0x66 => self.ror(Mode::ZeroPage),
-            0x76 => self.ror(Mode::ZeroPageX),
+            Mnemonic::Asl if mode == AddressingMode::Accumulator => self.implied_op(Self::asl_a),
+            Mnemonic::Asl => self.read_modify_write_op(mode, Self::asl),
+            Mnemonic::Lsr if mode == AddressingMode::Accumulator => self.implied_op(Self::lsr_a),
+            Mnemonic::Lsr => self.read_modify_write_op(mode, Self::lsr),
+            Mnemonic::Rol if mode == AddressingMode::Accumulator => self.implied_op(Self::rol_a),
+            Mnemonic::Rol => self.read_modify_write_op(mode, Self::rol),
+            Mnemonic::Ror if mode == AddressingMode::Accumulator => self.implied_op(Self::ror_a),
+            Mnemonic::Ror => self.read_modify_write_op(mode, Self::ror),
 
             // logic
-            0x29 => self.and(Mode::Immediate),
-            0x2d => self.and(Mode::Absolute),
-            0x3d => self.and(Mode::AbsoluteX),
-            0x39 => self.and(Mode::AbsoluteY),
-            0x25 => self.and(Mode::ZeroPage),
-            0x35 => self.and(Mode::ZeroPageX),
-            0x21 => self.and(Mode::IndirectX),
-            0x31 => self.and(Mode::IndirectY),
-
-            0x2c => self.bit(Mode::Absolute),
-            0x24 => self.bit(Mode::ZeroPage),
-
-            0x49 => self.eor(Mode::Immediate),
-            0x4d => self.eor(Mode::Absolute),
-            0x5d => self.eor(Mode::AbsoluteX),
-            0x59 => self.eor(Mode::AbsoluteY),
-            0x45 => self.eor(Mode::ZeroPage),
-            0x55 => self.eor(Mode::ZeroPageX),
-            0x41 => self.eor(Mode::IndirectX),
-            0x51 => self.eor(Mode::IndirectY),
-
-            0x09 => self.ora(Mode::Immediate),
-            0x0d => self.ora(Mode::Absolute),
-            0x1d => self.ora(Mode::AbsoluteX),
-            0x19 => self.ora(Mode::AbsoluteY),
-            0x05 => self.ora(Mode::ZeroPage),
-            0x15 => self.ora(Mode::ZeroPageX),
-            0x01 => self.ora(Mode::IndirectX),
-            0x11 => self.ora(Mode::IndirectY),
+            Mnemonic::And => self.read_op(mode, Self::and),
+            Mnemonic::Bit => self.read_op(mode, Self::bit),
+            Mnemonic::Eor => self.read_op(mode, Self::eor),
+            Mnemonic::Ora => self.read_op(mode, Self::ora),
 
             // arithmetic
-            0x69 => self.adc(Mode::Immediate),
-            0x6d => self.adc(Mode::Absolute),
-            0x7d => self.adc(Mode::AbsoluteX),
-            0x79 => self.adc(Mode::AbsoluteY),
-            0x65 => self.adc(Mode::ZeroPage),
-            0x75 => self.adc(Mode::ZeroPageX),
-            0x61 => self.adc(Mode::IndirectX),
-            0x71 => self.adc(Mode::IndirectY),
-
-            0xe9 => self.sbc(Mode::Immediate),
-            0xed => self.sbc(Mode::Absolute),
-            0xfd => self.sbc(Mode::AbsoluteX),
-            0xf9 => self.sbc(Mode::AbsoluteY),
-            0xe5 => self.sbc(Mode::ZeroPage),
-            0xf5 => self.sbc(Mode::ZeroPageX),
-            0xe1 => self.sbc(Mode::IndirectX),
-            0xf1 => self.sbc(Mode::IndirectY),
-
-            0xc9 => self.cmp(Mode::Immediate),
-            0xcd => self.cmp(Mode::Absolute),
-            0xdd => self.cmp(Mode::AbsoluteX),
-            0xd9 => self.cmp(Mode::AbsoluteY),
-            0xc5 => self.cmp(Mode::ZeroPage),
-            0xd5 => self.cmp(Mode::ZeroPageX),
-            0xc1 => self.cmp(Mode::IndirectX),
-            0xd1 => self.cmp(Mode::IndirectY),
-
-            0xe0 => self.cpx(Mode::Immediate),
-            0xec => self.cpx(Mode::Absolute),
-            0xe4 => self.cpx(Mode::ZeroPage),
-
-            0xc0 => self.cpy(Mode::Immediate),
-            0xcc => self.cpy(Mode::Absolute),
-            0xc4 => self.cpy(Mode::ZeroPage),
-
-            // increment
-            0xee => self.inc(Mode::Absolute),
-            0xfe => self.inc(Mode::AbsoluteXForceClock),
-            0xe6 => self.inc(Mode::ZeroPage),
-            0xf6 => self.inc(Mode::ZeroPageX),
-
-            0xe8 => self.inx(),
-            0xc8 => self.iny(),
-
-            // decrement
-            0xce => self.dec(Mode::Absolute),
-            0xde => self.dec(Mode::AbsoluteXForceClock),
-            0xc6 => self.dec(Mode::ZeroPage),
-            0xd6 => self.dec(Mode::ZeroPageX),
-
-            0xca => self.dex(),
-            0x88 => self.dey(),
+            Mnemonic::Adc => self.read_op(mode, Self::adc),
+            Mnemonic::Sbc => self.read_op(mode, Self::sbc),
+            Mnemonic::Cmp => self.read_op(mode, Self::cmp),
+            Mnemonic::Cpx => self.read_op(mode, Self::cpx),
+            Mnemonic::Cpy => self.read_op(mode, Self::cpy),
+
+            // increment and decrement
+            Mnemonic::Inc => self.read_modify_write_op(mode, Self::inc),
+            Mnemonic::Inx => self.implied_op(Self::inx),
+            Mnemonic::Iny => self.implied_op(Self::iny),
+            Mnemonic::Dec => self.read_modify_write_op(mode, Self::dec),
+            Mnemonic::Dex => self.implied_op(Self::dex),
+            Mnemonic::Dey => self.implied_op(Self::dey),
 
             // controls
-            0x4c => self.jmp(Mode::Absolute),
-            0x6c => self.jmp(Mode::Indirect),
-
-            0x00 => self.brk(),
-            0x20 => self.jsr(Mode::Absolute),
-            0x40 => self.rti(),
-            0x60 => self.rts(),
+            Mnemonic::Jmp if mode == AddressingMode::Indirect => self.jmp_indirect(),
+            Mnemonic::Jmp => self.jmp(),
+            Mnemonic::Brk => self.brk(),
+            Mnemonic::Jsr => self.jsr(),
+            Mnemonic::Rti => self.rti(),
+            Mnemonic::Rts => self.rts(),
 
             // branches
-            0x90 => self.bcc(),
-            0xb0 => self.bcs(),
-            0xf0 => self.beq(),
-            0x30 => self.bmi(),
-            0xd0 => self.bne(),
-            0x10 => self.bpl(),
-            0x50 => self.bvc(),
-            0x70 => self.bvs(),
+            Mnemonic::Bcc => self.bcc(),
+            Mnemonic::Bcs => self.bcs(),
+            Mnemonic::Beq => self.beq(),
+            Mnemonic::Bmi => self.bmi(),
+            Mnemonic::Bne => self.bne(),
+            Mnemonic::Bpl => self.bpl(),
+            Mnemonic::Bvc => self.bvc(),
+            Mnemonic::Bvs => self.bvs(),
 
             // flags
-            0x18 => self.clc(),
-            0xd8 => self.cld(),
-            0x58 => self.cli(),
-            0xb8 => self.clv(),
-            0x38 => self.sec(),
-            0xf8 => self.sed(),
-            0x78 => self.sei(),
-
-            // does nothing
-            0xea => self.nop(),
-
-            // undocumented opcodes
-
-            // register loads and stores
-            0xbb => self.las(Mode::AbsoluteY),
-
-            0xa7 => self.lax(Mode::ZeroPage),
-            0xb7 => self.lax(Mode::ZeroPageY),
-            0xa3 => self.lax(Mode::IndirectX),
-            0xb3 => self.lax(Mode::IndirectY),
-            0xaf => self.lax(Mode::Absolute),
-            0xbf => self.lax(Mode::AbsoluteY),
-
-            0x8f => self.sax(Mode::Absolute),
-            0x87 => self.sax(Mode::ZeroPage),
-            0x97 => self.sax(Mode::ZeroPageX),
-            0x83 => self.sax(Mode::IndirectX),
-
-            // register loads
-            _ => panic!(
-                "Instruction with opcode '0x{:x}' not supported! PC: {}",
-                opcode, self.pc
-            ),
-        }
+            Mnemonic::Clc => self.implied_op(Self::clc),
+            Mnemonic::Cld => self.implied_op(Self::cld),
+            Mnemonic::Cli => self.implied_op(Self::cli),
+            Mnemonic::Clv => self.implied_op(Self::clv),
+            Mnemonic::Sec => self.implied_op(Self::sec),
+            Mnemonic::Sed => self.implied_op(Self::sed),
+            Mnemonic::Sei => self.implied_op(Self::sei),
+
+            // unofficial nops still read their operand
+            Mnemonic::Nop if mode == AddressingMode::Implied => self.implied_op(Self::nop),
+            Mnemonic::Nop => self.read_op(mode, Self::nop_read),
+
+            // undocumented register loads and stores
+            Mnemonic::Las => self.read_op(mode, Self::las),
+            Mnemonic::Lax => self.read_op(mode, Self::lax),
+            Mnemonic::Sax => self.write_op(mode, Self::sax),
+            Mnemonic::Sha => self.write_op(mode, Self::sha),
+            Mnemonic::Shx => self.write_op(mode, Self::shx),
+            Mnemonic::Shy => self.write_op(mode, Self::shy),
+            Mnemonic::Tas => self.write_op(mode, Self::tas),
+
+            // read-modify-write combined with an operation
+            Mnemonic::Slo => self.read_modify_write_op(mode, Self::slo),
+            Mnemonic::Rla => self.read_modify_write_op(mode, Self::rla),
+            Mnemonic::Sre => self.read_modify_write_op(mode, Self::sre),
+            Mnemonic::Rra => self.read_modify_write_op(mode, Self::rra),
+            Mnemonic::Dcp => self.read_modify_write_op(mode, Self::dcp),
+            Mnemonic::Isb => self.read_modify_write_op(mode, Self::isc),
+
+            // immediate logic and arithmetic
+            Mnemonic::Anc => self.read_op(mode, Self::anc),
+            Mnemonic::Alr => self.read_op(mode, Self::alr),
+            Mnemonic::Arr => self.read_op(mode, Self::arr),
+            Mnemonic::Axs => self.read_op(mode, Self::axs),
+            Mnemonic::Xaa => self.read_op(mode, Self::xaa),
+            Mnemonic::Lxa => self.read_op(mode, Self::lxa),
+
+            // locks up the cpu until reset
+            Mnemonic::Jam => return Err(self.jam(opcode)),
+        };
+
+        Ok(finished)
     }
 
     // begin instructions!
 
-    fn lda(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn lda(&mut self, data: u8) {
         self.set_flag_zero_negative(data);
         self.a = data;
     }
 
-    fn ldx(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn ldx(&mut self, data: u8) {
         self.set_flag_zero_negative(data);
         self.x = data;
     }
 
-    fn ldy(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn ldy(&mut self, data: u8) {
         self.set_flag_zero_negative(data);
         self.y = data;
     }
 
-    fn sta(&mut self, mode: Mode) {
-        let address = self.read_operand_address(mode);
-        self.bus.write_byte(address, self.a);
+    fn sta(&mut self) -> u8 {
+        self.a
     }
 
-    fn stx(&mut self, mode: Mode) {
-        let address = self.read_operand_address(mode);
-        self.bus.write_byte(address, self.x);
+    fn stx(&mut self) -> u8 {
+        self.x
     }
 
-    fn sty(&mut self, mode: Mode) {
-        let address = self.read_operand_address(mode);
-        self.bus.write_byte(address, self.y);
+    fn sty(&mut self) -> u8 {
+        self.y
     }
 
     fn tax(&mut self) {
         self.x = self.a;
         self.set_flag_zero_negative(self.x);
-        self.bus.clock();
     }
 
     fn tay(&mut self) {
         self.y = self.a;
         self.set_flag_zero_negative(self.y);
-        self.bus.clock();
     }
 
     fn tsx(&mut self) {
         self.x = self.sp;
         self.set_flag_zero_negative(self.x);
-        self.bus.clock();
     }
 
     fn txa(&mut self) {
         self.a = self.x;
         self.set_flag_zero_negative(self.a);
-        self.bus.clock();
     }
 
     fn txs(&mut self) {
         self.sp = self.x;
-        self.set_flag_zero_negative(self.sp);
-        self.bus.clock();
     }
 
     fn tya(&mut self) {
         self.a = self.y;
         self.set_flag_zero_negative(self.a);
-        self.bus.clock();
     }
 
-    fn pha(&mut self) {
-        self.push_byte(self.a);
+    fn pha(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.bus.read(self.pc);
+                false
+            }
+            _ => {
+                self.push_byte(self.a);
+                true
+            }
+        }
     }
 
-    fn php(&mut self) {
-        self.push_byte(self.flags);
+    fn php(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.bus.read(self.pc);
+                false
+            }
+            _ => {
+                self.push_byte(self.flags | Flag::Break as u8 | Flag::Unused as u8);
+                true
+            }
+        }
     }
 
-    fn pla(&mut self) {
-        self.a = self.pop_byte();
-        self.set_flag_zero_negative(self.a);
+    fn pla(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.bus.read(self.pc);
+                false
+            }
+            2 => {
+                self.read_stack();
+                false
+            }
+            _ => {
+                self.a = self.pop_byte();
+                self.set_flag_zero_negative(self.a);
+                true
+            }
+        }
     }
 
-    fn plp(&mut self) {
-        self.flags = self.pop_byte();
+    fn plp(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.bus.read(self.pc);
+                false
+            }
+            2 => {
+                self.read_stack();
+                false
+            }
+            _ => {
+                self.pop_flags();
+                true
+            }
+        }
     }
 
-    fn do_asl(&mut self, data: u8) -> u8 {
+    fn asl(&mut self, data: u8) -> u8 {
         let result = data << 1;
         self.set_flag(Flag::Carry, data & 0x80 != 0);
         self.set_flag_zero_negative(result);
-        self.bus.clock();
         result
     }
 
-    fn asl(&mut self, mode: Mode) {
-        let (data, address) = self.read_operand(mode);
-        let result = self.do_asl(data);
-        self.bus.write_byte(address, result);
-    }
-
     fn asl_a(&mut self) {
-        self.a = self.do_asl(self.a);
+        self.a = self.asl(self.a);
     }
 
-    fn do_lsr(&mut self, data: u8) -> u8 {
+    fn lsr(&mut self, data: u8) -> u8 {
         let result = data >> 1;
         self.set_flag(Flag::Carry, data & 0x01 != 0);
         self.set_flag_zero_negative(result);
-        self.bus.clock();
         result
     }
 
-    fn lsr(&mut self, mode: Mode) {
-        let (data, address) = self.read_operand(mode);
-        let result = self.do_lsr(data);
-        self.bus.write_byte(address, result);
-    }
-
     fn lsr_a(&mut self) {
-        self.a = self.do_lsr(self.a);
+        self.a = self.lsr(self.a);
     }
 
-    fn do_rol(&mut self, data: u8) -> u8 {
+    fn rol(&mut self, data: u8) -> u8 {
         let result = (data << 1) | self.get_flag(Flag::Carry) as u8;
         self.set_flag(Flag::Carry, data & 0x80 != 0);
         self.set_flag_zero_negative(result);
-        self.bus.clock();
         result
     }
 
-    fn rol(&mut self, mode: Mode) {
-        let (data, address) = self.read_operand(mode);
-        let result = self.do_rol(data);
-        self.bus.write_byte(address, result);
-    }
-
     fn rol_a(&mut self) {
-        self.a = self.do_rol(self.a);
+        self.a = self.rol(self.a);
     }
 
-    fn do_ror(&mut self, data: u8) -> u8 {
+    fn ror(&mut self, data: u8) -> u8 {
         let result = (data >> 1) | (self.get_flag(Flag::Carry) as u8) << 7;
         self.set_flag(Flag::Carry, data & 0x01 != 0);
         self.set_flag_zero_negative(result);
-        self.bus.clock();
         result
     }
 
-    fn ror(&mut self, mode: Mode) {
-        let (data, address) = self.read_operand(mode);
-        let result = self.do_ror(data);
-        self.bus.write_byte(address, result);
-    }
-
     fn ror_a(&mut self) {
-        self.a = self.do_ror(self.a);
+        self.a = self.ror(self.a);
     }
 
-    fn and(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn and(&mut self, data: u8) {
         self.a &= data;
         self.set_flag_zero_negative(self.a);
     }
 
-    fn bit(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn bit(&mut self, data: u8) {
         let result = self.a & data;
 
         self.set_flag(Flag::Zero, result == 0);
@@ -634,14 +953,12 @@ impl CPU {
         self.set_flag(Flag::Negative, data & (1 << 7) != 0);
     }
 
-    fn eor(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn eor(&mut self, data: u8) {
         self.a ^= data;
         self.set_flag_zero_negative(self.a);
     }
 
-    fn ora(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn ora(&mut self, data: u8) {
         self.a |= data;
         self.set_flag_zero_negative(self.a);
     }
@@ -660,206 +977,381 @@ impl CPU {
         self.a = result;
     }
 
-    fn adc(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
-        self.do_adc(data);
+    // bcd versions of adc and sbc which set the flags the same way as the nmos 6502
+    fn do_adc_decimal(&mut self, data: u8) {
+        let carry = self.get_flag(Flag::Carry) as u8;
+        let binary_result = self.a.wrapping_add(data).wrapping_add(carry);
+
+        let mut low = (self.a & 0x0f) + (data & 0x0f) + carry;
+        let mut high = (self.a >> 4) as u16 + (data >> 4) as u16;
+        if low > 0x09 {
+            low += 0x06;
+        }
+        if low > 0x0f {
+            high += 1;
+        }
+
+        // negative and overflow come from the high nibble before it gets adjusted
+        let unadjusted = (high << 4) as u8;
+        self.set_flag(Flag::Zero, binary_result == 0);
+        self.set_flag(Flag::Negative, unadjusted & 0x80 != 0);
+        self.set_flag(
+            Flag::Overflow,
+            (!(self.a ^ data) & (self.a ^ unadjusted)) & 0x80 != 0,
+        );
+
+        if high > 0x09 {
+            high += 0x06;
+        }
+        self.set_flag(Flag::Carry, high > 0x0f);
+        self.a = (high << 4) as u8 | (low & 0x0f);
     }
 
-    fn sbc(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn do_sbc_decimal(&mut self, data: u8) {
+        let borrow = !self.get_flag(Flag::Carry) as i16;
+
+        let mut low = (self.a & 0x0f) as i16 - (data & 0x0f) as i16 - borrow;
+        let mut high = (self.a >> 4) as i16 - (data >> 4) as i16;
+        if low & 0x10 != 0 {
+            low -= 0x06;
+            high -= 1;
+        }
+        if high & 0x10 != 0 {
+            high -= 0x06;
+        }
 
-        // invert data and use the same code as adc
+        // all the flags are the same as binary mode
+        let result = ((high << 4) as u8) | (low as u8 & 0x0f);
         self.do_adc(!data);
+        self.a = result;
     }
 
-    fn cmp(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
-        let result = self.a - data;
+    fn adc(&mut self, data: u8) {
+        if self.decimal_mode_enabled && self.get_flag(Flag::DecimalMode) {
+            self.do_adc_decimal(data);
+        } else {
+            self.do_adc(data);
+        }
+    }
 
-        self.set_flag(Flag::Carry, self.a >= result);
-        self.set_flag_zero_negative(result);
+    fn sbc(&mut self, data: u8) {
+        if self.decimal_mode_enabled && self.get_flag(Flag::DecimalMode) {
+            self.do_sbc_decimal(data);
+        } else {
+            // invert data and use the same code as adc
+            self.do_adc(!data);
+        }
     }
 
-    fn cpx(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
-        let result = self.x - data;
+    fn do_compare(&mut self, register: u8, data: u8) {
+        let result = register.wrapping_sub(data);
 
-        self.set_flag(Flag::Carry, self.x >= result);
+        self.set_flag(Flag::Carry, register >= data);
         self.set_flag_zero_negative(result);
     }
 
-    fn cpy(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
-        let result = self.y - data;
+    fn cmp(&mut self, data: u8) {
+        self.do_compare(self.a, data);
+    }
 
-        self.set_flag(Flag::Carry, self.y >= result);
-        self.set_flag_zero_negative(result);
+    fn cpx(&mut self, data: u8) {
+        self.do_compare(self.x, data);
     }
 
-    fn inc(&mut self, mode: Mode) {
-        let (data, address) = self.read_operand(mode);
-        let result = data + 1;
+    fn cpy(&mut self, data: u8) {
+        self.do_compare(self.y, data);
+    }
 
+    fn inc(&mut self, data: u8) -> u8 {
+        let result = data.wrapping_add(1);
         self.set_flag_zero_negative(result);
-        self.bus.clock();
-        self.bus.write_byte(address, result);
+        result
     }
 
     fn inx(&mut self) {
-        self.x += 1;
+        self.x = self.x.wrapping_add(1);
         self.set_flag_zero_negative(self.x);
-        self.bus.clock();
     }
 
     fn iny(&mut self) {
-        self.y += 1;
+        self.y = self.y.wrapping_add(1);
         self.set_flag_zero_negative(self.y);
-        self.bus.clock();
     }
 
-    fn dec(&mut self, mode: Mode) {
-        let (data, address) = self.read_operand(mode);
-        let result = data - 1;
-
+    fn dec(&mut self, data: u8) -> u8 {
+        let result = data.wrapping_sub(1);
         self.set_flag_zero_negative(result);
-        self.bus.clock();
-        self.bus.write_byte(address, result);
+        result
     }
 
     fn dex(&mut self) {
-        self.x -= 1;
+        self.x = self.x.wrapping_sub(1);
         self.set_flag_zero_negative(self.x);
-        self.bus.clock();
     }
 
     fn dey(&mut self) {
-        self.y -= 1;
+        self.y = self.y.wrapping_sub(1);
         self.set_flag_zero_negative(self.y);
-        self.bus.clock();
     }
 
-    fn jmp(&mut self, mode: Mode) {
-        let address = self.read_operand_address(mode);
-        self.pc = address;
+    fn jmp(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.address = self.fetch_byte() as u16;
+                false
+            }
+            _ => {
+                self.pc = self.address | (self.bus.read(self.pc) as u16) << 8;
+                true
+            }
+        }
     }
 
-    fn brk(&mut self) {
-        self.push_word(self.pc);
-
-        let flags = self.flags | Flag::Break as u8;
-        self.push_byte(flags);
-        self.bus.clock();
-
-        self.pc = self.bus.read_word(0xfffe);
+    fn jmp_indirect(&mut self) -> bool {
+        match self.step {
+            1 | 2 => self.resolve_address(AddressingMode::Absolute, false),
+            3 => {
+                self.data = self.bus.read(self.address);
+                false
+            }
+            _ => {
+                // emulate page boundary crossing bug
+                let high_address = self.address & 0xff00 | self.address.wrapping_add(1) & 0x00ff;
+                self.pc = self.data as u16 | (self.bus.read(high_address) as u16) << 8;
+                true
+            }
+        }
     }
 
-    fn jsr(&mut self, mode: Mode) {
-        let address = self.read_operand_address(mode);
-        let return_address = self.pc - 1;
-
-        self.push_word(return_address);
-        self.pc = address;
+    // interrupts use the same sequence as brk but without moving past the next byte or setting
+    // the break flag
+    fn brk(&mut self) -> bool {
+        match self.step {
+            1 => {
+                if self.in_interrupt {
+                    self.bus.read(self.pc);
+                } else {
+                    // the byte after brk gets skipped over
+                    self.fetch_byte();
+                }
+                false
+            }
+            2 => {
+                self.push_byte((self.pc >> 8) as u8);
+                false
+            }
+            3 => {
+                self.push_byte(self.pc as u8);
+                false
+            }
+            4 => {
+                let flags = if self.in_interrupt {
+                    self.flags & !(Flag::Break as u8) | Flag::Unused as u8
+                } else {
+                    self.flags | Flag::Break as u8 | Flag::Unused as u8
+                };
+                self.push_byte(flags);
+                self.set_flag(Flag::InterruptDisable, true);
+
+                // an nmi that shows up by now takes over the vector even for brk and irqs
+                self.address = if self.need_nmi {
+                    self.need_nmi = false;
+                    0xfffa
+                } else {
+                    0xfffe
+                };
+                false
+            }
+            5 => {
+                self.data = self.bus.read(self.address);
+                false
+            }
+            _ => {
+                self.pc = self.data as u16 | (self.bus.read(self.address + 1) as u16) << 8;
+                true
+            }
+        }
     }
 
-    fn rti(&mut self) {
-        self.flags = self.pop_byte();
-        self.pc = self.pop_word();
+    fn jsr(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.address = self.fetch_byte() as u16;
+                false
+            }
+            2 => {
+                self.read_stack();
+                false
+            }
+            // the return address pushed is the last byte of the jsr instruction
+            3 => {
+                self.push_byte((self.pc >> 8) as u8);
+                false
+            }
+            4 => {
+                self.push_byte(self.pc as u8);
+                false
+            }
+            _ => {
+                self.pc = self.address | (self.bus.read(self.pc) as u16) << 8;
+                true
+            }
+        }
     }
 
-    fn rts(&mut self) {
-        self.pc = self.pop_word();
-        self.pc += 1;
+    fn rti(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.bus.read(self.pc);
+                false
+            }
+            2 => {
+                self.read_stack();
+                false
+            }
+            3 => {
+                self.pop_flags();
+                false
+            }
+            4 => {
+                self.address = self.pop_byte() as u16;
+                false
+            }
+            _ => {
+                self.pc = self.address | (self.pop_byte() as u16) << 8;
+                true
+            }
+        }
     }
 
-    fn branch(&mut self, condition: bool) {
-        if condition {
-            self.bus.clock();
-
-            // interpret address as signed
-            let offset = self.read_operand(Mode::Immediate).0 as i8 as i16;
-            let address = (self.pc as i16 + offset) as u16;
+    fn rts(&mut self) -> bool {
+        match self.step {
+            1 => {
+                self.bus.read(self.pc);
+                false
+            }
+            2 => {
+                self.read_stack();
+                false
+            }
+            3 => {
+                self.address = self.pop_byte() as u16;
+                false
+            }
+            4 => {
+                self.pc = self.address | (self.pop_byte() as u16) << 8;
+                false
+            }
+            _ => {
+                self.fetch_byte();
+                true
+            }
+        }
+    }
 
-            if address & 0xff00 != self.pc & 0xff00 {
-                self.bus.clock()
+    fn branch(&mut self, condition: bool) -> bool {
+        match self.step {
+            1 => {
+                self.data = self.fetch_byte();
+                !condition
             }
+            2 => {
+                self.bus.read(self.pc);
+
+                // interpret offset as signed
+                let address = self.pc.wrapping_add(self.data as i8 as u16);
+                let page_crossed = address & 0xff00 != self.pc & 0xff00;
+
+                // a taken branch that stays on the same page doesn't poll for interrupts on its
+                // last cycle so one that just showed up waits for another instruction, an nmi was
+                // an edge that's already gone so it has to be held onto instead of dropped
+                if !page_crossed {
+                    if self.run_irq && !self.prev_run_irq {
+                        self.run_irq = false;
+                    }
+                    if self.need_nmi && !self.prev_need_nmi {
+                        self.need_nmi = false;
+                        self.delayed_nmi = true;
+                    }
+                }
 
-            self.pc = address;
+                // only the low byte gets added at first and the high byte is fixed on the next
+                // cycle if needed
+                self.pc = self.pc & 0xff00 | address & 0x00ff;
+                self.address = address;
+                !page_crossed
+            }
+            _ => {
+                self.bus.read(self.pc);
+                self.pc = self.address;
+                true
+            }
         }
     }
 
-    fn bcc(&mut self) {
-        self.branch(!self.get_flag(Flag::Carry));
+    fn bcc(&mut self) -> bool {
+        self.branch(!self.get_flag(Flag::Carry))
     }
 
-    fn bcs(&mut self) {
-        self.branch(self.get_flag(Flag::Carry));
+    fn bcs(&mut self) -> bool {
+        self.branch(self.get_flag(Flag::Carry))
     }
 
-    fn beq(&mut self) {
-        self.branch(self.get_flag(Flag::Zero));
+    fn beq(&mut self) -> bool {
+        self.branch(self.get_flag(Flag::Zero))
     }
 
-    fn bmi(&mut self) {
-        self.branch(self.get_flag(Flag::Negative));
+    fn bmi(&mut self) -> bool {
+        self.branch(self.get_flag(Flag::Negative))
     }
 
-    fn bne(&mut self) {
-        self.branch(!self.get_flag(Flag::Zero));
+    fn bne(&mut self) -> bool {
+        self.branch(!self.get_flag(Flag::Zero))
     }
 
-    fn bpl(&mut self) {
-        self.branch(!self.get_flag(Flag::Negative));
+    fn bpl(&mut self) -> bool {
+        self.branch(!self.get_flag(Flag::Negative))
     }
 
-    fn bvc(&mut self) {
-        self.branch(!self.get_flag(Flag::Overflow));
+    fn bvc(&mut self) -> bool {
+        self.branch(!self.get_flag(Flag::Overflow))
     }
 
-    fn bvs(&mut self) {
-        self.branch(self.get_flag(Flag::Overflow));
+    fn bvs(&mut self) -> bool {
+        self.branch(self.get_flag(Flag::Overflow))
     }
 
     fn clc(&mut self) {
         self.set_flag(Flag::Carry, false);
-        self.bus.clock();
     }
 
     fn cld(&mut self) {
         self.set_flag(Flag::DecimalMode, false);
-        self.bus.clock();
     }
 
     fn cli(&mut self) {
         self.set_flag(Flag::InterruptDisable, false);
-        self.bus.clock();
     }
 
     fn clv(&mut self) {
         self.set_flag(Flag::Overflow, false);
-        self.bus.clock();
     }
 
     fn sec(&mut self) {
         self.set_flag(Flag::Carry, true);
-        self.bus.clock();
     }
 
     fn sed(&mut self) {
         self.set_flag(Flag::DecimalMode, true);
-        self.bus.clock();
     }
 
     fn sei(&mut self) {
         self.set_flag(Flag::InterruptDisable, true);
-        self.bus.clock();
     }
 
-    fn nop(&mut self) {
-        self.bus.clock();
-    }
+    fn nop(&mut self) {}
 
-    fn las(&mut self, mode: Mode) {
-        let data = self.read_operand(mode).0;
+    fn las(&mut self, data: u8) {
         let result = data & self.sp;
         self.a = result;
         self.x = result;
@@ -867,14 +1359,136 @@ impl CPU {
         self.set_flag_zero_negative(result);
     }
 
-    fn lax(&mut self, mode: Mode) {
-        self.lda(mode);
+    fn lax(&mut self, data: u8) {
+        self.lda(data);
         self.x = self.a;
     }
 
-    fn sax(&mut self, mode: Mode) {
-        let address = self.read_operand_address(mode);
-        let result = self.a & self.x;
-        self.bus.write_byte(address, result);
+    fn sax(&mut self) -> u8 {
+        self.a & self.x
+    }
+
+    // the high byte of the target address plus one gets anded into the stored value and when
+    // indexing crosses a page the value also replaces the high byte of the address
+    fn do_unstable_store(&mut self, index: u8, value: u8) -> u8 {
+        let base_address = self.address.wrapping_sub(index as u16);
+        let result = value & ((base_address >> 8) as u8).wrapping_add(1);
+
+        if base_address & 0xff00 != self.address & 0xff00 {
+            self.address = (result as u16) << 8 | self.address & 0x00ff;
+        }
+
+        result
+    }
+
+    fn sha(&mut self) -> u8 {
+        self.do_unstable_store(self.y, self.a & self.x)
+    }
+
+    fn shx(&mut self) -> u8 {
+        self.do_unstable_store(self.y, self.x)
+    }
+
+    fn shy(&mut self) -> u8 {
+        self.do_unstable_store(self.x, self.y)
+    }
+
+    fn tas(&mut self) -> u8 {
+        self.sp = self.a & self.x;
+        self.do_unstable_store(self.y, self.sp)
+    }
+
+    fn slo(&mut self, data: u8) -> u8 {
+        let result = self.asl(data);
+        self.a |= result;
+        self.set_flag_zero_negative(self.a);
+        result
+    }
+
+    fn rla(&mut self, data: u8) -> u8 {
+        let result = self.rol(data);
+        self.a &= result;
+        self.set_flag_zero_negative(self.a);
+        result
+    }
+
+    fn sre(&mut self, data: u8) -> u8 {
+        let result = self.lsr(data);
+        self.a ^= result;
+        self.set_flag_zero_negative(self.a);
+        result
+    }
+
+    fn rra(&mut self, data: u8) -> u8 {
+        let result = self.ror(data);
+        self.adc(result);
+        result
+    }
+
+    fn dcp(&mut self, data: u8) -> u8 {
+        let result = data.wrapping_sub(1);
+        self.do_compare(self.a, result);
+        result
+    }
+
+    fn isc(&mut self, data: u8) -> u8 {
+        let result = data.wrapping_add(1);
+        self.sbc(result);
+        result
+    }
+
+    fn anc(&mut self, data: u8) {
+        self.and(data);
+        self.set_flag(Flag::Carry, self.get_flag(Flag::Negative));
+    }
+
+    fn alr(&mut self, data: u8) {
+        let result = (self.a & data) >> 1;
+
+        self.set_flag(Flag::Carry, self.a & data & 0x01 != 0);
+        self.set_flag_zero_negative(result);
+        self.a = result;
+    }
+
+    fn arr(&mut self, data: u8) {
+        let result = (self.a & data) >> 1 | (self.get_flag(Flag::Carry) as u8) << 7;
+
+        // carry and overflow come from the adder in the middle of the operation
+        self.set_flag_zero_negative(result);
+        self.set_flag(Flag::Carry, result & 0x40 != 0);
+        self.set_flag(Flag::Overflow, (result >> 6 ^ result >> 5) & 0x01 != 0);
+        self.a = result;
+    }
+
+    fn axs(&mut self, data: u8) {
+        let value = self.a & self.x;
+
+        self.x = value.wrapping_sub(data);
+        self.set_flag(Flag::Carry, value >= data);
+        self.set_flag_zero_negative(self.x);
+    }
+
+    // the magic constant really depends on analog effects that vary between chips and temperature
+    fn xaa(&mut self, data: u8) {
+        self.a = (self.a | UNSTABLE_MAGIC) & self.x & data;
+        self.set_flag_zero_negative(self.a);
+    }
+
+    fn lxa(&mut self, data: u8) {
+        let result = (self.a | UNSTABLE_MAGIC) & data;
+        self.a = result;
+        self.x = result;
+        self.set_flag_zero_negative(result);
+    }
+
+    fn nop_read(&mut self, _data: u8) {}
+
+    fn jam(&mut self, opcode: u8) -> Error {
+        self.jammed = Some(opcode);
+        self.pc = self.pc.wrapping_sub(1);
+        Error::CpuJam {
+            opcode,
+            pc: self.pc,
+        }
     }
 }
@@ -0,0 +1,272 @@
+use std::collections::BTreeSet;
+
+use crate::opcodes::{Mnemonic, OPCODES};
+use crate::ppu::SCANLINES_PER_FRAME;
+use crate::{Bus, BusCycle, Error, CPU};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    // either a read or a write
+    Access,
+    // the opcode fetch of an instruction
+    Execute,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    // the range is inclusive
+    pub start: u16,
+    pub end: u16,
+    pub kind: WatchKind,
+    // only trigger when this value gets read or written
+    pub value: Option<u8>,
+}
+
+impl Watchpoint {
+    pub fn new(address: u16, kind: WatchKind) -> Self {
+        Watchpoint {
+            start: address,
+            end: address,
+            kind,
+            value: None,
+        }
+    }
+
+    fn matches(&self, kind: WatchKind, address: u16, data: u8) -> bool {
+        let kind_matches =
+            self.kind == kind || (self.kind == WatchKind::Access && kind != WatchKind::Execute);
+
+        kind_matches
+            && (self.start..=self.end).contains(&address)
+            && self.value.is_none_or(|value| value == data)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunMode {
+    // until a breakpoint, watchpoint or error
+    Continue,
+    StepInto,
+    // runs a whole subroutine when the next instruction is a jsr
+    StepOver,
+    // until the current subroutine or interrupt handler returns
+    StepOut,
+    // until the ppu gets to the start of the scanline, wrapping into the next frame if it's
+    // already past it
+    Scanline(u16),
+    // until the ppu starts the frame with this number
+    Frame(u64),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    // the step or run mode finished
+    Done,
+    Breakpoint(u16),
+    // kind is the access that triggered it
+    Watchpoint {
+        kind: WatchKind,
+        address: u16,
+        data: u8,
+    },
+    InstructionLimit,
+    Error(Error),
+}
+
+// runs the cpu an instruction at a time until something interesting happens, the cpu always
+// stops at an instruction boundary
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+
+    // lets frontends get control back every so often during long runs
+    pub instruction_limit: Option<u64>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_breakpoint(&mut self, pc: u16) -> bool {
+        self.breakpoints.insert(pc)
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: u16) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|other| *other != watchpoint);
+        self.watchpoints.len() != len
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    fn triggered_watchpoint(&self, kind: WatchKind, address: u16, data: u8) -> Option<StopReason> {
+        self.watchpoints
+            .iter()
+            .find(|watchpoint| watchpoint.matches(kind, address, data))
+            .map(|_| StopReason::Watchpoint {
+                kind,
+                address,
+                data,
+            })
+    }
+
+    // breakpoints and execute watchpoints stop before the instruction runs
+    fn check_execute(&self, cpu: &CPU) -> Option<StopReason> {
+        let pc = cpu.pc();
+        if self.breakpoints.contains(&pc) {
+            return Some(StopReason::Breakpoint(pc));
+        }
+
+        self.triggered_watchpoint(WatchKind::Execute, pc, cpu.bus.peek(pc))
+    }
+
+    fn check_accesses(&self, cycles: &[BusCycle]) -> Option<StopReason> {
+        cycles.iter().find_map(|&cycle| match cycle {
+            BusCycle::Read { address, data } => {
+                self.triggered_watchpoint(WatchKind::Read, address, data)
+            }
+            BusCycle::Write { address, data } => {
+                self.triggered_watchpoint(WatchKind::Write, address, data)
+            }
+            BusCycle::Internal => None,
+        })
+    }
+
+    pub fn run(&mut self, cpu: &mut CPU, mode: RunMode) -> StopReason {
+        let watch_accesses = self
+            .watchpoints
+            .iter()
+            .any(|watchpoint| watchpoint.kind != WatchKind::Execute);
+        let owns_cycle_log = watch_accesses && cpu.bus.cycle_log.is_none();
+        if owns_cycle_log {
+            cpu.bus.cycle_log = Some(Vec::new());
+        }
+
+        let reason = self.run_until_stop(cpu, mode, watch_accesses, owns_cycle_log);
+
+        if owns_cycle_log {
+            cpu.bus.cycle_log = None;
+        }
+        reason
+    }
+
+    fn run_until_stop(
+        &mut self,
+        cpu: &mut CPU,
+        mode: RunMode,
+        watch_accesses: bool,
+        owns_cycle_log: bool,
+    ) -> StopReason {
+        // the stack wraps around so its depth is compared relative to where it started
+        let start_sp = cpu.sp();
+        let stack_popped = |cpu: &CPU| cpu.sp().wrapping_sub(start_sp) as i8;
+
+        // jsr pushes the address of its last byte and rts adds one to it
+        let opcode = OPCODES[cpu.bus.peek(cpu.pc()) as usize];
+        let return_pc = match (mode, opcode.mnemonic) {
+            (RunMode::StepOver, Mnemonic::Jsr) => Some(cpu.pc().wrapping_add(3)),
+            _ => None,
+        };
+
+        // scanlines are counted from the start of the emulation so wrapping into the next frame
+        // is just a bigger number
+        let line =
+            |bus: &Bus| bus.ppu.frame() * SCANLINES_PER_FRAME as u64 + bus.ppu.scanline() as u64;
+        let target_line = match mode {
+            RunMode::Scanline(scanline) => {
+                let current = line(&cpu.bus);
+                let target = current - current % SCANLINES_PER_FRAME as u64
+                    + (scanline % SCANLINES_PER_FRAME) as u64;
+                if target > current {
+                    target
+                } else {
+                    target + SCANLINES_PER_FRAME as u64
+                }
+            }
+            _ => 0,
+        };
+
+        let mut executed = 0;
+        loop {
+            // continuing from a breakpoint shouldn't stop on it again straight away
+            if executed > 0 {
+                if let Some(reason) = self.check_execute(cpu) {
+                    return reason;
+                }
+            }
+
+            if self
+                .instruction_limit
+                .is_some_and(|limit| executed >= limit)
+            {
+                return StopReason::InstructionLimit;
+            }
+
+            let opcode = OPCODES[cpu.bus.peek(cpu.pc()) as usize];
+            let log_start = cpu.bus.cycle_log.as_ref().map_or(0, Vec::len);
+            let result = cpu.execute_next_instruction();
+            executed += 1;
+
+            if watch_accesses {
+                let reason = cpu
+                    .bus
+                    .cycle_log
+                    .as_ref()
+                    .and_then(|cycle_log| self.check_accesses(&cycle_log[log_start..]));
+
+                // the log only needs this instruction so it doesn't grow forever
+                if owns_cycle_log {
+                    if let Some(cycle_log) = &mut cpu.bus.cycle_log {
+                        cycle_log.clear();
+                    }
+                }
+
+                if let Some(reason) = reason {
+                    return reason;
+                }
+            }
+
+            if let Err(error) = result {
+                return StopReason::Error(error);
+            }
+
+            let done = match mode {
+                RunMode::Continue => false,
+                RunMode::StepOver if return_pc.is_some() => {
+                    Some(cpu.pc()) == return_pc && stack_popped(cpu) >= 0
+                }
+                RunMode::StepInto | RunMode::StepOver => true,
+                RunMode::StepOut => {
+                    (opcode.mnemonic == Mnemonic::Rts || opcode.mnemonic == Mnemonic::Rti)
+                        && stack_popped(cpu) > 0
+                }
+                RunMode::Scanline(_) => line(&cpu.bus) >= target_line,
+                RunMode::Frame(frame) => cpu.bus.ppu.frame() >= frame,
+            };
+
+            if done {
+                return StopReason::Done;
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+
+use crate::catridge::PRG_BANK_SIZE;
+use crate::opcodes::AddressingMode::*;
+use crate::opcodes::{branch_target, Mnemonic, Opcode, OPCODES};
+use crate::{Catridge, CpuBus, Symbols};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassembledInstruction {
+    pub address: u16,
+    // the label of the instruction itself when symbols were given
+    pub label: Option<String>,
+    pub bytes: Vec<u8>,
+    // none when the data ran out in the middle of the instruction so the bytes are left as data
+    pub opcode: Option<Opcode>,
+    // operand in ca65 syntax with branches already resolved to their target
+    pub operand: String,
+    // where a branch or jump goes if it's known without running the code
+    pub target: Option<u16>,
+}
+
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<String>>()
+            .join(" ");
+        if let Some(label) = &self.label {
+            writeln!(f, "{}:", label)?;
+        }
+        write!(f, "{:04X}  {:<8}  ", self.address, bytes)?;
+
+        match self.opcode {
+            Some(opcode) if self.operand.is_empty() => write!(f, "{}", opcode.mnemonic),
+            Some(opcode) => write!(f, "{} {}", opcode.mnemonic, self.operand),
+            None => {
+                let bytes = self
+                    .bytes
+                    .iter()
+                    .map(|byte| format!("${:02X}", byte))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, ".byte {}", bytes)
+            }
+        }
+    }
+}
+
+// decodes a single instruction using read to get the bytes, which returns none past the end of
+// the data, and label to name addresses
+fn decode<F, L>(read: F, label: L, address: u16) -> DisassembledInstruction
+where
+    F: Fn(u16) -> Option<u8>,
+    L: Fn(u16) -> Option<String>,
+{
+    let opcode = OPCODES[read(address).unwrap_or(0) as usize];
+    let bytes: Option<Vec<u8>> = (0..opcode.size as u16)
+        .map(|offset| read(address.wrapping_add(offset)))
+        .collect();
+
+    let bytes = match bytes {
+        Some(bytes) => bytes,
+        None => {
+            return DisassembledInstruction {
+                address,
+                label: label(address),
+                bytes: (0..opcode.size as u16)
+                    .map_while(|offset| read(address.wrapping_add(offset)))
+                    .collect(),
+                opcode: None,
+                operand: String::new(),
+                target: None,
+            }
+        }
+    };
+
+    let byte = *bytes.get(1).unwrap_or(&0);
+    let word = (*bytes.get(2).unwrap_or(&0) as u16) << 8 | byte as u16;
+    let operand = opcode.format_operand(address, &bytes, &label);
+    let target = match opcode.mode {
+        Absolute if opcode.mnemonic == Mnemonic::Jmp || opcode.mnemonic == Mnemonic::Jsr => {
+            Some(word)
+        }
+        Relative => Some(branch_target(address, byte)),
+        _ => None,
+    };
+
+    DisassembledInstruction {
+        address,
+        label: label(address),
+        bytes,
+        opcode: Some(opcode),
+        operand,
+        target,
+    }
+}
+
+// disassembles raw bytes as if they were loaded at origin
+pub fn disassemble(data: &[u8], origin: u16) -> Vec<DisassembledInstruction> {
+    disassemble_labeled(data, origin, |_| None)
+}
+
+fn disassemble_labeled<L: Fn(u16) -> Option<String>>(
+    data: &[u8],
+    origin: u16,
+    label: L,
+) -> Vec<DisassembledInstruction> {
+    let read = |address: u16| data.get(address.wrapping_sub(origin) as usize).copied();
+
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let instruction = decode(read, &label, origin.wrapping_add(offset as u16));
+        offset += instruction.bytes.len();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+// disassembles start to end inclusive from what the cpu currently sees, this only peeks so it
+// doesn't disturb the emulation
+pub fn disassemble_bus<B: CpuBus>(
+    bus: &B,
+    start: u16,
+    end: u16,
+    symbols: Option<&Symbols>,
+) -> Vec<DisassembledInstruction> {
+    let read = |address: u16| Some(bus.peek(address));
+    let label = |address: u16| Some(symbols?.cpu_label(bus, address)?.to_string());
+
+    let mut instructions = Vec::new();
+    let mut address = start as u32;
+    while address <= end as u32 {
+        let instruction = decode(read, label, address as u16);
+        address += instruction.bytes.len() as u32;
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+// disassembles a 16kb bank of prg rom as if it was mapped in at origin, whatever the mapper is
+// currently doing
+pub fn disassemble_prg_bank(
+    catridge: &Catridge,
+    bank: usize,
+    origin: u16,
+    symbols: Option<&Symbols>,
+) -> Option<Vec<DisassembledInstruction>> {
+    let prg_rom = catridge.prg_rom();
+    let start = bank.checked_mul(PRG_BANK_SIZE)?;
+    if start >= prg_rom.len() {
+        return None;
+    }
+
+    let end = start.checked_add(PRG_BANK_SIZE)?.min(prg_rom.len());
+    let data = &prg_rom[start..end];
+    // addresses inside the bank are named by their prg rom offset
+    let label = |address: u16| {
+        let offset = address.wrapping_sub(origin) as usize;
+        let prg_offset = if offset < data.len() {
+            Some(start + offset)
+        } else {
+            None
+        };
+        Some(symbols?.label(address, prg_offset)?.to_string())
+    };
+
+    Some(disassemble_labeled(data, origin, label))
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use crate::CartridgeError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnknownOpcode { opcode: u8, pc: u16 },
+    CpuJam { opcode: u8, pc: u16 },
+    BadRegisterAddress(u16),
+    InvalidCartridge(CartridgeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownOpcode { opcode, pc } => write!(
+                f,
+                "Instruction with opcode '0x{:02x}' not supported! PC: 0x{:04x}",
+                opcode, pc
+            ),
+            Error::CpuJam { opcode, pc } => write!(
+                f,
+                "CPU jammed by opcode '0x{:02x}'! PC: 0x{:04x}",
+                opcode, pc
+            ),
+            Error::BadRegisterAddress(address) => write!(
+                f,
+                "Accessing register at address 0x{:x} goes out of range!",
+                address
+            ),
+            Error::InvalidCartridge(error) => write!(f, "Invalid catridge: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidCartridge(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<CartridgeError> for Error {
+    fn from(error: CartridgeError) -> Self {
+        Error::InvalidCartridge(error)
+    }
+}
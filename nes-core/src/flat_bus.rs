@@ -0,0 +1,63 @@
+use crate::{BusCycle, CpuBus};
+
+// the whole address space is plain ram instead of the nes memory map, used for running generic
+// 6502 programs and tests
+pub struct FlatBus {
+    pub memory: Vec<u8>,
+    pub cycles_count: u32,
+
+    // when set every cycle gets recorded for checking the cpu timing
+    pub cycle_log: Option<Vec<BusCycle>>,
+}
+
+impl Default for FlatBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlatBus {
+    pub fn new() -> Self {
+        FlatBus {
+            memory: vec![0; 0x10000],
+            cycles_count: 0,
+            cycle_log: None,
+        }
+    }
+
+    fn log_cycle(&mut self, cycle: BusCycle) {
+        self.cycles_count = self.cycles_count.wrapping_add(1);
+        if let Some(cycle_log) = &mut self.cycle_log {
+            cycle_log.push(cycle);
+        }
+    }
+}
+
+impl CpuBus for FlatBus {
+    fn read(&mut self, address: u16) -> u8 {
+        let data = self.memory[address as usize];
+        self.log_cycle(BusCycle::Read { address, data });
+        data
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.memory[address as usize] = data;
+        self.log_cycle(BusCycle::Write { address, data });
+    }
+
+    fn tick(&mut self) {
+        self.log_cycle(BusCycle::Internal);
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        self.memory[address as usize] = data;
+    }
+
+    fn cycles_count(&self) -> u32 {
+        self.cycles_count
+    }
+}
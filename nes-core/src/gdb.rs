@@ -0,0 +1,455 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use crate::{Debugger, Error, RunMode, StatusFlags, StopReason, WatchKind, Watchpoint, CPU};
+
+// instructions run between checks for ctrl-c from the client
+const INSTRUCTIONS_PER_POLL: u64 = 10_000;
+// the biggest packet the client may send or expect back, the PacketSize in the qSupported reply
+const PACKET_SIZE: usize = 0x1000;
+
+// gdb has no 6502 target built in so the registers get described to it, the order here is the
+// order of the g packet and the register numbers
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.nes.6502">
+    <reg name="pc" bitsize="16" type="code_ptr" regnum="0"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="a" bitsize="8" type="uint8"/>
+    <reg name="x" bitsize="8" type="uint8"/>
+    <reg name="y" bitsize="8" type="uint8"/>
+    <reg name="flags" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+const REGISTER_COUNT: usize = 6;
+
+// signal numbers used in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 16).ok()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| Some(hex_digit(pair[0])? << 4 | hex_digit(pair[1])?))
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// parses an "address,length" pair
+fn parse_range(text: &str) -> Option<(u16, usize)> {
+    let (address, length) = text.split_once(',')?;
+    Some((parse_hex(address)? as u16, parse_hex(length)? as usize))
+}
+
+enum Command {
+    Reply(String),
+    Resume(RunMode),
+    Detach,
+    Kill,
+}
+
+// serves the gdb remote serial protocol for a single client, the cpu only runs when the client
+// asks it to
+pub struct GdbServer {
+    pub cpu: CPU,
+    pub debugger: Debugger,
+
+    stream: TcpStream,
+    // bytes received but not handled yet
+    received: Vec<u8>,
+    no_ack_mode: bool,
+}
+
+impl GdbServer {
+    pub fn new(cpu: CPU, stream: TcpStream) -> Self {
+        GdbServer {
+            cpu,
+            debugger: Debugger::new(),
+            stream,
+            received: Vec::new(),
+            no_ack_mode: false,
+        }
+    }
+
+    // handles packets until the client detaches, kills the session or disconnects
+    pub fn serve(&mut self) -> io::Result<()> {
+        while let Some(packet) = self.receive_packet()? {
+            let command = self.handle_packet(&packet);
+            match command {
+                Command::Reply(reply) => self.send_packet(&reply)?,
+                Command::Resume(mode) => {
+                    let reply = self.resume(mode)?;
+                    self.send_packet(&reply)?;
+                }
+                Command::Detach => {
+                    self.send_packet("OK")?;
+                    return Ok(());
+                }
+                Command::Kill => return Ok(()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        if self.received.is_empty() {
+            let mut buffer = [0; 1024];
+            let count = self.stream.read(&mut buffer)?;
+            if count == 0 {
+                return Ok(None);
+            }
+            self.received.extend_from_slice(&buffer[..count]);
+        }
+
+        Ok(Some(self.received.remove(0)))
+    }
+
+    // returns none once the client disconnects
+    fn receive_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            // acks and stray interrupts between packets don't need anything done
+            match self.read_byte()? {
+                Some(b'$') => (),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+
+            let mut data = Vec::new();
+            let mut escaped = false;
+            loop {
+                match self.read_byte()? {
+                    Some(b'#') if !escaped => break,
+                    Some(b'}') if !escaped => escaped = true,
+                    Some(byte) if escaped => {
+                        data.push(byte ^ 0x20);
+                        escaped = false;
+                    }
+                    Some(byte) => data.push(byte),
+                    None => return Ok(None),
+                }
+            }
+
+            let high = self.read_byte()?.and_then(hex_digit);
+            let low = self.read_byte()?.and_then(hex_digit);
+            let valid = match (high, low) {
+                (Some(high), Some(low)) => high << 4 | low == checksum(&data),
+                _ => false,
+            };
+
+            if !self.no_ack_mode {
+                self.stream.write_all(if valid { b"+" } else { b"-" })?;
+            }
+
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+        }
+    }
+
+    fn send_packet(&mut self, data: &str) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(data.len() + 4);
+        packet.push(b'$');
+        for &byte in data.as_bytes() {
+            // these would be mistaken for the end of the packet or an escape
+            if let b'$' | b'#' | b'}' | b'*' = byte {
+                packet.extend_from_slice(&[b'}', byte ^ 0x20]);
+            } else {
+                packet.push(byte);
+            }
+        }
+        packet.extend_from_slice(format!("#{:02x}", checksum(&packet[1..])).as_bytes());
+
+        self.stream.write_all(&packet)?;
+        self.stream.flush()
+    }
+
+    // checks for the client sending ctrl-c without waiting for it
+    fn interrupted(&mut self) -> io::Result<bool> {
+        let mut buffer = [0; 1024];
+        self.stream.set_nonblocking(true)?;
+        let result = self.stream.read(&mut buffer);
+        self.stream.set_nonblocking(false)?;
+
+        match result {
+            Ok(count) => self.received.extend_from_slice(&buffer[..count]),
+            Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => (),
+            Err(error) => return Err(error),
+        }
+
+        match self.received.iter().position(|&byte| byte == 0x03) {
+            Some(index) => {
+                self.received.remove(index);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn resume(&mut self, mode: RunMode) -> io::Result<String> {
+        self.debugger.instruction_limit = Some(INSTRUCTIONS_PER_POLL);
+        loop {
+            let reason = self.debugger.run(&mut self.cpu, mode);
+            if reason != StopReason::InstructionLimit {
+                return Ok(stop_reply(&reason));
+            }
+
+            if self.interrupted()? {
+                return Ok(format!("S{:02x}", SIGINT));
+            }
+        }
+    }
+
+    fn register(&self, number: usize) -> Option<Vec<u8>> {
+        let cpu = &self.cpu;
+        Some(match number {
+            0 => cpu.pc().to_le_bytes().to_vec(),
+            1 => vec![cpu.sp()],
+            2 => vec![cpu.a()],
+            3 => vec![cpu.x()],
+            4 => vec![cpu.y()],
+            5 => vec![cpu.flags().bits()],
+            _ => return None,
+        })
+    }
+
+    // returns how many bytes of data the register used
+    fn set_register(&mut self, number: usize, data: &[u8]) -> Option<usize> {
+        let byte = *data.first()?;
+        match number {
+            0 => self.cpu.set_pc(u16::from_le_bytes([byte, *data.get(1)?])),
+            1 => self.cpu.set_sp(byte),
+            2 => self.cpu.set_a(byte),
+            3 => self.cpu.set_x(byte),
+            4 => self.cpu.set_y(byte),
+            5 => self.cpu.set_flags(StatusFlags::from_bits(byte)),
+            _ => return None,
+        }
+
+        Some(if number == 0 { 2 } else { 1 })
+    }
+
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let (address, length) = parse_range(args)?;
+        // every byte takes two hex digits in the reply
+        if length > PACKET_SIZE / 2 {
+            return None;
+        }
+
+        let bytes: Vec<u8> = (0..length)
+            .map(|offset| self.cpu.bus.peek(address.wrapping_add(offset as u16)))
+            .collect();
+        Some(encode_hex(&bytes))
+    }
+
+    fn write_memory(&mut self, args: &str) -> Option<()> {
+        let (range, data) = args.split_once(':')?;
+        let (address, length) = parse_range(range)?;
+        let data = decode_hex(data)?;
+        if data.len() != length {
+            return None;
+        }
+
+        for (offset, &byte) in data.iter().enumerate() {
+            self.cpu.bus.poke(address.wrapping_add(offset as u16), byte);
+        }
+        Some(())
+    }
+
+    // handles Z and z packets, type 0 and 1 are breakpoints and 2-4 are write, read and access
+    // watchpoints
+    fn set_stop_point(&mut self, args: &str, insert: bool) -> Option<String> {
+        let mut parts = args.split(',');
+        let kind = parts.next()?;
+        let address = parse_hex(parts.next()?)? as u16;
+        // anything longer than the whole address space is an error
+        let length = parse_hex(parts.next()?)?.max(1);
+        if length > 0x10000 {
+            return None;
+        }
+
+        let watch_kind = match kind {
+            "0" | "1" => {
+                if insert {
+                    self.debugger.add_breakpoint(address);
+                } else {
+                    self.debugger.remove_breakpoint(address);
+                }
+                return Some("OK".to_string());
+            }
+            "2" => WatchKind::Write,
+            "3" => WatchKind::Read,
+            "4" => WatchKind::Access,
+            // tells the client this kind isn't supported
+            _ => return Some(String::new()),
+        };
+
+        // ranges past $ffff wrap around to $0000 which takes a second watchpoint
+        let end = address as u32 + length - 1;
+        let mut watchpoints = vec![Watchpoint {
+            end: end.min(0xffff) as u16,
+            ..Watchpoint::new(address, watch_kind)
+        }];
+        if end > 0xffff {
+            watchpoints.push(Watchpoint {
+                end: (end - 0x10000) as u16,
+                ..Watchpoint::new(0x0000, watch_kind)
+            });
+        }
+
+        for watchpoint in watchpoints {
+            if insert {
+                self.debugger.add_watchpoint(watchpoint);
+            } else {
+                self.debugger.remove_watchpoint(watchpoint);
+            }
+        }
+        Some("OK".to_string())
+    }
+
+    // qXfer:features:read:target.xml:offset,length
+    fn read_target_xml(&self, args: &str) -> Option<String> {
+        let (offset, length) = parse_range(args.strip_prefix("target.xml:")?)?;
+        let offset = offset as usize;
+        let xml = TARGET_XML.as_bytes();
+        if offset >= xml.len() {
+            return Some("l".to_string());
+        }
+
+        let end = (offset + length).min(xml.len());
+        let marker = if end == xml.len() { 'l' } else { 'm' };
+        Some(format!(
+            "{}{}",
+            marker,
+            String::from_utf8_lossy(&xml[offset..end])
+        ))
+    }
+
+    fn handle_packet(&mut self, packet: &str) -> Command {
+        let error = || "E01".to_string();
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        let reply = match command {
+            "?" => format!("S{:02x}", SIGTRAP),
+            "g" => (0..REGISTER_COUNT)
+                .filter_map(|number| self.register(number))
+                .map(|bytes| encode_hex(&bytes))
+                .collect(),
+            "G" => {
+                let mut data = &decode_hex(args).unwrap_or_default()[..];
+                for number in 0..REGISTER_COUNT {
+                    match self.set_register(number, data) {
+                        Some(used) => data = &data[used..],
+                        None => return Command::Reply(error()),
+                    }
+                }
+                "OK".to_string()
+            }
+            "p" => parse_hex(args)
+                .and_then(|number| self.register(number as usize))
+                .map_or_else(error, |bytes| encode_hex(&bytes)),
+            "P" => args
+                .split_once('=')
+                .and_then(|(number, value)| {
+                    self.set_register(parse_hex(number)? as usize, &decode_hex(value)?)
+                })
+                .map_or_else(error, |_| "OK".to_string()),
+            "m" => self.read_memory(args).unwrap_or_else(error),
+            "M" => self
+                .write_memory(args)
+                .map_or_else(error, |_| "OK".to_string()),
+            "Z" | "z" => self
+                .set_stop_point(args, command == "Z")
+                .unwrap_or_else(error),
+            "c" | "s" => {
+                if let Some(address) = parse_hex(args) {
+                    self.cpu.set_pc(address as u16);
+                }
+
+                let mode = if command == "c" {
+                    RunMode::Continue
+                } else {
+                    RunMode::StepInto
+                };
+                return Command::Resume(mode);
+            }
+            "D" => return Command::Detach,
+            "k" => return Command::Kill,
+            // there's only ever the one thread
+            "H" => "OK".to_string(),
+            "T" => "OK".to_string(),
+            "q" | "Q" => self.handle_query(packet).unwrap_or_default(),
+            _ => String::new(),
+        };
+
+        Command::Reply(reply)
+    }
+
+    fn handle_query(&mut self, packet: &str) -> Option<String> {
+        if let Some(args) = packet.strip_prefix("qXfer:features:read:") {
+            return self.read_target_xml(args);
+        }
+
+        Some(
+            match packet.split(':').next()? {
+                // PacketSize is PACKET_SIZE in hex
+                "qSupported" => {
+                    "PacketSize=1000;QStartNoAckMode+;qXfer:features:read+;swbreak+;hwbreak+"
+                }
+                "QStartNoAckMode" => {
+                    self.no_ack_mode = true;
+                    "OK"
+                }
+                "qAttached" => "1",
+                "qC" => "QC1",
+                "qfThreadInfo" => "m1",
+                "qsThreadInfo" => "l",
+                _ => return None,
+            }
+            .to_string(),
+        )
+    }
+}
+
+fn stop_reply(reason: &StopReason) -> String {
+    match reason {
+        StopReason::Done | StopReason::InstructionLimit => format!("S{:02x}", SIGTRAP),
+        StopReason::Breakpoint(_) => format!("T{:02x}swbreak:;", SIGTRAP),
+        StopReason::Watchpoint { kind, address, .. } => {
+            let name = match kind {
+                WatchKind::Read => "rwatch",
+                _ => "watch",
+            };
+            format!("T{:02x}{}:{:04x};", SIGTRAP, name, address)
+        }
+        StopReason::Error(Error::CpuJam { .. })
+        | StopReason::Error(Error::UnknownOpcode { .. }) => {
+            format!("S{:02x}", SIGILL)
+        }
+        StopReason::Error(_) => format!("S{:02x}", SIGSEGV),
+    }
+}
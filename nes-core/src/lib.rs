@@ -1,11 +1,31 @@
+mod assembler;
 mod bus;
 mod catridge;
 mod cpu;
+mod debugger;
+mod disasm;
+mod error;
+mod flat_bus;
+mod gdb;
 mod mappers;
+mod opcodes;
 mod ppu;
+mod profiler;
+mod symbols;
+mod trace;
 
-pub use bus::Bus;
-pub use catridge::Catridge;
-pub use cpu::CPU;
+pub use assembler::{assemble, AssemblerError, Program, Segment};
+pub use bus::{Bus, BusCycle, CpuBus};
+pub use catridge::{CartridgeError, Catridge};
+pub use cpu::{CpuState, Flag, StatusFlags, CPU};
+pub use debugger::{Debugger, RunMode, StopReason, WatchKind, Watchpoint};
+pub use disasm::{disassemble, disassemble_bus, disassemble_prg_bank, DisassembledInstruction};
+pub use error::Error;
+pub use flat_bus::FlatBus;
+pub use gdb::GdbServer;
 pub use mappers::*;
-pub use ppu::PPU;
+pub use opcodes::{AddressingMode, Mnemonic, Opcode, OPCODES};
+pub use ppu::{PPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use profiler::{Profiler, RoutineKind, RoutineProfile};
+pub use symbols::{SymbolError, SymbolFormat, Symbols};
+pub use trace::{TraceEntry, TraceSink, TraceWriter};
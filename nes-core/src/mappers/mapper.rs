@@ -1,6 +1,31 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct MapperInfo {
-    pub prg_banks: u8,
-    pub chr_banks: u8,
+    pub mapper_id: u16,
+    pub submapper_id: u8,
+    // in 16kb units for prg and 8kb units for chr
+    pub prg_banks: u16,
+    pub chr_banks: u16,
+    // sizes are in bytes and include any battery backed memory
+    pub prg_ram_size: usize,
+    pub chr_ram_size: usize,
+    pub mirroring: Mirroring,
+    pub has_battery: bool,
+    pub timing: Timing,
 }
 
 pub trait Mapper {
@@ -8,4 +33,12 @@ pub trait Mapper {
     fn map_prg_write(&self, address: u16, address_out: &mut u16) -> bool;
     fn map_chr_read(&self, address: u16, address_out: &mut u16) -> bool;
     fn map_chr_write(&self, address: u16, address_out: &mut u16) -> bool;
+    fn map_prg_ram_read(&self, address: u16, address_out: &mut u16) -> bool;
+    fn map_prg_ram_write(&self, address: u16, address_out: &mut u16) -> bool;
+
+    // where debugger peeks and pokes land, this is the prg rom that's currently mapped in even
+    // when a normal write to the address would go to a bank register instead
+    fn map_prg_peek(&self, address: u16, address_out: &mut u16) -> bool {
+        self.map_prg_read(address, address_out)
+    }
 }
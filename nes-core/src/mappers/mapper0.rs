@@ -37,6 +37,27 @@ impl Mapper for Mapper0 {
     }
 
     fn map_chr_write(&self, address: u16, address_out: &mut u16) -> bool {
-        false
+        // only writable when the catridge uses chr ram instead of chr rom
+        if self.info.chr_banks == 0 {
+            self.map_chr_read(address, address_out)
+        } else {
+            false
+        }
+    }
+
+    fn map_prg_ram_read(&self, address: u16, address_out: &mut u16) -> bool {
+        let prg_ram_size = self.info.prg_ram_size;
+        match address {
+            0x6000..=0x7fff if prg_ram_size != 0 => {
+                // smaller ram chips get mirrored across the whole range
+                *address_out = ((address - 0x6000) as usize % prg_ram_size) as u16;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn map_prg_ram_write(&self, address: u16, address_out: &mut u16) -> bool {
+        self.map_prg_ram_read(address, address_out)
     }
 }
@@ -0,0 +1,527 @@
+use std::fmt;
+
+use self::AddressingMode::*;
+use self::Mnemonic::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressingMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+
+impl AddressingMode {
+    // the number of bytes following the opcode
+    pub const fn operand_size(self) -> u8 {
+        match self {
+            Implied | Accumulator => 0,
+            Immediate | ZeroPage | ZeroPageX | ZeroPageY | IndirectX | IndirectY | Relative => 1,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 2,
+        }
+    }
+}
+
+// names follow nestest.log so unofficial opcodes like isc show up as isb
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mnemonic {
+    Adc,
+    Alr,
+    Anc,
+    And,
+    Arr,
+    Asl,
+    Axs,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dcp,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Isb,
+    Jam,
+    Jmp,
+    Jsr,
+    Las,
+    Lax,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Lxa,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Pla,
+    Plp,
+    Rla,
+    Rol,
+    Ror,
+    Rra,
+    Rti,
+    Rts,
+    Sax,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Sha,
+    Shx,
+    Shy,
+    Slo,
+    Sre,
+    Sta,
+    Stx,
+    Sty,
+    Tas,
+    Tax,
+    Tay,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+    Xaa,
+}
+
+impl Mnemonic {
+    pub fn name(self) -> &'static str {
+        match self {
+            Adc => "ADC",
+            Alr => "ALR",
+            Anc => "ANC",
+            And => "AND",
+            Arr => "ARR",
+            Asl => "ASL",
+            Axs => "AXS",
+            Bcc => "BCC",
+            Bcs => "BCS",
+            Beq => "BEQ",
+            Bit => "BIT",
+            Bmi => "BMI",
+            Bne => "BNE",
+            Bpl => "BPL",
+            Brk => "BRK",
+            Bvc => "BVC",
+            Bvs => "BVS",
+            Clc => "CLC",
+            Cld => "CLD",
+            Cli => "CLI",
+            Clv => "CLV",
+            Cmp => "CMP",
+            Cpx => "CPX",
+            Cpy => "CPY",
+            Dcp => "DCP",
+            Dec => "DEC",
+            Dex => "DEX",
+            Dey => "DEY",
+            Eor => "EOR",
+            Inc => "INC",
+            Inx => "INX",
+            Iny => "INY",
+            Isb => "ISB",
+            Jam => "JAM",
+            Jmp => "JMP",
+            Jsr => "JSR",
+            Las => "LAS",
+            Lax => "LAX",
+            Lda => "LDA",
+            Ldx => "LDX",
+            Ldy => "LDY",
+            Lsr => "LSR",
+            Lxa => "LXA",
+            Nop => "NOP",
+            Ora => "ORA",
+            Pha => "PHA",
+            Php => "PHP",
+            Pla => "PLA",
+            Plp => "PLP",
+            Rla => "RLA",
+            Rol => "ROL",
+            Ror => "ROR",
+            Rra => "RRA",
+            Rti => "RTI",
+            Rts => "RTS",
+            Sax => "SAX",
+            Sbc => "SBC",
+            Sec => "SEC",
+            Sed => "SED",
+            Sei => "SEI",
+            Sha => "SHA",
+            Shx => "SHX",
+            Shy => "SHY",
+            Slo => "SLO",
+            Sre => "SRE",
+            Sta => "STA",
+            Stx => "STX",
+            Sty => "STY",
+            Tas => "TAS",
+            Tax => "TAX",
+            Tay => "TAY",
+            Tsx => "TSX",
+            Txa => "TXA",
+            Txs => "TXS",
+            Tya => "TYA",
+            Xaa => "XAA",
+        }
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Opcode {
+    pub mnemonic: Mnemonic,
+    pub mode: AddressingMode,
+    // the whole instruction including the opcode
+    pub size: u8,
+    // cycles taken without any page crossing and with branches not taken
+    pub cycles: u8,
+    // reading across a page or taking a branch costs an extra cycle, and a branch to another page
+    // costs one more
+    pub page_cross_penalty: bool,
+    pub official: bool,
+}
+
+const fn opcode(
+    mnemonic: Mnemonic,
+    mode: AddressingMode,
+    cycles: u8,
+    page_cross_penalty: bool,
+    official: bool,
+) -> Opcode {
+    Opcode {
+        mnemonic,
+        mode,
+        size: mode.operand_size() + 1,
+        cycles,
+        page_cross_penalty,
+        official,
+    }
+}
+
+// jam opcodes lock up the cpu so they don't have a cycle count
+pub static OPCODES: [Opcode; 256] = [
+    opcode(Brk, Implied, 7, false, true),     // 0x00
+    opcode(Ora, IndirectX, 6, false, true),   // 0x01
+    opcode(Jam, Implied, 0, false, false),    // 0x02
+    opcode(Slo, IndirectX, 8, false, false),  // 0x03
+    opcode(Nop, ZeroPage, 3, false, false),   // 0x04
+    opcode(Ora, ZeroPage, 3, false, true),    // 0x05
+    opcode(Asl, ZeroPage, 5, false, true),    // 0x06
+    opcode(Slo, ZeroPage, 5, false, false),   // 0x07
+    opcode(Php, Implied, 3, false, true),     // 0x08
+    opcode(Ora, Immediate, 2, false, true),   // 0x09
+    opcode(Asl, Accumulator, 2, false, true), // 0x0a
+    opcode(Anc, Immediate, 2, false, false),  // 0x0b
+    opcode(Nop, Absolute, 4, false, false),   // 0x0c
+    opcode(Ora, Absolute, 4, false, true),    // 0x0d
+    opcode(Asl, Absolute, 6, false, true),    // 0x0e
+    opcode(Slo, Absolute, 6, false, false),   // 0x0f
+    opcode(Bpl, Relative, 2, true, true),     // 0x10
+    opcode(Ora, IndirectY, 5, true, true),    // 0x11
+    opcode(Jam, Implied, 0, false, false),    // 0x12
+    opcode(Slo, IndirectY, 8, false, false),  // 0x13
+    opcode(Nop, ZeroPageX, 4, false, false),  // 0x14
+    opcode(Ora, ZeroPageX, 4, false, true),   // 0x15
+    opcode(Asl, ZeroPageX, 6, false, true),   // 0x16
+    opcode(Slo, ZeroPageX, 6, false, false),  // 0x17
+    opcode(Clc, Implied, 2, false, true),     // 0x18
+    opcode(Ora, AbsoluteY, 4, true, true),    // 0x19
+    opcode(Nop, Implied, 2, false, false),    // 0x1a
+    opcode(Slo, AbsoluteY, 7, false, false),  // 0x1b
+    opcode(Nop, AbsoluteX, 4, true, false),   // 0x1c
+    opcode(Ora, AbsoluteX, 4, true, true),    // 0x1d
+    opcode(Asl, AbsoluteX, 7, false, true),   // 0x1e
+    opcode(Slo, AbsoluteX, 7, false, false),  // 0x1f
+    opcode(Jsr, Absolute, 6, false, true),    // 0x20
+    opcode(And, IndirectX, 6, false, true),   // 0x21
+    opcode(Jam, Implied, 0, false, false),    // 0x22
+    opcode(Rla, IndirectX, 8, false, false),  // 0x23
+    opcode(Bit, ZeroPage, 3, false, true),    // 0x24
+    opcode(And, ZeroPage, 3, false, true),    // 0x25
+    opcode(Rol, ZeroPage, 5, false, true),    // 0x26
+    opcode(Rla, ZeroPage, 5, false, false),   // 0x27
+    opcode(Plp, Implied, 4, false, true),     // 0x28
+    opcode(And, Immediate, 2, false, true),   // 0x29
+    opcode(Rol, Accumulator, 2, false, true), // 0x2a
+    opcode(Anc, Immediate, 2, false, false),  // 0x2b
+    opcode(Bit, Absolute, 4, false, true),    // 0x2c
+    opcode(And, Absolute, 4, false, true),    // 0x2d
+    opcode(Rol, Absolute, 6, false, true),    // 0x2e
+    opcode(Rla, Absolute, 6, false, false),   // 0x2f
+    opcode(Bmi, Relative, 2, true, true),     // 0x30
+    opcode(And, IndirectY, 5, true, true),    // 0x31
+    opcode(Jam, Implied, 0, false, false),    // 0x32
+    opcode(Rla, IndirectY, 8, false, false),  // 0x33
+    opcode(Nop, ZeroPageX, 4, false, false),  // 0x34
+    opcode(And, ZeroPageX, 4, false, true),   // 0x35
+    opcode(Rol, ZeroPageX, 6, false, true),   // 0x36
+    opcode(Rla, ZeroPageX, 6, false, false),  // 0x37
+    opcode(Sec, Implied, 2, false, true),     // 0x38
+    opcode(And, AbsoluteY, 4, true, true),    // 0x39
+    opcode(Nop, Implied, 2, false, false),    // 0x3a
+    opcode(Rla, AbsoluteY, 7, false, false),  // 0x3b
+    opcode(Nop, AbsoluteX, 4, true, false),   // 0x3c
+    opcode(And, AbsoluteX, 4, true, true),    // 0x3d
+    opcode(Rol, AbsoluteX, 7, false, true),   // 0x3e
+    opcode(Rla, AbsoluteX, 7, false, false),  // 0x3f
+    opcode(Rti, Implied, 6, false, true),     // 0x40
+    opcode(Eor, IndirectX, 6, false, true),   // 0x41
+    opcode(Jam, Implied, 0, false, false),    // 0x42
+    opcode(Sre, IndirectX, 8, false, false),  // 0x43
+    opcode(Nop, ZeroPage, 3, false, false),   // 0x44
+    opcode(Eor, ZeroPage, 3, false, true),    // 0x45
+    opcode(Lsr, ZeroPage, 5, false, true),    // 0x46
+    opcode(Sre, ZeroPage, 5, false, false),   // 0x47
+    opcode(Pha, Implied, 3, false, true),     // 0x48
+    opcode(Eor, Immediate, 2, false, true),   // 0x49
+    opcode(Lsr, Accumulator, 2, false, true), // 0x4a
+    opcode(Alr, Immediate, 2, false, false),  // 0x4b
+    opcode(Jmp, Absolute, 3, false, true),    // 0x4c
+    opcode(Eor, Absolute, 4, false, true),    // 0x4d
+    opcode(Lsr, Absolute, 6, false, true),    // 0x4e
+    opcode(Sre, Absolute, 6, false, false),   // 0x4f
+    opcode(Bvc, Relative, 2, true, true),     // 0x50
+    opcode(Eor, IndirectY, 5, true, true),    // 0x51
+    opcode(Jam, Implied, 0, false, false),    // 0x52
+    opcode(Sre, IndirectY, 8, false, false),  // 0x53
+    opcode(Nop, ZeroPageX, 4, false, false),  // 0x54
+    opcode(Eor, ZeroPageX, 4, false, true),   // 0x55
+    opcode(Lsr, ZeroPageX, 6, false, true),   // 0x56
+    opcode(Sre, ZeroPageX, 6, false, false),  // 0x57
+    opcode(Cli, Implied, 2, false, true),     // 0x58
+    opcode(Eor, AbsoluteY, 4, true, true),    // 0x59
+    opcode(Nop, Implied, 2, false, false),    // 0x5a
+    opcode(Sre, AbsoluteY, 7, false, false),  // 0x5b
+    opcode(Nop, AbsoluteX, 4, true, false),   // 0x5c
+    opcode(Eor, AbsoluteX, 4, true, true),    // 0x5d
+    opcode(Lsr, AbsoluteX, 7, false, true),   // 0x5e
+    opcode(Sre, AbsoluteX, 7, false, false),  // 0x5f
+    opcode(Rts, Implied, 6, false, true),     // 0x60
+    opcode(Adc, IndirectX, 6, false, true),   // 0x61
+    opcode(Jam, Implied, 0, false, false),    // 0x62
+    opcode(Rra, IndirectX, 8, false, false),  // 0x63
+    opcode(Nop, ZeroPage, 3, false, false),   // 0x64
+    opcode(Adc, ZeroPage, 3, false, true),    // 0x65
+    opcode(Ror, ZeroPage, 5, false, true),    // 0x66
+    opcode(Rra, ZeroPage, 5, false, false),   // 0x67
+    opcode(Pla, Implied, 4, false, true),     // 0x68
+    opcode(Adc, Immediate, 2, false, true),   // 0x69
+    opcode(Ror, Accumulator, 2, false, true), // 0x6a
+    opcode(Arr, Immediate, 2, false, false),  // 0x6b
+    opcode(Jmp, Indirect, 5, false, true),    // 0x6c
+    opcode(Adc, Absolute, 4, false, true),    // 0x6d
+    opcode(Ror, Absolute, 6, false, true),    // 0x6e
+    opcode(Rra, Absolute, 6, false, false),   // 0x6f
+    opcode(Bvs, Relative, 2, true, true),     // 0x70
+    opcode(Adc, IndirectY, 5, true, true),    // 0x71
+    opcode(Jam, Implied, 0, false, false),    // 0x72
+    opcode(Rra, IndirectY, 8, false, false),  // 0x73
+    opcode(Nop, ZeroPageX, 4, false, false),  // 0x74
+    opcode(Adc, ZeroPageX, 4, false, true),   // 0x75
+    opcode(Ror, ZeroPageX, 6, false, true),   // 0x76
+    opcode(Rra, ZeroPageX, 6, false, false),  // 0x77
+    opcode(Sei, Implied, 2, false, true),     // 0x78
+    opcode(Adc, AbsoluteY, 4, true, true),    // 0x79
+    opcode(Nop, Implied, 2, false, false),    // 0x7a
+    opcode(Rra, AbsoluteY, 7, false, false),  // 0x7b
+    opcode(Nop, AbsoluteX, 4, true, false),   // 0x7c
+    opcode(Adc, AbsoluteX, 4, true, true),    // 0x7d
+    opcode(Ror, AbsoluteX, 7, false, true),   // 0x7e
+    opcode(Rra, AbsoluteX, 7, false, false),  // 0x7f
+    opcode(Nop, Immediate, 2, false, false),  // 0x80
+    opcode(Sta, IndirectX, 6, false, true),   // 0x81
+    opcode(Nop, Immediate, 2, false, false),  // 0x82
+    opcode(Sax, IndirectX, 6, false, false),  // 0x83
+    opcode(Sty, ZeroPage, 3, false, true),    // 0x84
+    opcode(Sta, ZeroPage, 3, false, true),    // 0x85
+    opcode(Stx, ZeroPage, 3, false, true),    // 0x86
+    opcode(Sax, ZeroPage, 3, false, false),   // 0x87
+    opcode(Dey, Implied, 2, false, true),     // 0x88
+    opcode(Nop, Immediate, 2, false, false),  // 0x89
+    opcode(Txa, Implied, 2, false, true),     // 0x8a
+    opcode(Xaa, Immediate, 2, false, false),  // 0x8b
+    opcode(Sty, Absolute, 4, false, true),    // 0x8c
+    opcode(Sta, Absolute, 4, false, true),    // 0x8d
+    opcode(Stx, Absolute, 4, false, true),    // 0x8e
+    opcode(Sax, Absolute, 4, false, false),   // 0x8f
+    opcode(Bcc, Relative, 2, true, true),     // 0x90
+    opcode(Sta, IndirectY, 6, false, true),   // 0x91
+    opcode(Jam, Implied, 0, false, false),    // 0x92
+    opcode(Sha, IndirectY, 6, false, false),  // 0x93
+    opcode(Sty, ZeroPageX, 4, false, true),   // 0x94
+    opcode(Sta, ZeroPageX, 4, false, true),   // 0x95
+    opcode(Stx, ZeroPageY, 4, false, true),   // 0x96
+    opcode(Sax, ZeroPageY, 4, false, false),  // 0x97
+    opcode(Tya, Implied, 2, false, true),     // 0x98
+    opcode(Sta, AbsoluteY, 5, false, true),   // 0x99
+    opcode(Txs, Implied, 2, false, true),     // 0x9a
+    opcode(Tas, AbsoluteY, 5, false, false),  // 0x9b
+    opcode(Shy, AbsoluteX, 5, false, false),  // 0x9c
+    opcode(Sta, AbsoluteX, 5, false, true),   // 0x9d
+    opcode(Shx, AbsoluteY, 5, false, false),  // 0x9e
+    opcode(Sha, AbsoluteY, 5, false, false),  // 0x9f
+    opcode(Ldy, Immediate, 2, false, true),   // 0xa0
+    opcode(Lda, IndirectX, 6, false, true),   // 0xa1
+    opcode(Ldx, Immediate, 2, false, true),   // 0xa2
+    opcode(Lax, IndirectX, 6, false, false),  // 0xa3
+    opcode(Ldy, ZeroPage, 3, false, true),    // 0xa4
+    opcode(Lda, ZeroPage, 3, false, true),    // 0xa5
+    opcode(Ldx, ZeroPage, 3, false, true),    // 0xa6
+    opcode(Lax, ZeroPage, 3, false, false),   // 0xa7
+    opcode(Tay, Implied, 2, false, true),     // 0xa8
+    opcode(Lda, Immediate, 2, false, true),   // 0xa9
+    opcode(Tax, Implied, 2, false, true),     // 0xaa
+    opcode(Lxa, Immediate, 2, false, false),  // 0xab
+    opcode(Ldy, Absolute, 4, false, true),    // 0xac
+    opcode(Lda, Absolute, 4, false, true),    // 0xad
+    opcode(Ldx, Absolute, 4, false, true),    // 0xae
+    opcode(Lax, Absolute, 4, false, false),   // 0xaf
+    opcode(Bcs, Relative, 2, true, true),     // 0xb0
+    opcode(Lda, IndirectY, 5, true, true),    // 0xb1
+    opcode(Jam, Implied, 0, false, false),    // 0xb2
+    opcode(Lax, IndirectY, 5, true, false),   // 0xb3
+    opcode(Ldy, ZeroPageX, 4, false, true),   // 0xb4
+    opcode(Lda, ZeroPageX, 4, false, true),   // 0xb5
+    opcode(Ldx, ZeroPageY, 4, false, true),   // 0xb6
+    opcode(Lax, ZeroPageY, 4, false, false),  // 0xb7
+    opcode(Clv, Implied, 2, false, true),     // 0xb8
+    opcode(Lda, AbsoluteY, 4, true, true),    // 0xb9
+    opcode(Tsx, Implied, 2, false, true),     // 0xba
+    opcode(Las, AbsoluteY, 4, true, false),   // 0xbb
+    opcode(Ldy, AbsoluteX, 4, true, true),    // 0xbc
+    opcode(Lda, AbsoluteX, 4, true, true),    // 0xbd
+    opcode(Ldx, AbsoluteY, 4, true, true),    // 0xbe
+    opcode(Lax, AbsoluteY, 4, true, false),   // 0xbf
+    opcode(Cpy, Immediate, 2, false, true),   // 0xc0
+    opcode(Cmp, IndirectX, 6, false, true),   // 0xc1
+    opcode(Nop, Immediate, 2, false, false),  // 0xc2
+    opcode(Dcp, IndirectX, 8, false, false),  // 0xc3
+    opcode(Cpy, ZeroPage, 3, false, true),    // 0xc4
+    opcode(Cmp, ZeroPage, 3, false, true),    // 0xc5
+    opcode(Dec, ZeroPage, 5, false, true),    // 0xc6
+    opcode(Dcp, ZeroPage, 5, false, false),   // 0xc7
+    opcode(Iny, Implied, 2, false, true),     // 0xc8
+    opcode(Cmp, Immediate, 2, false, true),   // 0xc9
+    opcode(Dex, Implied, 2, false, true),     // 0xca
+    opcode(Axs, Immediate, 2, false, false),  // 0xcb
+    opcode(Cpy, Absolute, 4, false, true),    // 0xcc
+    opcode(Cmp, Absolute, 4, false, true),    // 0xcd
+    opcode(Dec, Absolute, 6, false, true),    // 0xce
+    opcode(Dcp, Absolute, 6, false, false),   // 0xcf
+    opcode(Bne, Relative, 2, true, true),     // 0xd0
+    opcode(Cmp, IndirectY, 5, true, true),    // 0xd1
+    opcode(Jam, Implied, 0, false, false),    // 0xd2
+    opcode(Dcp, IndirectY, 8, false, false),  // 0xd3
+    opcode(Nop, ZeroPageX, 4, false, false),  // 0xd4
+    opcode(Cmp, ZeroPageX, 4, false, true),   // 0xd5
+    opcode(Dec, ZeroPageX, 6, false, true),   // 0xd6
+    opcode(Dcp, ZeroPageX, 6, false, false),  // 0xd7
+    opcode(Cld, Implied, 2, false, true),     // 0xd8
+    opcode(Cmp, AbsoluteY, 4, true, true),    // 0xd9
+    opcode(Nop, Implied, 2, false, false),    // 0xda
+    opcode(Dcp, AbsoluteY, 7, false, false),  // 0xdb
+    opcode(Nop, AbsoluteX, 4, true, false),   // 0xdc
+    opcode(Cmp, AbsoluteX, 4, true, true),    // 0xdd
+    opcode(Dec, AbsoluteX, 7, false, true),   // 0xde
+    opcode(Dcp, AbsoluteX, 7, false, false),  // 0xdf
+    opcode(Cpx, Immediate, 2, false, true),   // 0xe0
+    opcode(Sbc, IndirectX, 6, false, true),   // 0xe1
+    opcode(Nop, Immediate, 2, false, false),  // 0xe2
+    opcode(Isb, IndirectX, 8, false, false),  // 0xe3
+    opcode(Cpx, ZeroPage, 3, false, true),    // 0xe4
+    opcode(Sbc, ZeroPage, 3, false, true),    // 0xe5
+    opcode(Inc, ZeroPage, 5, false, true),    // 0xe6
+    opcode(Isb, ZeroPage, 5, false, false),   // 0xe7
+    opcode(Inx, Implied, 2, false, true),     // 0xe8
+    opcode(Sbc, Immediate, 2, false, true),   // 0xe9
+    opcode(Nop, Implied, 2, false, true),     // 0xea
+    opcode(Sbc, Immediate, 2, false, false),  // 0xeb
+    opcode(Cpx, Absolute, 4, false, true),    // 0xec
+    opcode(Sbc, Absolute, 4, false, true),    // 0xed
+    opcode(Inc, Absolute, 6, false, true),    // 0xee
+    opcode(Isb, Absolute, 6, false, false),   // 0xef
+    opcode(Beq, Relative, 2, true, true),     // 0xf0
+    opcode(Sbc, IndirectY, 5, true, true),    // 0xf1
+    opcode(Jam, Implied, 0, false, false),    // 0xf2
+    opcode(Isb, IndirectY, 8, false, false),  // 0xf3
+    opcode(Nop, ZeroPageX, 4, false, false),  // 0xf4
+    opcode(Sbc, ZeroPageX, 4, false, true),   // 0xf5
+    opcode(Inc, ZeroPageX, 6, false, true),   // 0xf6
+    opcode(Isb, ZeroPageX, 6, false, false),  // 0xf7
+    opcode(Sed, Implied, 2, false, true),     // 0xf8
+    opcode(Sbc, AbsoluteY, 4, true, true),    // 0xf9
+    opcode(Nop, Implied, 2, false, false),    // 0xfa
+    opcode(Isb, AbsoluteY, 7, false, false),  // 0xfb
+    opcode(Nop, AbsoluteX, 4, true, false),   // 0xfc
+    opcode(Sbc, AbsoluteX, 4, true, true),    // 0xfd
+    opcode(Inc, AbsoluteX, 7, false, true),   // 0xfe
+    opcode(Isb, AbsoluteX, 7, false, false),  // 0xff
+];
+
+impl Opcode {
+    // the operand in ca65 syntax like nestest.log shows it, bytes are the whole instruction at
+    // address and label names the addresses that have one
+    pub(crate) fn format_operand<L>(&self, address: u16, bytes: &[u8], label: L) -> String
+    where
+        L: Fn(u16) -> Option<String>,
+    {
+        let byte = *bytes.get(1).unwrap_or(&0);
+        let word = (*bytes.get(2).unwrap_or(&0) as u16) << 8 | byte as u16;
+        let zero_page = || label(byte as u16).unwrap_or_else(|| format!("${:02X}", byte));
+        let absolute = |word: u16| label(word).unwrap_or_else(|| format!("${:04X}", word));
+
+        match self.mode {
+            Implied => String::new(),
+            Accumulator => "A".to_string(),
+            Immediate => format!("#${:02X}", byte),
+            ZeroPage => zero_page(),
+            ZeroPageX => format!("{},X", zero_page()),
+            ZeroPageY => format!("{},Y", zero_page()),
+            Absolute => absolute(word),
+            AbsoluteX => format!("{},X", absolute(word)),
+            AbsoluteY => format!("{},Y", absolute(word)),
+            Indirect => format!("({})", absolute(word)),
+            IndirectX => format!("({},X)", zero_page()),
+            IndirectY => format!("({}),Y", zero_page()),
+            Relative => absolute(branch_target(address, byte)),
+        }
+    }
+}
+
+pub(crate) fn branch_target(address: u16, offset: u8) -> u16 {
+    address.wrapping_add(2).wrapping_add(offset as i8 as u16)
+}
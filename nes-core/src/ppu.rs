@@ -1,6 +1,127 @@
+use crate::mappers::Mirroring;
+use crate::{Catridge, Error};
+
+pub(crate) const DOTS_PER_SCANLINE: u16 = 341;
+// scanlines 0-239 are visible, vblank starts at 241 and 261 is the pre-render line
+pub(crate) const SCANLINES_PER_FRAME: u16 = 262;
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+const VBLANK_SCANLINE: u16 = 241;
+const PRE_RENDER_SCANLINE: u16 = 261;
+
+const PPUCTRL: u16 = 0;
+const PPUMASK: u16 = 1;
+const PPUSTATUS: u16 = 2;
+const OAMADDR: u16 = 3;
+const OAMDATA: u16 = 4;
+const PPUSCROLL: u16 = 5;
+const PPUADDR: u16 = 6;
+const PPUDATA: u16 = 7;
+
+const CTRL_NAMETABLE: u8 = 0x03;
+const CTRL_INCREMENT_32: u8 = 0x04;
+const CTRL_SPRITE_TABLE: u8 = 0x08;
+const CTRL_BACKGROUND_TABLE: u8 = 0x10;
+const CTRL_SPRITE_SIZE: u8 = 0x20;
+const CTRL_NMI_ENABLE: u8 = 0x80;
+const MASK_GREYSCALE: u8 = 0x01;
+const MASK_SHOW_BACKGROUND_LEFT: u8 = 0x02;
+const MASK_SHOW_SPRITES_LEFT: u8 = 0x04;
+const MASK_SHOW_BACKGROUND: u8 = 0x08;
+const MASK_SHOW_SPRITES: u8 = 0x10;
+const STATUS_SPRITE_OVERFLOW: u8 = 0x20;
+const STATUS_SPRITE_ZERO_HIT: u8 = 0x40;
+const STATUS_VBLANK: u8 = 0x80;
+
+// the parts of the loopy v and t registers, yyy NN YYYYY XXXXX for fine y, nametable, coarse y and
+// coarse x
+const COARSE_X: u16 = 0x001f;
+const COARSE_Y: u16 = 0x03e0;
+const NAMETABLE_X: u16 = 0x0400;
+const NAMETABLE_Y: u16 = 0x0800;
+const FINE_Y: u16 = 0x7000;
+const HORIZONTAL_BITS: u16 = COARSE_X | NAMETABLE_X;
+const VERTICAL_BITS: u16 = FINE_Y | NAMETABLE_Y | COARSE_Y;
+
+const MAX_SPRITES_PER_LINE: usize = 8;
+const ATTRIBUTE_PALETTE: u8 = 0x03;
+const ATTRIBUTE_BEHIND_BACKGROUND: u8 = 0x20;
+const ATTRIBUTE_FLIP_HORIZONTAL: u8 = 0x40;
+const ATTRIBUTE_FLIP_VERTICAL: u8 = 0x80;
+
 pub struct PPU {
     nametables: [[u8; 1024]; 2],
     palletes: [u8; 32],
+    oam: [u8; 256],
+    // the sprites the evaluation found for the next line
+    secondary_oam: [u8; 32],
+
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_address: u8,
+    // the current vram address (loopy v) is both where ppudata reads and writes go and where
+    // rendering fetches from. ppuctrl, ppuscroll and ppuaddr write the temporary one (loopy t)
+    // which gets copied over at the start of each line and frame
+    vram_address: u16,
+    temp_vram_address: u16,
+    fine_x_scroll: u8,
+    // ppuscroll and ppuaddr share this to tell the first write from the second (loopy w)
+    write_toggle: bool,
+    // reads of ppudata below the palettes return what the previous read fetched
+    read_buffer: u8,
+    // the data bus between the cpu and the ppu keeps the last value put on it which shows up in
+    // the unused bits of reads and when reading write only registers
+    io_latch: u8,
+    // reading ppustatus right before vblank starts keeps the flag from being set this frame
+    suppress_vblank: bool,
+
+    // what the background fetches got for the next tile
+    next_tile: u8,
+    next_attribute: u8,
+    next_pattern_low: u8,
+    next_pattern_high: u8,
+    // the high bytes are the tile being drawn and the low bytes the one after it, the attribute
+    // shifters hold each palette bit expanded to 8 pixels
+    pattern_shifter_low: u16,
+    pattern_shifter_high: u16,
+    attribute_shifter_low: u16,
+    attribute_shifter_high: u16,
+
+    // how many sprites secondary oam has and if sprite 0 is one of them
+    next_sprite_count: usize,
+    next_sprite_zero: bool,
+    // the sprites fetched for the line being drawn
+    sprite_count: usize,
+    sprite_zero_on_line: bool,
+    sprite_pattern_low: [u8; MAX_SPRITES_PER_LINE],
+    sprite_pattern_high: [u8; MAX_SPRITES_PER_LINE],
+    sprite_attributes: [u8; MAX_SPRITES_PER_LINE],
+    sprite_x: [u8; MAX_SPRITES_PER_LINE],
+
+    // palette ram values for each pixel of the last frame, which are indices into the nes's
+    // system palette. color emphasis isn't applied
+    frame_buffer: Vec<u8>,
+
+    dot: u16,
+    scanline: u16,
+    frame: u64,
+}
+
+impl Default for PPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// palette entries for the sprite backdrops are mirrors of the background ones
+fn palette_index(address: u16) -> usize {
+    let index = address as usize & 0x1f;
+    if index & 0x13 == 0x10 {
+        index & 0x0f
+    } else {
+        index
+    }
 }
 
 impl PPU {
@@ -8,28 +129,677 @@ impl PPU {
         PPU {
             nametables: [[0; 1024]; 2],
             palletes: [0; 32],
+            oam: [0; 256],
+            secondary_oam: [0xff; 32],
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_address: 0,
+            vram_address: 0,
+            temp_vram_address: 0,
+            fine_x_scroll: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            io_latch: 0,
+            suppress_vblank: false,
+            next_tile: 0,
+            next_attribute: 0,
+            next_pattern_low: 0,
+            next_pattern_high: 0,
+            pattern_shifter_low: 0,
+            pattern_shifter_high: 0,
+            attribute_shifter_low: 0,
+            attribute_shifter_high: 0,
+            next_sprite_count: 0,
+            next_sprite_zero: false,
+            sprite_count: 0,
+            sprite_zero_on_line: false,
+            sprite_pattern_low: [0; MAX_SPRITES_PER_LINE],
+            sprite_pattern_high: [0; MAX_SPRITES_PER_LINE],
+            sprite_attributes: [0; MAX_SPRITES_PER_LINE],
+            sprite_x: [0; MAX_SPRITES_PER_LINE],
+            frame_buffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            dot: 0,
+            scanline: 0,
+            frame: 0,
+        }
+    }
+
+    // runs a single dot, the cpu bus runs 3 of these every cycle. the catridge is where the
+    // pattern tables get fetched from
+    pub fn clock(&mut self, catridge: Option<&Catridge>) {
+        self.dot += 1;
+
+        // odd frames skip the last dot of the pre-render line when rendering
+        let skip_dot = self.scanline == PRE_RENDER_SCANLINE
+            && self.dot == DOTS_PER_SCANLINE - 1
+            && self.frame % 2 == 1
+            && self.is_rendering_enabled();
+        if self.dot == DOTS_PER_SCANLINE || skip_dot {
+            self.dot = 0;
+            self.scanline += 1;
+            if self.scanline == SCANLINES_PER_FRAME {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+
+        if self.is_rendering_enabled() && self.is_rendering_line() {
+            self.fetch_background(catridge);
+            self.update_scroll();
+            self.update_sprites(catridge);
+        }
+
+        if self.scanline < SCREEN_HEIGHT as u16 && (1..=SCREEN_WIDTH as u16).contains(&self.dot) {
+            self.draw_pixel();
+        }
+
+        if self.dot == 1 {
+            match self.scanline {
+                VBLANK_SCANLINE => {
+                    if !self.suppress_vblank {
+                        self.status |= STATUS_VBLANK;
+                    }
+                    self.suppress_vblank = false;
+                }
+                PRE_RENDER_SCANLINE => {
+                    self.status &=
+                        !(STATUS_VBLANK | STATUS_SPRITE_ZERO_HIT | STATUS_SPRITE_OVERFLOW)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn ctrl(&self) -> u8 {
+        self.ctrl
+    }
+
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    pub fn status(&self) -> u8 {
+        self.status
+    }
+
+    pub fn vram_address(&self) -> u16 {
+        self.vram_address
+    }
+
+    pub fn temp_vram_address(&self) -> u16 {
+        self.temp_vram_address
+    }
+
+    pub fn fine_x_scroll(&self) -> u8 {
+        self.fine_x_scroll
+    }
+
+    pub fn write_toggle(&self) -> bool {
+        self.write_toggle
+    }
+
+    // the /nmi output to the cpu, turning nmis on during vblank raises it right away. the cpu only
+    // looks at it between its cycles so a ppustatus read clearing the flag in the same cycle it got
+    // set means no nmi that frame
+    pub fn nmi_line(&self) -> bool {
+        self.status & STATUS_VBLANK != 0 && self.ctrl & CTRL_NMI_ENABLE != 0
+    }
+
+    pub fn is_rendering_enabled(&self) -> bool {
+        self.mask & (MASK_SHOW_BACKGROUND | MASK_SHOW_SPRITES) != 0
+    }
+
+    fn is_rendering_line(&self) -> bool {
+        self.scanline < 240 || self.scanline == PRE_RENDER_SCANLINE
+    }
+
+    // moves the vram address along with the background fetches while rendering
+    fn update_scroll(&mut self) {
+        if !self.is_rendering_line() {
+            return;
+        }
+
+        match self.dot {
+            // the fetches for the next tile finish every 8 dots, the first two tiles of the next
+            // line are fetched at the end of this one
+            8..=255 | 328 | 336 if self.dot.is_multiple_of(8) => self.increment_coarse_x(),
+            256 => {
+                self.increment_coarse_x();
+                self.increment_y();
+            }
+            257 => {
+                self.vram_address =
+                    self.vram_address & !HORIZONTAL_BITS | self.temp_vram_address & HORIZONTAL_BITS
+            }
+            280..=304 if self.scanline == PRE_RENDER_SCANLINE => {
+                self.vram_address =
+                    self.vram_address & !VERTICAL_BITS | self.temp_vram_address & VERTICAL_BITS
+            }
+            _ => (),
+        }
+    }
+
+    // wraps around into the horizontally neighbouring nametable
+    fn increment_coarse_x(&mut self) {
+        if self.vram_address & COARSE_X == COARSE_X {
+            self.vram_address &= !COARSE_X;
+            self.vram_address ^= NAMETABLE_X;
+        } else {
+            self.vram_address += 1;
+        }
+    }
+
+    // the nametables are 30 tiles high, coarse y can still be set to 30 or 31 in which case the
+    // attributes get read as tiles and it wraps without switching nametables
+    fn increment_y(&mut self) {
+        if self.vram_address & FINE_Y != FINE_Y {
+            self.vram_address += 0x1000;
+            return;
+        }
+
+        self.vram_address &= !FINE_Y;
+        let coarse_y = match (self.vram_address & COARSE_Y) >> 5 {
+            29 => {
+                self.vram_address ^= NAMETABLE_Y;
+                0
+            }
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+        self.vram_address = self.vram_address & !COARSE_Y | coarse_y << 5;
+    }
+
+    pub fn oam(&self) -> &[u8; 256] {
+        &self.oam
+    }
+
+    // SCREEN_WIDTH * SCREEN_HEIGHT system palette indices, rows are filled in as they get drawn
+    // so this is only a whole frame while in vblank
+    pub fn frame_buffer(&self) -> &[u8] {
+        &self.frame_buffer
+    }
+
+    // the same schedule as the hardware, each tile takes 8 dots with a nametable, attribute and
+    // two pattern fetches and is loaded into the shifters once done
+    fn fetch_background(&mut self, catridge: Option<&Catridge>) {
+        let dot = self.dot;
+        if matches!(dot, 2..=257 | 322..=337) {
+            self.pattern_shifter_low <<= 1;
+            self.pattern_shifter_high <<= 1;
+            self.attribute_shifter_low <<= 1;
+            self.attribute_shifter_high <<= 1;
+        }
+
+        if matches!(dot, 9..=257 | 329..=337) && (dot - 1).is_multiple_of(8) {
+            self.load_shifters();
         }
+
+        if !matches!(dot, 1..=256 | 321..=336) {
+            return;
+        }
+
+        let v = self.vram_address;
+        match (dot - 1) % 8 {
+            0 => self.next_tile = self.peek_memory(0x2000 | v & 0x0fff, catridge),
+            2 => {
+                let address = 0x23c0 | v & 0x0c00 | (v >> 4) & 0x38 | (v >> 2) & 0x07;
+                let mut attribute = self.peek_memory(address, catridge);
+                // each attribute byte covers 4x4 tiles with 2 bits for each 2x2 quarter
+                if v & 0x0040 != 0 {
+                    attribute >>= 4;
+                }
+                if v & 0x0002 != 0 {
+                    attribute >>= 2;
+                }
+                self.next_attribute = attribute & 0x03;
+            }
+            4 => self.next_pattern_low = self.peek_memory(self.pattern_address(), catridge),
+            6 => self.next_pattern_high = self.peek_memory(self.pattern_address() + 8, catridge),
+            _ => (),
+        }
+    }
+
+    fn pattern_address(&self) -> u16 {
+        let table = if self.ctrl & CTRL_BACKGROUND_TABLE != 0 {
+            0x1000
+        } else {
+            0
+        };
+        table + self.next_tile as u16 * 16 + (self.vram_address >> 12)
+    }
+
+    fn load_shifters(&mut self) {
+        let expand = |bit: u8| if bit != 0 { 0xff } else { 0x00 };
+        self.pattern_shifter_low = self.pattern_shifter_low & 0xff00 | self.next_pattern_low as u16;
+        self.pattern_shifter_high =
+            self.pattern_shifter_high & 0xff00 | self.next_pattern_high as u16;
+        self.attribute_shifter_low =
+            self.attribute_shifter_low & 0xff00 | expand(self.next_attribute & 0x01);
+        self.attribute_shifter_high =
+            self.attribute_shifter_high & 0xff00 | expand(self.next_attribute & 0x02);
+    }
+
+    fn sprite_height(&self) -> i32 {
+        if self.ctrl & CTRL_SPRITE_SIZE != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    // secondary oam gets cleared at the start of the line, filled in by the time the background
+    // fetches are done and the sprites in it get fetched during hblank
+    fn update_sprites(&mut self, catridge: Option<&Catridge>) {
+        match self.dot {
+            // the pre-render line doesn't evaluate anything so no sprites show up on line 0
+            1 => {
+                self.secondary_oam = [0xff; 32];
+                self.next_sprite_count = 0;
+                self.next_sprite_zero = false;
+            }
+            256 if self.scanline != PRE_RENDER_SCANLINE => self.evaluate_sprites(),
+            257..=320 => {
+                self.oam_address = 0;
+                if self.dot == 320 {
+                    self.fetch_sprites(catridge);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // finds the first 8 sprites on the next line, sprite y positions are one less than the line
+    // they start on
+    fn evaluate_sprites(&mut self) {
+        let line = self.scanline as i32;
+        let height = self.sprite_height();
+        let in_range = |y: u8| (0..height).contains(&(line - y as i32));
+
+        let mut count = 0;
+        let mut n = 0;
+        while n < 64 && count < MAX_SPRITES_PER_LINE {
+            let y = self.oam[n * 4];
+            self.secondary_oam[count * 4] = y;
+            if in_range(y) {
+                self.secondary_oam[count * 4..count * 4 + 4]
+                    .copy_from_slice(&self.oam[n * 4..n * 4 + 4]);
+                if n == 0 {
+                    self.next_sprite_zero = true;
+                }
+                count += 1;
+            }
+            n += 1;
+        }
+
+        // looking for a ninth sprite the hardware increments the byte within the sprite along
+        // with the sprite so it goes diagonally and treats tiles, attributes and x positions as y
+        let mut m = 0;
+        while n < 64 {
+            if in_range(self.oam[n * 4 + m]) {
+                self.status |= STATUS_SPRITE_OVERFLOW;
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 0x03;
+        }
+
+        self.next_sprite_count = count;
+    }
+
+    fn fetch_sprites(&mut self, catridge: Option<&Catridge>) {
+        let height = self.sprite_height();
+        for index in 0..self.next_sprite_count {
+            let sprite = &self.secondary_oam[index * 4..index * 4 + 4];
+            let (y, tile, attributes, x) = (sprite[0], sprite[1], sprite[2], sprite[3]);
+
+            let mut row = self.scanline as i32 - y as i32;
+            if attributes & ATTRIBUTE_FLIP_VERTICAL != 0 {
+                row = height - 1 - row;
+            }
+
+            // 8x16 sprites pick their pattern table with the lowest bit of the tile and are drawn
+            // from two tiles in a row
+            let (table, tile) = if height == 16 {
+                let tile = tile & 0xfe;
+                let tile = if row >= 8 { tile + 1 } else { tile };
+                ((sprite[1] as u16 & 0x01) * 0x1000, tile)
+            } else if self.ctrl & CTRL_SPRITE_TABLE != 0 {
+                (0x1000, tile)
+            } else {
+                (0x0000, tile)
+            };
+            let address = table + tile as u16 * 16 + (row & 0x07) as u16;
+
+            let mut low = self.peek_memory(address, catridge);
+            let mut high = self.peek_memory(address + 8, catridge);
+            if attributes & ATTRIBUTE_FLIP_HORIZONTAL != 0 {
+                low = low.reverse_bits();
+                high = high.reverse_bits();
+            }
+
+            self.sprite_pattern_low[index] = low;
+            self.sprite_pattern_high[index] = high;
+            self.sprite_attributes[index] = attributes;
+            self.sprite_x[index] = x;
+        }
+
+        self.sprite_count = self.next_sprite_count;
+        self.sprite_zero_on_line = self.next_sprite_zero;
+    }
+
+    // the first sprite with a pixel here wins even if it goes behind the background, returns its
+    // index, pixel, attributes
+    fn sprite_pixel(&self, x: usize) -> Option<(usize, u16, u8)> {
+        (0..self.sprite_count).find_map(|index| {
+            let offset = x.checked_sub(self.sprite_x[index] as usize)?;
+            if offset >= 8 {
+                return None;
+            }
+
+            let bit = 7 - offset;
+            let pixel = (self.sprite_pattern_high[index] >> bit & 0x01) as u16 * 2
+                + (self.sprite_pattern_low[index] >> bit & 0x01) as u16;
+            if pixel == 0 {
+                None
+            } else {
+                Some((index, pixel, self.sprite_attributes[index]))
+            }
+        })
+    }
+
+    fn draw_pixel(&mut self) {
+        let x = self.dot as usize - 1;
+        let y = self.scanline as usize;
+
+        let mut color_address = 0x3f00;
+        if self.is_rendering_enabled() {
+            let show_background = self.mask & MASK_SHOW_BACKGROUND != 0
+                && (x >= 8 || self.mask & MASK_SHOW_BACKGROUND_LEFT != 0);
+            let (pixel, palette) = if show_background {
+                let bit = 0x8000 >> self.fine_x_scroll;
+                let pixel = (self.pattern_shifter_high & bit != 0) as u16 * 2
+                    + (self.pattern_shifter_low & bit != 0) as u16;
+                let palette = (self.attribute_shifter_high & bit != 0) as u16 * 2
+                    + (self.attribute_shifter_low & bit != 0) as u16;
+                (pixel, palette)
+            } else {
+                (0, 0)
+            };
+            if pixel != 0 {
+                color_address = 0x3f00 | palette << 2 | pixel;
+            }
+
+            let show_sprites = self.mask & MASK_SHOW_SPRITES != 0
+                && (x >= 8 || self.mask & MASK_SHOW_SPRITES_LEFT != 0);
+            if let Some((index, sprite_pixel, attributes)) =
+                self.sprite_pixel(x).filter(|_| show_sprites)
+            {
+                // sprite 0 hits whenever both are opaque, priority doesn't matter but the last
+                // pixel never hits
+                if index == 0 && self.sprite_zero_on_line && pixel != 0 && x != 255 {
+                    self.status |= STATUS_SPRITE_ZERO_HIT;
+                }
+
+                if pixel == 0 || attributes & ATTRIBUTE_BEHIND_BACKGROUND == 0 {
+                    let palette = (attributes & ATTRIBUTE_PALETTE) as u16;
+                    color_address = 0x3f10 | palette << 2 | sprite_pixel;
+                }
+            }
+        } else if self.vram_address & 0x3fff >= 0x3f00 {
+            // with rendering off the backdrop comes from wherever the vram address points into
+            // the palettes
+            color_address = self.vram_address & 0x3fff;
+        }
+
+        let mut color = self.palletes[palette_index(color_address)];
+        if self.mask & MASK_GREYSCALE != 0 {
+            color &= 0x30;
+        }
+        self.frame_buffer[y * SCREEN_WIDTH + x] = color;
+    }
+
+    // which of the two nametables in the ppu an address in $2000-$3eff ends up in. four screen
+    // catridges bring their own extra 2kb which isn't emulated so those act like vertical mirroring
+    fn nametable_index(address: u16, mirroring: Mirroring) -> (usize, usize) {
+        let table = (address as usize >> 10) & 0x03;
+        let index = match mirroring {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical | Mirroring::FourScreen => table & 0x01,
+        };
+        (index, address as usize & 0x03ff)
     }
 
-    pub fn read_register(&self, address: u16) -> u8 {
+    fn mirroring(catridge: Option<&Catridge>) -> Mirroring {
+        catridge.map_or(Mirroring::Horizontal, Catridge::mirroring)
+    }
+
+    // reads the ppu address space, pattern tables come from the catridge
+    pub fn peek_memory(&self, address: u16, catridge: Option<&Catridge>) -> u8 {
+        let address = address & 0x3fff;
         match address {
-            0x0000..=0x0007 => 0xff,
+            0x0000..=0x1fff => catridge
+                .and_then(|catridge| catridge.ppu_read(address))
+                .unwrap_or(0),
+            0x2000..=0x3eff => {
+                let (index, offset) = Self::nametable_index(address, Self::mirroring(catridge));
+                self.nametables[index][offset]
+            }
+            _ => self.palletes[palette_index(address)],
+        }
+    }
 
-            _ => panic!(
-                "Reading at address 0x{:x} goes out of range of PPU! (0x0000 to 0x0007)",
-                address
-            ),
+    fn write_memory(&mut self, address: u16, data: u8, catridge: Option<&mut Catridge>) {
+        let address = address & 0x3fff;
+        match address {
+            0x0000..=0x1fff => {
+                if let Some(catridge) = catridge {
+                    catridge.ppu_write(address, data);
+                }
+            }
+            0x2000..=0x3eff => {
+                let mirroring = Self::mirroring(catridge.as_deref());
+                let (index, offset) = Self::nametable_index(address, mirroring);
+                self.nametables[index][offset] = data;
+            }
+            // palette entries are only 6 bits wide
+            _ => self.palletes[palette_index(address)] = data & 0x3f,
         }
     }
 
-    pub fn write_register(&mut self, address: u16, data: u8) {
+    // changes the ppu address space for debugging, chr rom included
+    pub fn poke_memory(&mut self, address: u16, data: u8, catridge: Option<&mut Catridge>) {
+        match address & 0x3fff {
+            0x0000..=0x1fff => {
+                if let Some(catridge) = catridge {
+                    catridge.ppu_poke(address & 0x3fff, data);
+                }
+            }
+            _ => self.write_memory(address, data, catridge),
+        }
+    }
+
+    fn increment_vram_address(&mut self) {
+        // while rendering the access bumps the address like the background fetches do instead
+        if self.is_rendering_enabled() && self.is_rendering_line() {
+            self.increment_coarse_x();
+            self.increment_y();
+            return;
+        }
+
+        let increment = if self.ctrl & CTRL_INCREMENT_32 != 0 {
+            32
+        } else {
+            1
+        };
+        self.vram_address = self.vram_address.wrapping_add(increment) & 0x7fff;
+    }
+
+    // palette reads skip the buffer and only drive the low 6 bits of the data bus
+    fn palette_read(&self, address: u16) -> u8 {
+        let mut data = self.palletes[palette_index(address)];
+        if self.mask & MASK_GREYSCALE != 0 {
+            data &= 0x30;
+        }
+        data | self.io_latch & 0xc0
+    }
+
+    fn write_oam(&mut self, data: u8) {
+        // the attribute byte doesn't have bits 2-4 so they always read back as 0
+        let data = if self.oam_address & 0x03 == 0x02 {
+            data & 0xe3
+        } else {
+            data
+        };
+        self.oam[self.oam_address as usize] = data;
+    }
+
+    pub fn read_register(
+        &mut self,
+        address: u16,
+        catridge: Option<&Catridge>,
+    ) -> Result<u8, Error> {
+        let data = match address {
+            PPUSTATUS => {
+                let data = self.status & 0xe0 | self.io_latch & 0x1f;
+                self.status &= !STATUS_VBLANK;
+                if self.scanline == VBLANK_SCANLINE && self.dot == 0 {
+                    self.suppress_vblank = true;
+                }
+                self.write_toggle = false;
+                data
+            }
+            OAMDATA => self.oam[self.oam_address as usize],
+            PPUDATA => {
+                let address = self.vram_address & 0x3fff;
+                let data = if address >= 0x3f00 {
+                    // the buffer gets the nametable byte hidden under the palettes instead
+                    self.read_buffer = self.peek_memory(address - 0x1000, catridge);
+                    self.palette_read(address)
+                } else {
+                    let data = self.read_buffer;
+                    self.read_buffer = self.peek_memory(address, catridge);
+                    data
+                };
+                self.increment_vram_address();
+                data
+            }
+            // the rest are write only
+            0x0000..=0x0007 => self.io_latch,
+
+            _ => return Err(Error::BadRegisterAddress(address)),
+        };
+
+        self.io_latch = data;
+        Ok(data)
+    }
+
+    // what reading the register would return without clearing flags or moving the vram address
+    pub fn peek_register(&self, address: u16) -> Result<u8, Error> {
+        match address {
+            PPUSTATUS => Ok(self.status & 0xe0 | self.io_latch & 0x1f),
+            OAMDATA => Ok(self.oam[self.oam_address as usize]),
+            PPUDATA => {
+                let address = self.vram_address & 0x3fff;
+                if address >= 0x3f00 {
+                    Ok(self.palette_read(address))
+                } else {
+                    Ok(self.read_buffer)
+                }
+            }
+            0x0000..=0x0007 => Ok(self.io_latch),
+
+            _ => Err(Error::BadRegisterAddress(address)),
+        }
+    }
+
+    // sets the register for debugging without the side effects of a write, scroll and address
+    // need two writes so poking them does nothing
+    pub fn poke_register(
+        &mut self,
+        address: u16,
+        data: u8,
+        catridge: Option<&mut Catridge>,
+    ) -> Result<(), Error> {
         match address {
-            0x0000..=0x0007 => (),
+            PPUCTRL => self.ctrl = data,
+            PPUMASK => self.mask = data,
+            PPUSTATUS => self.status = data & 0xe0,
+            OAMADDR => self.oam_address = data,
+            OAMDATA => self.write_oam(data),
+            PPUSCROLL | PPUADDR => (),
+            PPUDATA => self.poke_memory(self.vram_address, data, catridge),
 
-            _ => panic!(
-                "Writing at address 0x{:x} goes out of range of PPU! (0x0000 to 0x0007)",
-                address
-            ),
+            _ => return Err(Error::BadRegisterAddress(address)),
         }
+        Ok(())
+    }
+
+    pub fn write_register(
+        &mut self,
+        address: u16,
+        data: u8,
+        catridge: Option<&mut Catridge>,
+    ) -> Result<(), Error> {
+        match address {
+            PPUCTRL => {
+                self.ctrl = data;
+                self.temp_vram_address = self.temp_vram_address & !(NAMETABLE_X | NAMETABLE_Y)
+                    | ((data & CTRL_NAMETABLE) as u16) << 10;
+            }
+            PPUMASK => self.mask = data,
+            // status is read only but the write still lands on the data bus
+            PPUSTATUS => (),
+            OAMADDR => self.oam_address = data,
+            OAMDATA => {
+                self.write_oam(data);
+                self.oam_address = self.oam_address.wrapping_add(1);
+            }
+            PPUSCROLL => {
+                let data = data as u16;
+                if self.write_toggle {
+                    self.temp_vram_address = self.temp_vram_address & !(FINE_Y | COARSE_Y)
+                        | (data & 0x07) << 12
+                        | (data >> 3) << 5;
+                } else {
+                    self.temp_vram_address = self.temp_vram_address & !COARSE_X | data >> 3;
+                    self.fine_x_scroll = data as u8 & 0x07;
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUADDR => {
+                // the first write also clears the top bit of fine y and the address only takes
+                // effect after the second
+                let data = data as u16;
+                if self.write_toggle {
+                    self.temp_vram_address = self.temp_vram_address & 0x7f00 | data;
+                    self.vram_address = self.temp_vram_address;
+                } else {
+                    self.temp_vram_address = self.temp_vram_address & 0x00ff | (data & 0x3f) << 8;
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUDATA => {
+                self.write_memory(self.vram_address, data, catridge);
+                self.increment_vram_address();
+            }
+
+            _ => return Err(Error::BadRegisterAddress(address)),
+        }
+
+        self.io_latch = data;
+        Ok(())
     }
 }
@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::Symbols;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RoutineKind {
+    Subroutine,
+    Nmi,
+    Irq,
+    Brk,
+}
+
+impl RoutineKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RoutineKind::Subroutine => "JSR",
+            RoutineKind::Nmi => "NMI",
+            RoutineKind::Irq => "IRQ",
+            RoutineKind::Brk => "BRK",
+        }
+    }
+}
+
+// a routine is told apart by where it is in prg rom when it's there since the same address can
+// hold different code in each bank
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct RoutineKey {
+    address: u16,
+    prg_offset: Option<usize>,
+    kind: RoutineKind,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct RoutineStats {
+    calls: u64,
+    inclusive_cycles: u64,
+    exclusive_cycles: u64,
+    longest_call: u64,
+}
+
+#[derive(Clone, Debug)]
+struct Frame {
+    key: RoutineKey,
+    // the stack pointer from before the return address got pushed, the routine has returned once
+    // the stack is back up there
+    caller_sp: u8,
+    start_cycles: u64,
+    child_cycles: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutineProfile {
+    pub address: u16,
+    pub prg_offset: Option<usize>,
+    pub kind: RoutineKind,
+    pub calls: u64,
+    // cycles spent in the routine including what it called
+    pub inclusive_cycles: u64,
+    // cycles spent in the routine's own code
+    pub exclusive_cycles: u64,
+    // the most inclusive cycles a single call took
+    pub longest_call: u64,
+}
+
+impl RoutineProfile {
+    pub fn name(&self, symbols: Option<&Symbols>) -> String {
+        routine_name(&self.key(), symbols)
+    }
+
+    fn key(&self) -> RoutineKey {
+        RoutineKey {
+            address: self.address,
+            prg_offset: self.prg_offset,
+            kind: self.kind,
+        }
+    }
+}
+
+fn routine_name(key: &RoutineKey, symbols: Option<&Symbols>) -> String {
+    match symbols.and_then(|symbols| symbols.label(key.address, key.prg_offset)) {
+        Some(label) => label.to_string(),
+        None => format!("${:04X}", key.address),
+    }
+}
+
+// the name used for cycles that aren't inside any routine like the main loop after reset
+const ROOT_NAME: &str = "<root>";
+
+// keeps a shadow of the call stack from the jsr, rts, rti and interrupt sequences the cpu runs and
+// charges the cycles between them to the routines on it. games that manipulate the stack directly
+// are handled by the stack pointer, a return only finishes the routines it went back past and a
+// call made from further up the stack drops the routines that got abandoned below it
+#[derive(Clone, Debug, Default)]
+pub struct Profiler {
+    // the bus cycle counter wraps so only the difference to the last seen value gets used
+    last_bus_cycles: u32,
+    cycles: u64,
+    start_cycles: u64,
+    stack: Vec<Frame>,
+    routines: HashMap<RoutineKey, RoutineStats>,
+    // exclusive cycles of each distinct call stack for flamegraphs
+    stacks: HashMap<Vec<RoutineKey>, u64>,
+    root_child_cycles: u64,
+}
+
+// true if the stack pointer is at or above the one a frame was entered from
+fn has_returned_past(sp: u8, caller_sp: u8) -> bool {
+    sp.wrapping_sub(caller_sp) as i8 >= 0
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // forgets everything measured so far and starts counting from the bus cycles given, the call
+    // stack is kept so returns still match up
+    pub fn reset(&mut self, bus_cycles: u32) {
+        self.advance(bus_cycles);
+        self.start_cycles = self.cycles;
+        self.routines.clear();
+        self.stacks.clear();
+        self.root_child_cycles = 0;
+        for frame in &mut self.stack {
+            frame.start_cycles = self.cycles;
+            frame.child_cycles = 0;
+        }
+    }
+
+    fn advance(&mut self, bus_cycles: u32) {
+        self.cycles += bus_cycles.wrapping_sub(self.last_bus_cycles) as u64;
+        self.last_bus_cycles = bus_cycles;
+    }
+
+    // sp is the stack pointer after the return address and flags for interrupts got pushed
+    pub fn enter(
+        &mut self,
+        kind: RoutineKind,
+        address: u16,
+        prg_offset: Option<usize>,
+        sp: u8,
+        bus_cycles: u32,
+    ) {
+        self.advance(bus_cycles);
+
+        let pushed = match kind {
+            RoutineKind::Subroutine => 2,
+            _ => 3,
+        };
+        let caller_sp = sp.wrapping_add(pushed);
+        self.unwind(caller_sp);
+
+        let key = RoutineKey {
+            address,
+            prg_offset,
+            kind,
+        };
+        self.routines.entry(key).or_default().calls += 1;
+        self.stack.push(Frame {
+            key,
+            caller_sp,
+            start_cycles: self.cycles,
+            child_cycles: 0,
+        });
+    }
+
+    // sp is the stack pointer after an rts or rti popped the return address
+    pub fn leave(&mut self, sp: u8, bus_cycles: u32) {
+        self.advance(bus_cycles);
+        self.unwind(sp);
+    }
+
+    // the stack is gone after a reset so every routine on it ends
+    pub fn clear_stack(&mut self, bus_cycles: u32) {
+        self.advance(bus_cycles);
+        while !self.stack.is_empty() {
+            self.pop();
+        }
+    }
+
+    // how deep the shadow call stack currently is
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn unwind(&mut self, sp: u8) {
+        while let Some(frame) = self.stack.last() {
+            if !has_returned_past(sp, frame.caller_sp) {
+                break;
+            }
+            self.pop();
+        }
+    }
+
+    fn pop(&mut self) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let inclusive = self.cycles - frame.start_cycles;
+        let exclusive = inclusive - frame.child_cycles;
+
+        // recursive calls are already counted by the outermost one
+        let recursive = self.stack.iter().any(|outer| outer.key == frame.key);
+        let stats = self.routines.entry(frame.key).or_default();
+        if !recursive {
+            stats.inclusive_cycles += inclusive;
+        }
+        stats.exclusive_cycles += exclusive;
+        stats.longest_call = stats.longest_call.max(inclusive);
+
+        let mut path: Vec<RoutineKey> = self.stack.iter().map(|outer| outer.key).collect();
+        path.push(frame.key);
+        *self.stacks.entry(path).or_default() += exclusive;
+
+        match self.stack.last_mut() {
+            Some(parent) => parent.child_cycles += inclusive,
+            None => self.root_child_cycles += inclusive,
+        }
+    }
+
+    // a copy where the routines still running have been charged up to the bus cycles given
+    fn finished(&self, bus_cycles: u32) -> Profiler {
+        let mut profiler = self.clone();
+        profiler.clear_stack(bus_cycles);
+        profiler
+    }
+
+    pub fn total_cycles(&self, bus_cycles: u32) -> u64 {
+        self.cycles + bus_cycles.wrapping_sub(self.last_bus_cycles) as u64 - self.start_cycles
+    }
+
+    // sorted by inclusive cycles with the most expensive routine first
+    pub fn routines(&self, bus_cycles: u32) -> Vec<RoutineProfile> {
+        let finished = self.finished(bus_cycles);
+        let mut routines: Vec<RoutineProfile> = finished
+            .routines
+            .iter()
+            .map(|(key, stats)| RoutineProfile {
+                address: key.address,
+                prg_offset: key.prg_offset,
+                kind: key.kind,
+                calls: stats.calls,
+                inclusive_cycles: stats.inclusive_cycles,
+                exclusive_cycles: stats.exclusive_cycles,
+                longest_call: stats.longest_call,
+            })
+            .collect();
+        routines.sort_by(|a, b| {
+            b.inclusive_cycles
+                .cmp(&a.inclusive_cycles)
+                .then(b.exclusive_cycles.cmp(&a.exclusive_cycles))
+                .then(a.address.cmp(&b.address))
+        });
+        routines
+    }
+
+    pub fn write_table<W: Write>(
+        &self,
+        writer: &mut W,
+        bus_cycles: u32,
+        symbols: Option<&Symbols>,
+    ) -> io::Result<()> {
+        let finished = self.finished(bus_cycles);
+        let total = finished.cycles - finished.start_cycles;
+        let percent = |cycles: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                cycles as f64 * 100.0 / total as f64
+            }
+        };
+
+        writeln!(
+            writer,
+            "{:>8} {:>12} {:>6} {:>12} {:>6} {:>10}  Routine",
+            "Calls", "Inclusive", "%", "Exclusive", "%", "Longest"
+        )?;
+
+        let root = total - finished.root_child_cycles;
+        writeln!(
+            writer,
+            "{:>8} {:>12} {:>6.2} {:>12} {:>6.2} {:>10}  {}",
+            "",
+            total,
+            percent(total),
+            root,
+            percent(root),
+            "",
+            ROOT_NAME
+        )?;
+
+        for routine in self.routines(bus_cycles) {
+            let kind = match routine.kind {
+                RoutineKind::Subroutine => String::new(),
+                kind => format!(" ({})", kind.name()),
+            };
+            writeln!(
+                writer,
+                "{:>8} {:>12} {:>6.2} {:>12} {:>6.2} {:>10}  {}{}",
+                routine.calls,
+                routine.inclusive_cycles,
+                percent(routine.inclusive_cycles),
+                routine.exclusive_cycles,
+                percent(routine.exclusive_cycles),
+                routine.longest_call,
+                routine.name(symbols),
+                kind
+            )?;
+        }
+        Ok(())
+    }
+
+    // the folded stack format that flamegraph.pl and inferno read, one "outer;inner cycles" line
+    // per distinct call stack
+    pub fn write_folded<W: Write>(
+        &self,
+        writer: &mut W,
+        bus_cycles: u32,
+        symbols: Option<&Symbols>,
+    ) -> io::Result<()> {
+        let finished = self.finished(bus_cycles);
+        let root = finished.cycles - finished.start_cycles - finished.root_child_cycles;
+
+        // routines in different banks can end up with the same name so lines get merged by name
+        let mut folded: HashMap<String, u64> = HashMap::new();
+        for (path, &cycles) in &finished.stacks {
+            let names: Vec<String> = path
+                .iter()
+                .map(|key| match key.kind {
+                    RoutineKind::Subroutine => routine_name(key, symbols),
+                    kind => format!("[{}] {}", kind.name(), routine_name(key, symbols)),
+                })
+                // semicolons separate the frames so they can't be part of a name
+                .map(|name| name.replace(';', ":"))
+                .collect();
+            *folded.entry(names.join(";")).or_default() += cycles;
+        }
+        *folded.entry(ROOT_NAME.to_string()).or_default() += root;
+
+        let mut lines: Vec<(String, u64)> = folded
+            .into_iter()
+            .filter(|&(_, cycles)| cycles > 0)
+            .collect();
+        lines.sort();
+
+        for (path, cycles) in lines {
+            writeln!(writer, "{} {}", path, cycles)?;
+        }
+        Ok(())
+    }
+}
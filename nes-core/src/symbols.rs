@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use crate::catridge::PRG_BANK_SIZE;
+use crate::CpuBus;
+
+// a label can't cover more than the whole cpu address space
+const MAX_LABEL_SIZE: usize = 0x10000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for SymbolError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolFormat {
+    // ca65 and ld65 debug info from --dbgfile
+    Dbg,
+    // fceux name lists, bank is none for the game.nes.ram.nl file and the 16kb prg bank for the
+    // game.nes.<bank>.nl files
+    Nl { bank: Option<usize> },
+    // mesen label files
+    Mlb,
+}
+
+impl SymbolFormat {
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let lower = file_name.to_ascii_lowercase();
+        if lower.ends_with(".dbg") {
+            Some(SymbolFormat::Dbg)
+        } else if lower.ends_with(".mlb") {
+            Some(SymbolFormat::Mlb)
+        } else if let Some(stem) = lower.strip_suffix(".nl") {
+            let bank = match stem.rsplit_once('.') {
+                Some((_, "ram")) | None => None,
+                Some((_, bank)) => Some(usize::from_str_radix(bank, 16).ok()?),
+            };
+            Some(SymbolFormat::Nl { bank })
+        } else {
+            None
+        }
+    }
+}
+
+// labels for cpu addresses like ram and registers, and for prg rom offsets which only mean
+// something through whatever bank the mapper has switched in
+#[derive(Clone, Debug, Default)]
+pub struct Symbols {
+    cpu: HashMap<u16, String>,
+    prg: HashMap<usize, String>,
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = text.strip_prefix('$') {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text.trim(), 16).ok()
+}
+
+// the address of a label if all of it fits in the cpu address space
+fn cpu_address(address: u32, size: usize) -> Option<u16> {
+    match (address as usize).checked_add(size) {
+        Some(end) if address <= 0xffff && end <= 0x10000 => Some(address as u16),
+        _ => None,
+    }
+}
+
+// splits the key=value list of a .dbg line, values can be quoted
+fn parse_dbg_fields(text: &str) -> HashMap<&str, &str> {
+    let mut fields = HashMap::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (key, value_start) = match rest.split_once('=') {
+            Some(split) => split,
+            None => break,
+        };
+
+        let (value, next) = if let Some(quoted) = value_start.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let next = quoted[end..].trim_start_matches('"');
+            (&quoted[..end], next.trim_start_matches(','))
+        } else {
+            match value_start.split_once(',') {
+                Some(split) => split,
+                None => (value_start, ""),
+            }
+        };
+
+        fields.insert(key.trim(), value);
+        rest = next;
+    }
+    fields
+}
+
+struct DbgSegment {
+    start: u32,
+    // where the segment is in the prg rom if it's part of it
+    prg_offset: Option<usize>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(format: SymbolFormat, text: &str) -> Result<Self, SymbolError> {
+        let mut symbols = Symbols::new();
+        match format {
+            SymbolFormat::Dbg => symbols.parse_dbg(text)?,
+            SymbolFormat::Nl { bank } => symbols.parse_nl(text, bank)?,
+            SymbolFormat::Mlb => symbols.parse_mlb(text)?,
+        }
+        Ok(symbols)
+    }
+
+    // later labels for the same address replace earlier ones
+    pub fn extend(&mut self, other: Symbols) {
+        self.cpu.extend(other.cpu);
+        self.prg.extend(other.prg);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpu.is_empty() && self.prg.is_empty()
+    }
+
+    // sized labels also name the addresses inside them like name+1 unless they have their own
+    pub fn add_cpu_label(&mut self, address: u16, name: &str, size: usize) {
+        self.cpu.insert(address, name.to_string());
+        for offset in 1..size.min(MAX_LABEL_SIZE) {
+            self.cpu
+                .entry(address.wrapping_add(offset as u16))
+                .or_insert_with(|| format!("{}+{}", name, offset));
+        }
+    }
+
+    pub fn add_prg_label(&mut self, offset: usize, name: &str, size: usize) {
+        self.prg.insert(offset, name.to_string());
+        for index in 1..size {
+            self.prg
+                .entry(offset + index)
+                .or_insert_with(|| format!("{}+{}", name, index));
+        }
+    }
+
+    // prg_offset is where the address lands in prg rom, if it does
+    pub fn label(&self, address: u16, prg_offset: Option<usize>) -> Option<&str> {
+        prg_offset
+            .and_then(|offset| self.prg.get(&offset))
+            .or_else(|| self.cpu.get(&address))
+            .map(String::as_str)
+    }
+
+    // looks the address up through the current banking of the bus
+    pub fn cpu_label<B: CpuBus>(&self, bus: &B, address: u16) -> Option<&str> {
+        self.label(address, bus.prg_rom_offset(address))
+    }
+
+    // finds where a label currently is in the cpu address space, prg labels are only found while
+    // their bank is mapped in
+    pub fn address_of<B: CpuBus>(&self, bus: &B, name: &str) -> Option<u16> {
+        if let Some((&offset, _)) = self.prg.iter().find(|(_, label)| *label == name) {
+            if let Some(address) =
+                (0x4020..=0xffff).find(|&address| bus.prg_rom_offset(address) == Some(offset))
+            {
+                return Some(address);
+            }
+        }
+
+        self.cpu
+            .iter()
+            .find(|(_, label)| *label == name)
+            .map(|(&address, _)| address)
+    }
+
+    fn parse_dbg(&mut self, text: &str) -> Result<(), SymbolError> {
+        let mut segments = HashMap::new();
+        let mut header_size = 0;
+        // line, id, name, parent, segment, value and size of every label
+        let mut labels = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: &str| SymbolError {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let (kind, rest) = match line.split_once(char::is_whitespace) {
+                Some(split) => split,
+                None => continue,
+            };
+            let fields = parse_dbg_fields(rest.trim());
+            let number = |key: &str| fields.get(key).and_then(|value| parse_number(value));
+
+            match kind {
+                "seg" => {
+                    let id = number("id").ok_or_else(|| error("Segment without an id"))?;
+                    let start = number("start").ok_or_else(|| error("Segment without a start"))?;
+
+                    // the ines header is usually output as a segment in front of the prg rom
+                    if fields.get("name") == Some(&"HEADER") && number("ooffs") == Some(0) {
+                        header_size = number("size").unwrap_or(0) as usize;
+                    }
+
+                    let read_only = fields.get("type") == Some(&"ro");
+                    let prg_offset = match number("ooffs") {
+                        Some(offset) if read_only && start >= 0x4020 => Some(offset as usize),
+                        _ => None,
+                    };
+                    segments.insert(id, DbgSegment { start, prg_offset });
+                }
+                // equates are constants that aren't always addresses so only labels are used
+                "sym" if fields.get("type") == Some(&"lab") => {
+                    let name = match fields.get("name") {
+                        Some(name) => name.to_string(),
+                        None => return Err(error("Symbol without a name")),
+                    };
+                    let value = number("val").ok_or_else(|| error("Label without a value"))?;
+                    labels.push((
+                        index + 1,
+                        number("id"),
+                        name,
+                        number("parent"),
+                        number("seg"),
+                        value,
+                        number("size").unwrap_or(1) as usize,
+                    ));
+                }
+                _ => (),
+            }
+        }
+
+        let names: HashMap<u32, String> = labels
+            .iter()
+            .filter_map(|(_, id, name, ..)| Some(((*id)?, name.clone())))
+            .collect();
+
+        for &(line, _, ref name, parent, segment, value, size) in &labels {
+            let error = |message: &str| SymbolError {
+                line,
+                message: message.to_string(),
+            };
+            if size > MAX_LABEL_SIZE {
+                return Err(error("Invalid size"));
+            }
+
+            // cheap locals are only unique within their parent
+            let name = match parent.and_then(|parent| names.get(&parent)) {
+                Some(parent) => format!("{}{}", parent, name),
+                None => name.clone(),
+            };
+
+            let segment = segment.and_then(|segment| segments.get(&segment));
+            match segment.and_then(|segment| Some((segment.start, segment.prg_offset?))) {
+                Some((start, offset)) => {
+                    let relative = value
+                        .checked_sub(start)
+                        .ok_or_else(|| error("Label outside of its segment"))?;
+                    let offset = (offset + relative as usize).checked_sub(header_size);
+                    if let Some(offset) = offset {
+                        self.add_prg_label(offset, &name, size);
+                    }
+                }
+                None => {
+                    let address =
+                        cpu_address(value, size).ok_or_else(|| error("Invalid address"))?;
+                    self.add_cpu_label(address, &name, size);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // lines look like $C000#Reset#comment or $0300/10#buffer# for a 16 byte array
+    fn parse_nl(&mut self, text: &str, bank: Option<usize>) -> Result<(), SymbolError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: &str| SymbolError {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let line = line.trim();
+            let rest = match line.strip_prefix('$') {
+                Some(rest) => rest,
+                None => continue,
+            };
+
+            let mut parts = rest.split('#');
+            let location = parts.next().unwrap_or_default();
+            let name = parts.next().unwrap_or_default().trim();
+            if name.is_empty() {
+                continue;
+            }
+
+            let (address, size) = match location.split_once('/') {
+                Some((address, size)) => (address, parse_hex(size)),
+                None => (location, Some(1)),
+            };
+            let address = parse_hex(address).ok_or_else(|| error("Invalid address"))?;
+            let size = match size {
+                Some(size) if size as usize <= MAX_LABEL_SIZE => size as usize,
+                _ => return Err(error("Invalid size")),
+            };
+
+            match bank {
+                Some(bank) if address >= 0x8000 => {
+                    // the bank comes from the file name so it can be any size
+                    let offset = bank
+                        .checked_mul(PRG_BANK_SIZE)
+                        .and_then(|start| start.checked_add(address as usize % PRG_BANK_SIZE))
+                        .filter(|offset| offset.checked_add(size).is_some())
+                        .ok_or_else(|| error("Invalid bank"))?;
+                    self.add_prg_label(offset, name, size);
+                }
+                _ => {
+                    let address =
+                        cpu_address(address, size).ok_or_else(|| error("Invalid address"))?;
+                    self.add_cpu_label(address, name, size);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // lines look like P:0010:Reset:comment or R:0300-030F:buffer, newer versions of mesen use
+    // names like NesPrgRom instead of the single letters
+    fn parse_mlb(&mut self, text: &str) -> Result<(), SymbolError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: &str| SymbolError {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let mut parts = line.trim().splitn(4, ':');
+            let (kind, range, name) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(kind), Some(range), Some(name)) => (kind, range, name.trim()),
+                _ => continue,
+            };
+
+            // labels can be left empty when there's only a comment
+            if name.is_empty() {
+                continue;
+            }
+
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (parse_hex(start), parse_hex(end)),
+                None => (parse_hex(range), parse_hex(range)),
+            };
+            let (start, end) = match (start, end) {
+                (Some(start), Some(end)) if end >= start => (start, end),
+                _ => return Err(error("Invalid address")),
+            };
+            let size = (end - start) as usize + 1;
+            if size > MAX_LABEL_SIZE {
+                return Err(error("Invalid size"));
+            }
+
+            match kind {
+                "P" | "NesPrgRom" => self.add_prg_label(start as usize, name, size),
+                "R" | "NesInternalRam" | "G" | "NesMemory" => {
+                    let address =
+                        cpu_address(start, size).ok_or_else(|| error("Invalid address"))?;
+                    self.add_cpu_label(address, name, size)
+                }
+                // save and work ram are at $6000-$7fff
+                "S" | "W" | "NesSaveRam" | "NesWorkRam" if end < 0x2000 => {
+                    self.add_cpu_label(0x6000 + start as u16, name, size)
+                }
+                "S" | "W" | "NesSaveRam" | "NesWorkRam" => return Err(error("Invalid address")),
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
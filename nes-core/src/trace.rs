@@ -0,0 +1,177 @@
+use std::fmt;
+use std::io::Write;
+
+use crate::opcodes::AddressingMode::*;
+use crate::opcodes::{Mnemonic, OPCODES};
+use crate::{CpuBus, CpuState, Symbols};
+
+// a single executed instruction along with the cpu state from before it ran
+#[derive(Clone, Debug)]
+pub struct TraceEntry {
+    pub state: CpuState,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub official: bool,
+    // operand text including the resolved addresses and values like nestest.log
+    pub operand: String,
+    pub cycles: u32,
+    pub ppu_scanline: u32,
+    pub ppu_dot: u32,
+}
+
+impl TraceEntry {
+    // only peeks memory so creating the entry doesn't disturb the emulation
+    pub fn new<B: CpuBus>(state: CpuState, bus: &B) -> Self {
+        Self::with_symbols(state, bus, None)
+    }
+
+    // same as new but operand addresses are shown by their labels when there are some
+    pub fn with_symbols<B: CpuBus>(state: CpuState, bus: &B, symbols: Option<&Symbols>) -> Self {
+        let pc = state.pc;
+        let opcode = OPCODES[bus.peek(pc) as usize];
+        let mnemonic = opcode.mnemonic;
+
+        let bytes: Vec<u8> = (0..opcode.size as u16)
+            .map(|offset| bus.peek(pc.wrapping_add(offset)))
+            .collect();
+        let byte = *bytes.get(1).unwrap_or(&0);
+        let word = (*bytes.get(2).unwrap_or(&0) as u16) << 8 | byte as u16;
+
+        let label = |address: u16| {
+            symbols
+                .and_then(|symbols| symbols.cpu_label(bus, address))
+                .map(str::to_string)
+        };
+        let operand = opcode.format_operand(pc, &bytes, label);
+
+        let peek_word_zero_page = |pointer: u8| {
+            let low = bus.peek(pointer as u16) as u16;
+            let high = bus.peek(pointer.wrapping_add(1) as u16) as u16;
+            (high << 8) | low
+        };
+
+        // the addresses and values the instruction works on get shown after the operand
+        let resolved = match opcode.mode {
+            ZeroPage => format!(" = {:02X}", bus.peek(byte as u16)),
+            ZeroPageX | ZeroPageY => {
+                let index = if opcode.mode == ZeroPageX {
+                    state.x
+                } else {
+                    state.y
+                };
+                let address = byte.wrapping_add(index);
+                format!(" @ {:02X} = {:02X}", address, bus.peek(address as u16))
+            }
+            // jumps don't read from the address so there is no value to show
+            Absolute if mnemonic == Mnemonic::Jmp || mnemonic == Mnemonic::Jsr => String::new(),
+            Absolute => format!(" = {:02X}", bus.peek(word)),
+            AbsoluteX | AbsoluteY => {
+                let index = if opcode.mode == AbsoluteX {
+                    state.x
+                } else {
+                    state.y
+                };
+                let address = word.wrapping_add(index as u16);
+                format!(" @ {:04X} = {:02X}", address, bus.peek(address))
+            }
+            Indirect => {
+                // same page boundary bug as the cpu
+                let low = bus.peek(word) as u16;
+                let high = bus.peek((word & 0xff00) | (word.wrapping_add(1) & 0x00ff)) as u16;
+                format!(" = {:04X}", (high << 8) | low)
+            }
+            IndirectX => {
+                let pointer = byte.wrapping_add(state.x);
+                let address = peek_word_zero_page(pointer);
+                format!(
+                    " @ {:02X} = {:04X} = {:02X}",
+                    pointer,
+                    address,
+                    bus.peek(address)
+                )
+            }
+            IndirectY => {
+                let address_abs = peek_word_zero_page(byte);
+                let address = address_abs.wrapping_add(state.y as u16);
+                format!(
+                    " = {:04X} @ {:04X} = {:02X}",
+                    address_abs,
+                    address,
+                    bus.peek(address)
+                )
+            }
+            Implied | Accumulator | Immediate | Relative => String::new(),
+        };
+
+        let (ppu_scanline, ppu_dot) = bus.ppu_position();
+
+        TraceEntry {
+            state,
+            bytes,
+            mnemonic: mnemonic.name(),
+            official: opcode.official,
+            operand: operand + &resolved,
+            cycles: bus.cycles_count(),
+            ppu_scanline: ppu_scanline as u32,
+            ppu_dot: ppu_dot as u32,
+        }
+    }
+}
+
+// formats the entry as a nestest.log line
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let instruction = format!("{} {}", self.mnemonic, self.operand);
+
+        write!(
+            f,
+            "{:04X}  {:<8} {}{:<31} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            self.state.pc,
+            bytes,
+            if self.official { ' ' } else { '*' },
+            instruction.trim_end(),
+            self.state.a,
+            self.state.x,
+            self.state.y,
+            self.state.flags.bits(),
+            self.state.sp,
+            self.ppu_scanline,
+            self.ppu_dot,
+            self.cycles
+        )
+    }
+}
+
+pub trait TraceSink {
+    fn trace(&mut self, entry: &TraceEntry);
+}
+
+impl<F: FnMut(&TraceEntry)> TraceSink for F {
+    fn trace(&mut self, entry: &TraceEntry) {
+        self(entry)
+    }
+}
+
+// writes every entry as a line to something like stdout or a log file
+pub struct TraceWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(writer: W) -> Self {
+        TraceWriter { writer }
+    }
+}
+
+impl<W: Write> TraceSink for TraceWriter<W> {
+    fn trace(&mut self, entry: &TraceEntry) {
+        // tracing is best effort so a failed write shouldn't stop the emulation
+        let _ = writeln!(self.writer, "{}", entry);
+    }
+}
@@ -0,0 +1,80 @@
+extern crate nes_core;
+
+use nes_core::{Flag, CPU};
+
+// runs the program from $0200 in ram
+fn run(program: &[u8], instructions: usize) -> CPU {
+    let mut cpu = CPU::new();
+    cpu.bus.ram[0x0200..0x0200 + program.len()].copy_from_slice(program);
+    cpu.set_pc(0x0200);
+    cpu.set_sp(0xfd);
+    for _ in 0..instructions {
+        cpu.execute_next_instruction().unwrap();
+    }
+    cpu
+}
+
+#[test]
+fn wraps_zero_page_indexing() {
+    // ldx #$02, lda $ff,x
+    let mut cpu = CPU::new();
+    cpu.bus.ram[0x0001] = 0x42;
+    cpu.bus.ram[0x0101] = 0x24;
+    cpu.bus.ram[0x0200..0x0204].copy_from_slice(&[0xa2, 0x02, 0xb5, 0xff]);
+    cpu.set_pc(0x0200);
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.a(), 0x42);
+}
+
+#[test]
+fn wraps_zero_page_pointers() {
+    // ldx #$01, lda ($fe,x) with the pointer at $ff and $00
+    let mut cpu = CPU::new();
+    cpu.bus.ram[0x00ff] = 0x00;
+    cpu.bus.ram[0x0000] = 0x03;
+    cpu.bus.ram[0x0300] = 0x42;
+    cpu.bus.ram[0x0200..0x0204].copy_from_slice(&[0xa2, 0x01, 0xa1, 0xfe]);
+    cpu.set_pc(0x0200);
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.a(), 0x42);
+
+    // ldy #$10, lda ($ff),y
+    cpu.bus.ram[0x0310] = 0x24;
+    cpu.bus.ram[0x0204..0x0208].copy_from_slice(&[0xa0, 0x10, 0xb1, 0xff]);
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.a(), 0x24);
+}
+
+#[test]
+fn wraps_registers_and_memory() {
+    // ldx #$ff, inx
+    let cpu = run(&[0xa2, 0xff, 0xe8], 2);
+    assert_eq!(cpu.x(), 0x00);
+    assert!(cpu.flags().get(Flag::Zero));
+
+    // ldy #$00, dey
+    let cpu = run(&[0xa0, 0x00, 0x88], 2);
+    assert_eq!(cpu.y(), 0xff);
+    assert!(cpu.flags().get(Flag::Negative));
+
+    // inc $10, dec $11
+    let mut cpu = CPU::new();
+    cpu.bus.ram[0x0010] = 0xff;
+    cpu.bus.ram[0x0200..0x0204].copy_from_slice(&[0xe6, 0x10, 0xc6, 0x11]);
+    cpu.set_pc(0x0200);
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.bus.ram[0x0010], 0x00);
+    assert_eq!(cpu.bus.ram[0x0011], 0xff);
+}
+
+#[test]
+fn branches_backwards() {
+    // ldx #$03, dex, bne -3
+    let cpu = run(&[0xa2, 0x03, 0xca, 0xd0, 0xfd], 3);
+    assert_eq!(cpu.x(), 0x02);
+    assert_eq!(cpu.pc(), 0x0202);
+}
@@ -0,0 +1,138 @@
+extern crate nes_core;
+
+use nes_core::{assemble, FlatBus, CPU};
+
+#[test]
+fn assembles_each_addressing_mode() {
+    let program = assemble(
+        "
+        .org $c000
+        sei
+        lda #$10
+        lda $20,x
+        ldx $0200,y
+        asl a
+        jmp ($fffc)
+        sta ($40,x)
+        lax ($40),y
+        lda a:$20
+        stx z:$10,y
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(program.segments.len(), 1);
+    assert_eq!(program.segments[0].origin, 0xc000);
+    #[rustfmt::skip]
+    assert_eq!(
+        program.segments[0].data,
+        [
+            0x78,
+            0xa9, 0x10,
+            0xb5, 0x20,
+            0xbe, 0x00, 0x02,
+            0x0a,
+            0x6c, 0xfc, 0xff,
+            0x81, 0x40,
+            0xb3, 0x40,
+            0xad, 0x20, 0x00,
+            0x96, 0x10,
+        ]
+    );
+}
+
+#[test]
+fn resolves_labels_and_expressions() {
+    let program = assemble(
+        "
+        screen = $2000
+        .org $8000
+        reset:  ldx #0          ; forward references are always absolute
+        @loop:  lda table,x
+                sta screen+$20,x
+                inx
+                cpx #table_end-table
+                bne @loop
+                jmp reset
+        nmi:    rti
+        table:  .byte 1, %10, 'c', \"ab\"
+        table_end:
+                .org $fffa
+                .word nmi, reset, <nmi | >reset << 8
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(program.symbol("reset"), Some(0x8000));
+    assert_eq!(program.symbol("reset@loop"), Some(0x8002));
+    assert_eq!(program.symbol("table"), Some(0x8011));
+
+    let image = program.prg_image(0xc000, 0x4000);
+    assert_eq!(image, None);
+
+    let image = program.prg_image(0x8000, 0x8000).unwrap();
+    #[rustfmt::skip]
+    assert_eq!(
+        image[..0x16],
+        [
+            0xa2, 0x00,
+            0xbd, 0x11, 0x80,
+            0x9d, 0x20, 0x20,
+            0xe8,
+            0xe0, 0x05,
+            0xd0, 0xf5,
+            0x4c, 0x00, 0x80,
+            0x40,
+            0x01, 0x02, 0x63, 0x61, 0x62,
+        ][..]
+    );
+    assert_eq!(image[0x7ffa..], [0x10, 0x80, 0x00, 0x80, 0x10, 0x80]);
+}
+
+#[test]
+fn reports_errors_with_line_numbers() {
+    let error = assemble("nop\nlda ($10),x").unwrap_err();
+    assert_eq!(error.line, 2);
+
+    let error = assemble("nop\n\njmp nowhere").unwrap_err();
+    assert_eq!(error.to_string(), "Line 3: Undefined symbol 'nowhere'");
+
+    let error = assemble(".org $8000\nbeq far\n.org $9000\nfar: rts").unwrap_err();
+    assert_eq!(error.line, 2);
+
+    // overflowing expressions are errors instead of panics
+    let error = assemble("nop\nlda #-(1<<63)").unwrap_err();
+    assert_eq!(error.line, 2);
+    let error = assemble(".word (1<<63)/-1").unwrap_err();
+    assert_eq!(error.to_string(), "Line 1: Division overflow");
+
+    assert!(assemble("ldx ($10),y").is_err());
+    assert!(assemble("foo").is_err());
+}
+
+#[test]
+fn runs_an_assembled_program() {
+    let program = assemble(
+        "
+        .org $0600
+            ldx #5
+            lda #0
+        @add:
+            clc
+            adc #3
+            dex
+            bne @add
+            sta $10
+        ",
+    )
+    .unwrap();
+
+    let mut cpu = CPU::with_bus(FlatBus::new());
+    program.write_to_bus(&mut cpu.bus);
+    cpu.set_pc(0x0600);
+    for _ in 0..23 {
+        cpu.execute_next_instruction().unwrap();
+    }
+
+    assert_eq!(cpu.bus.memory[0x10], 15);
+}
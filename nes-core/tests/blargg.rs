@@ -0,0 +1,93 @@
+extern crate nes_core;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use nes_core::{Catridge, CPU};
+
+const MAX_CYCLES: u32 = 100_000_000;
+// the roms ask for a reset and need it to happen at least 100ms later
+const RESET_DELAY_CYCLES: u32 = 200_000;
+
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_NEEDS_RESET: u8 = 0x81;
+const SIGNATURE: [u8; 3] = [0xde, 0xb0, 0x61];
+
+// runs a rom that reports through $6000 and returns the text it printed on failure
+fn run_rom(path: &Path) -> Result<(), String> {
+    let data = fs::read(path).map_err(|error| error.to_string())?;
+    let catridge = Catridge::new(&data).map_err(|error| error.to_string())?;
+
+    let mut cpu = CPU::new();
+    cpu.bus.connect_catridge(catridge);
+    cpu.reset();
+
+    let mut reset_at = None;
+    while cpu.bus.cycles_count < MAX_CYCLES {
+        cpu.execute_next_instruction()
+            .map_err(|error| error.to_string())?;
+
+        if reset_at.is_some_and(|reset_at| cpu.bus.cycles_count >= reset_at) {
+            cpu.reset();
+            reset_at = None;
+        }
+
+        let prg_ram = cpu.bus.catridge.as_ref().unwrap().prg_ram();
+        // the status byte is only valid once the signature has been written
+        if prg_ram.len() < 4 || prg_ram[1..4] != SIGNATURE {
+            continue;
+        }
+
+        match prg_ram[0] {
+            STATUS_RUNNING => (),
+            STATUS_NEEDS_RESET => {
+                if reset_at.is_none() {
+                    reset_at = Some(cpu.bus.cycles_count + RESET_DELAY_CYCLES);
+                }
+            }
+            0 => return Ok(()),
+            code => {
+                let text: Vec<u8> = prg_ram[4..]
+                    .iter()
+                    .take_while(|&&byte| byte != 0)
+                    .cloned()
+                    .collect();
+                return Err(format!(
+                    "Failed with code {}: {}",
+                    code,
+                    String::from_utf8_lossy(&text).trim()
+                ));
+            }
+        }
+    }
+
+    Err(format!("Didn't finish within {} cycles", MAX_CYCLES))
+}
+
+#[test]
+#[ignore = "needs blargg's test roms in blargg/, see tests/roms/README.md"]
+fn blargg_roms() {
+    let directory = common::test_file_path("blargg");
+    let entries = fs::read_dir(&directory)
+        .unwrap_or_else(|error| panic!("Couldn't read {}: {}", directory.display(), error));
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "nes"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "No roms in {}", directory.display());
+
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            run_rom(path)
+                .err()
+                .map(|error| format!("{}: {}", path.display(), error))
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
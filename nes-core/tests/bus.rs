@@ -0,0 +1,52 @@
+extern crate nes_core;
+
+use nes_core::{assemble, Bus, Catridge, CpuBus};
+
+fn nrom_bus() -> Bus {
+    let program = assemble(
+        "
+        .org $c000
+        reset:  jmp reset
+                .org $fffc
+                .word reset
+        ",
+    )
+    .unwrap();
+
+    let mut rom = b"NES\x1a\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.extend(program.prg_image(0xc000, 0x4000).unwrap());
+
+    let mut bus = Bus::new();
+    bus.connect_catridge(Catridge::new(&rom).unwrap());
+    bus
+}
+
+#[test]
+fn peek_and_poke_take_no_cycles() {
+    let mut bus = nrom_bus();
+
+    bus.poke(0x0801, 0x42);
+    assert_eq!(bus.peek(0x0001), 0x42);
+    assert_eq!(bus.peek(0x1801), 0x42);
+    bus.peek(0x2002);
+    assert_eq!(bus.cycles_count(), 0);
+
+    assert_eq!(bus.read_byte(0x0001), 0x42);
+    assert_eq!(bus.cycles_count(), 1);
+}
+
+#[test]
+fn poke_patches_prg_rom() {
+    let mut bus = nrom_bus();
+    assert_eq!(bus.peek(0xfffc), 0x00);
+    assert_eq!(bus.peek(0xbffd), 0xc0);
+
+    // normal writes can't change rom
+    bus.write_byte(0xc000, 0xea);
+    assert_eq!(bus.peek(0xc000), 0x4c);
+
+    // a single bank is mirrored so the patch shows up in both halves
+    bus.poke(0xc000, 0xea);
+    assert_eq!(bus.peek(0xc000), 0xea);
+    assert_eq!(bus.peek(0x8000), 0xea);
+}
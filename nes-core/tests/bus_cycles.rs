@@ -0,0 +1,103 @@
+extern crate nes_core;
+
+use nes_core::{BusCycle, FlatBus, CPU};
+
+fn read(address: u16, data: u8) -> BusCycle {
+    BusCycle::Read { address, data }
+}
+
+fn write(address: u16, data: u8) -> BusCycle {
+    BusCycle::Write { address, data }
+}
+
+// runs the single instruction at $0600 and returns what it did on the bus
+fn run(bytes: &[u8], setup: fn(&mut CPU<FlatBus>)) -> Vec<BusCycle> {
+    let mut cpu = CPU::with_bus(FlatBus::new());
+    cpu.bus.memory[0x0600..0x0600 + bytes.len()].copy_from_slice(bytes);
+    cpu.set_pc(0x0600);
+    setup(&mut cpu);
+
+    cpu.bus.cycle_log = Some(Vec::new());
+    cpu.execute_next_instruction().unwrap();
+    cpu.bus.cycle_log.unwrap()
+}
+
+#[test]
+fn read_modify_write_writes_the_old_value_back_first() {
+    // inc $0210
+    let cycles = run(&[0xee, 0x10, 0x02], |cpu| cpu.bus.memory[0x0210] = 0x41);
+    assert_eq!(
+        cycles,
+        [
+            read(0x0600, 0xee),
+            read(0x0601, 0x10),
+            read(0x0602, 0x02),
+            read(0x0210, 0x41),
+            write(0x0210, 0x41),
+            write(0x0210, 0x42),
+        ]
+    );
+}
+
+#[test]
+fn indexing_across_a_page_reads_the_wrong_page_first() {
+    // lda $02f0,x
+    let cycles = run(&[0xbd, 0xf0, 0x02], |cpu| {
+        cpu.set_x(0x20);
+        cpu.bus.memory[0x0210] = 0x11;
+        cpu.bus.memory[0x0310] = 0x22;
+    });
+    assert_eq!(
+        cycles,
+        [
+            read(0x0600, 0xbd),
+            read(0x0601, 0xf0),
+            read(0x0602, 0x02),
+            read(0x0210, 0x11),
+            read(0x0310, 0x22),
+        ]
+    );
+}
+
+#[test]
+fn indirect_indexed_stores_always_take_the_dummy_read() {
+    // sta ($10),y
+    let cycles = run(&[0x91, 0x10], |cpu| {
+        cpu.set_a(0x77);
+        cpu.set_y(0x05);
+        cpu.bus.memory[0x10] = 0x00;
+        cpu.bus.memory[0x11] = 0x03;
+    });
+    assert_eq!(
+        cycles,
+        [
+            read(0x0600, 0x91),
+            read(0x0601, 0x10),
+            read(0x0010, 0x00),
+            read(0x0011, 0x03),
+            read(0x0305, 0x00),
+            write(0x0305, 0x77),
+        ]
+    );
+}
+
+#[test]
+fn rts_reads_the_stack_and_skips_the_return_address_byte() {
+    // rts back to a jsr whose last byte is at $06ff
+    let cycles = run(&[0x60], |cpu| {
+        cpu.set_sp(0xfb);
+        cpu.bus.memory[0x01fc] = 0xff;
+        cpu.bus.memory[0x01fd] = 0x06;
+    });
+    assert_eq!(
+        cycles,
+        [
+            read(0x0600, 0x60),
+            read(0x0601, 0x00),
+            read(0x01fb, 0x00),
+            read(0x01fc, 0xff),
+            read(0x01fd, 0x06),
+            read(0x06ff, 0x00),
+        ]
+    );
+}
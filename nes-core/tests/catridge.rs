@@ -0,0 +1,40 @@
+extern crate nes_core;
+
+use nes_core::{CartridgeError, Catridge};
+
+#[test]
+fn rejects_rom_sizes_that_overflow() {
+    // nes 2.0 with an exponent-multiplier prg size of 2^63 * 7
+    let mut rom = b"NES\x1a\xff\x00\x00\x08\x00\x0f\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(16 + 0x4000, 0);
+    assert_eq!(
+        Catridge::new(&rom).err(),
+        Some(CartridgeError::InvalidRomSize)
+    );
+}
+
+#[test]
+fn rejects_roms_without_prg() {
+    let mut rom = b"NES\x1a\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(16 + 0x2000, 0);
+    assert_eq!(
+        Catridge::new(&rom).err(),
+        Some(CartridgeError::MissingPrgRom)
+    );
+}
+
+#[test]
+fn mirrors_prg_smaller_than_a_bank() {
+    // nes 2.0 with 8kb of prg rom in exponent-multiplier form (2^13 * 1)
+    let mut rom = b"NES\x1a\x34\x00\x00\x08\x00\x0f\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(16 + 0x2000, 0);
+    rom[16 + 0x1ffc] = 0x34;
+    rom[16 + 0x1ffd] = 0x12;
+    let catridge = Catridge::new(&rom).unwrap();
+
+    assert_eq!(catridge.prg_rom().len(), 0x2000);
+    assert_eq!(catridge.cpu_read(0xfffc), Some(0x34));
+    assert_eq!(catridge.cpu_read(0xdffd), Some(0x12));
+    assert_eq!(catridge.cpu_peek(0x9ffc), Some(0x34));
+    assert_eq!(catridge.prg_rom_offset(0xfffc), Some(0x1ffc));
+}
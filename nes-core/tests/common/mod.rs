@@ -0,0 +1,28 @@
+// not every test uses every helper
+#![allow(dead_code)]
+
+pub mod json;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// test roms aren't checked in so they get looked up in tests/roms or the directory in NES_TEST_ROMS
+pub fn test_file_path(name: &str) -> PathBuf {
+    let directory = env::var_os("NES_TEST_ROMS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms"));
+    directory.join(name)
+}
+
+// the tests using these are ignored by default so a missing file is a failure once they're run
+pub fn load_test_file(name: &str) -> Vec<u8> {
+    let path = test_file_path(name);
+    fs::read(&path).unwrap_or_else(|error| {
+        panic!(
+            "Couldn't read {}: {} (see tests/roms/README.md)",
+            path.display(),
+            error
+        )
+    })
+}
@@ -0,0 +1,107 @@
+extern crate nes_core;
+
+use nes_core::{CpuState, Flag, StatusFlags, CPU};
+
+#[test]
+fn formats_status_flags() {
+    let mut flags = StatusFlags::from_bits(0x24);
+    assert!(flags.get(Flag::InterruptDisable));
+    assert!(flags.get(Flag::Unused));
+    assert!(!flags.get(Flag::Carry));
+    assert_eq!(flags.to_string(), "nvUbdIzc");
+
+    flags.set(Flag::Carry, true);
+    flags.set(Flag::Negative, true);
+    flags.set(Flag::InterruptDisable, false);
+    assert_eq!(flags.bits(), 0xa1);
+    assert_eq!(flags.to_string(), "NvUbdizC");
+
+    assert_eq!(StatusFlags::default().bits(), 0x00);
+    assert_eq!(StatusFlags::from_bits(0xff).to_string(), "NVUBDIZC");
+}
+
+#[test]
+fn round_trips_the_cpu_state() {
+    let state = CpuState {
+        pc: 0x1234,
+        sp: 0xfd,
+        a: 0x01,
+        x: 0x02,
+        y: 0x03,
+        flags: StatusFlags::from_bits(0xc3),
+    };
+
+    let mut cpu = CPU::new();
+    cpu.set_state(state);
+    assert_eq!(cpu.state(), state);
+    assert_eq!(cpu.pc(), 0x1234);
+    assert_eq!(cpu.sp(), 0xfd);
+    assert_eq!(cpu.a(), 0x01);
+    assert_eq!(cpu.x(), 0x02);
+    assert_eq!(cpu.y(), 0x03);
+    assert_eq!(cpu.flags(), StatusFlags::from_bits(0xc3));
+
+    cpu.set_pc(0x4321);
+    cpu.set_sp(0x80);
+    cpu.set_a(0x04);
+    cpu.set_x(0x05);
+    cpu.set_y(0x06);
+    cpu.set_flags(StatusFlags::from_bits(0x24));
+    assert_eq!(
+        cpu.state(),
+        CpuState {
+            pc: 0x4321,
+            sp: 0x80,
+            a: 0x04,
+            x: 0x05,
+            y: 0x06,
+            flags: StatusFlags::from_bits(0x24),
+        }
+    );
+}
+
+#[test]
+fn runs_from_the_state_it_was_given() {
+    // inx, sec at $0010
+    let mut cpu = CPU::new();
+    cpu.bus.ram[0x0010..0x0012].copy_from_slice(&[0xe8, 0x38]);
+    cpu.set_state(CpuState {
+        pc: 0x0010,
+        x: 0x7f,
+        ..CpuState::default()
+    });
+
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.pc(), 0x0012);
+    assert_eq!(cpu.x(), 0x80);
+    assert!(cpu.flags().get(Flag::Negative));
+    assert!(cpu.flags().get(Flag::Carry));
+}
+
+#[test]
+fn resets_without_clearing_the_registers() {
+    let mut cpu = CPU::new();
+    cpu.set_state(CpuState {
+        pc: 0x1234,
+        sp: 0xfd,
+        a: 0x01,
+        x: 0x02,
+        y: 0x03,
+        flags: StatusFlags::from_bits(0x81),
+    });
+
+    // reset pretends to push three bytes and there's no catridge so the vector is 0
+    cpu.reset();
+    assert_eq!(
+        cpu.state(),
+        CpuState {
+            pc: 0x0000,
+            sp: 0xfa,
+            a: 0x01,
+            x: 0x02,
+            y: 0x03,
+            flags: StatusFlags::from_bits(0xa5),
+        }
+    );
+}
@@ -0,0 +1,141 @@
+extern crate nes_core;
+
+use nes_core::{assemble, Debugger, RunMode, StopReason, WatchKind, Watchpoint, CPU};
+
+const PROGRAM: &str = "
+    .org $0200
+    main:   ldx #0
+    @loop:  jsr double
+            inx
+            cpx #4
+            bne @loop
+            sta $10
+    done:   jmp done
+
+    double: stx $20
+            lda $20
+            asl a
+            rts
+";
+
+fn cpu() -> (CPU, nes_core::Program) {
+    let program = assemble(PROGRAM).unwrap();
+    let mut cpu = CPU::new();
+    program.write_to_bus(&mut cpu.bus);
+    cpu.set_pc(0x0200);
+    (cpu, program)
+}
+
+#[test]
+fn stops_on_breakpoints() {
+    let (mut cpu, program) = cpu();
+    let mut debugger = Debugger::new();
+    let double = program.symbol("double").unwrap();
+    debugger.add_breakpoint(double);
+
+    for _ in 0..4 {
+        assert_eq!(
+            debugger.run(&mut cpu, RunMode::Continue),
+            StopReason::Breakpoint(double)
+        );
+    }
+
+    debugger.remove_breakpoint(double);
+    debugger.instruction_limit = Some(100);
+    assert_eq!(
+        debugger.run(&mut cpu, RunMode::Continue),
+        StopReason::InstructionLimit
+    );
+    assert_eq!(cpu.pc(), program.symbol("done").unwrap());
+    assert_eq!(cpu.bus.ram[0x10], 6);
+}
+
+#[test]
+fn stops_on_watchpoints() {
+    let (mut cpu, program) = cpu();
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint {
+        value: Some(2),
+        ..Watchpoint::new(0x0020, WatchKind::Write)
+    });
+
+    assert_eq!(
+        debugger.run(&mut cpu, RunMode::Continue),
+        StopReason::Watchpoint {
+            kind: WatchKind::Write,
+            address: 0x0020,
+            data: 2,
+        }
+    );
+    // stops after the instruction that did the write
+    assert_eq!(cpu.pc(), program.symbol("double").unwrap() + 2);
+
+    debugger.add_watchpoint(Watchpoint::new(0x0010, WatchKind::Access));
+    assert_eq!(
+        debugger.run(&mut cpu, RunMode::Continue),
+        StopReason::Watchpoint {
+            kind: WatchKind::Write,
+            address: 0x0010,
+            data: 6,
+        }
+    );
+    assert_eq!(cpu.bus.cycle_log, None);
+}
+
+#[test]
+fn steps_over_into_and_out_of_subroutines() {
+    let (mut cpu, program) = cpu();
+    let mut debugger = Debugger::new();
+    let double = program.symbol("double").unwrap();
+
+    assert_eq!(debugger.run(&mut cpu, RunMode::StepInto), StopReason::Done);
+    assert_eq!(cpu.pc(), 0x0202);
+    assert_eq!(debugger.run(&mut cpu, RunMode::StepOver), StopReason::Done);
+    assert_eq!(cpu.pc(), 0x0205);
+
+    debugger.run(&mut cpu, RunMode::StepInto);
+    debugger.run(&mut cpu, RunMode::StepInto);
+    debugger.run(&mut cpu, RunMode::StepInto);
+    assert_eq!(debugger.run(&mut cpu, RunMode::StepInto), StopReason::Done);
+    assert_eq!(cpu.pc(), double);
+
+    debugger.run(&mut cpu, RunMode::StepInto);
+    assert_eq!(debugger.run(&mut cpu, RunMode::StepOut), StopReason::Done);
+    assert_eq!(cpu.pc(), 0x0205);
+    assert_eq!(cpu.a(), 2);
+}
+
+#[test]
+fn runs_to_scanlines_and_frames() {
+    let (mut cpu, _) = cpu();
+    let mut debugger = Debugger::new();
+
+    assert_eq!(
+        debugger.run(&mut cpu, RunMode::Scanline(241)),
+        StopReason::Done
+    );
+    assert_eq!(cpu.bus.ppu.scanline(), 241);
+    assert_eq!(cpu.bus.ppu.frame(), 0);
+
+    debugger.run(&mut cpu, RunMode::Scanline(10));
+    assert_eq!(cpu.bus.ppu.scanline(), 10);
+    assert_eq!(cpu.bus.ppu.frame(), 1);
+
+    debugger.run(&mut cpu, RunMode::Frame(3));
+    assert_eq!(cpu.bus.ppu.scanline(), 0);
+    assert_eq!(cpu.bus.ppu.frame(), 3);
+}
+
+#[test]
+fn reports_errors_as_stop_reasons() {
+    let mut cpu = CPU::new();
+    // jam
+    cpu.bus.poke(0x0000, 0x02);
+    cpu.set_pc(0x0000);
+
+    let mut debugger = Debugger::new();
+    match debugger.run(&mut cpu, RunMode::Continue) {
+        StopReason::Error(nes_core::Error::CpuJam { opcode: 0x02, .. }) => (),
+        reason => panic!("Unexpected stop reason {:?}", reason),
+    }
+}
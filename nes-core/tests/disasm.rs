@@ -0,0 +1,74 @@
+extern crate nes_core;
+
+use nes_core::{disassemble, disassemble_bus, disassemble_prg_bank, Catridge, FlatBus};
+
+#[test]
+fn disassembles_each_addressing_mode() {
+    #[rustfmt::skip]
+    let code = [
+        0x78,             // sei
+        0xa9, 0x10,       // lda #$10
+        0xb5, 0x20,       // lda $20,x
+        0xbe, 0x00, 0x02, // ldx $0200,y
+        0x0a,             // asl a
+        0x6c, 0xfc, 0xff, // jmp ($fffc)
+        0x81, 0x40,       // sta ($40,x)
+        0xb3, 0x40,       // lax ($40),y
+        0xd0, 0xf0,       // bne $c002
+        0x20, 0x34, 0x12, // jsr $1234
+        0x8d,             // sta cut off in the middle
+    ];
+
+    let lines: Vec<String> = disassemble(&code, 0xc000)
+        .iter()
+        .map(|instruction| instruction.to_string())
+        .collect();
+
+    assert_eq!(
+        lines,
+        [
+            "C000  78        SEI",
+            "C001  A9 10     LDA #$10",
+            "C003  B5 20     LDA $20,X",
+            "C005  BE 00 02  LDX $0200,Y",
+            "C008  0A        ASL A",
+            "C009  6C FC FF  JMP ($FFFC)",
+            "C00C  81 40     STA ($40,X)",
+            "C00E  B3 40     LAX ($40),Y",
+            "C010  D0 F0     BNE $C002",
+            "C012  20 34 12  JSR $1234",
+            "C015  8D        .byte $8D",
+        ]
+    );
+}
+
+#[test]
+fn resolves_branch_and_jump_targets() {
+    let mut bus = FlatBus::new();
+    bus.memory[0x8000..0x8005].copy_from_slice(&[0x10, 0x03, 0x4c, 0x00, 0x80]);
+
+    let targets: Vec<Option<u16>> = disassemble_bus(&bus, 0x8000, 0x8004, None)
+        .iter()
+        .map(|instruction| instruction.target)
+        .collect();
+
+    assert_eq!(targets, [Some(0x8005), Some(0x8000)]);
+}
+
+#[test]
+fn rejects_banks_past_the_end_of_prg_rom() {
+    // nrom with a single 16kb bank of nops
+    let mut rom = b"NES\x1a\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(16 + 0x4000, 0xea);
+    rom.resize(16 + 0x4000 + 0x2000, 0);
+    let catridge = Catridge::new(&rom).unwrap();
+
+    let bank = disassemble_prg_bank(&catridge, 0, 0xc000, None).unwrap();
+    assert_eq!(bank.len(), 0x4000);
+    assert_eq!(disassemble_prg_bank(&catridge, 1, 0xc000, None), None);
+    // the bank's offset doesn't fit in a usize
+    assert_eq!(
+        disassemble_prg_bank(&catridge, usize::MAX, 0xc000, None),
+        None
+    );
+}
@@ -0,0 +1,109 @@
+extern crate nes_core;
+
+use std::error::Error as StdError;
+
+use nes_core::{CartridgeError, Catridge, Error, CPU, PPU};
+
+// without a catridge the reset vector reads as 0 so the program runs from the start of ram
+fn cpu_running(program: &[u8]) -> CPU {
+    let mut cpu = CPU::new();
+    cpu.bus.ram[..program.len()].copy_from_slice(program);
+    cpu.reset();
+    cpu
+}
+
+#[test]
+fn jams_until_reset() {
+    let mut cpu = cpu_running(&[0xea, 0x02]);
+    cpu.execute_next_instruction().unwrap();
+
+    let jam = Error::CpuJam {
+        opcode: 0x02,
+        pc: 0x0001,
+    };
+    assert_eq!(cpu.execute_next_instruction(), Err(jam.clone()));
+    assert!(cpu.is_jammed());
+    assert_eq!(cpu.execute_next_instruction(), Err(jam.clone()));
+    assert_eq!(jam.to_string(), "CPU jammed by opcode '0x02'! PC: 0x0001");
+
+    cpu.reset();
+    assert!(!cpu.is_jammed());
+}
+
+#[test]
+fn formats_unknown_opcodes() {
+    let error = Error::UnknownOpcode {
+        opcode: 0x8b,
+        pc: 0x0000,
+    };
+    assert_eq!(
+        error.to_string(),
+        "Instruction with opcode '0x8b' not supported! PC: 0x0000"
+    );
+}
+
+#[test]
+fn reports_bad_ppu_register_addresses() {
+    let mut ppu = PPU::new();
+    assert_eq!(
+        ppu.read_register(0x0008, None),
+        Err(Error::BadRegisterAddress(0x0008))
+    );
+    assert_eq!(
+        ppu.write_register(0x2000, 0x00, None),
+        Err(Error::BadRegisterAddress(0x2000))
+    );
+    assert_eq!(
+        Error::BadRegisterAddress(0x2000).to_string(),
+        "Accessing register at address 0x2000 goes out of range!"
+    );
+}
+
+#[test]
+fn rejects_invalid_catridge_headers() {
+    let header = b"NES\x1a\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+    assert_eq!(
+        Catridge::new(&header[..4]).err(),
+        Some(CartridgeError::UnexpectedEof {
+            expected: 16,
+            found: 4
+        })
+    );
+
+    let mut rom = header.to_vec();
+    rom[3] = 0x00;
+    assert_eq!(
+        Catridge::new(&rom).err(),
+        Some(CartridgeError::InvalidMagic)
+    );
+
+    // one bank of prg and chr but only part of the prg is there
+    let mut rom = header.to_vec();
+    rom.resize(16 + 0x100, 0);
+    assert_eq!(
+        Catridge::new(&rom).err(),
+        Some(CartridgeError::UnexpectedEof {
+            expected: 16 + 0x4000 + 0x2000,
+            found: 16 + 0x100
+        })
+    );
+
+    let mut rom = header.to_vec();
+    rom[6] = 0x10;
+    rom.resize(16 + 0x4000 + 0x2000, 0);
+    assert_eq!(
+        Catridge::new(&rom).err(),
+        Some(CartridgeError::UnsupportedMapper(1))
+    );
+}
+
+#[test]
+fn wraps_catridge_errors() {
+    let error = Error::from(CartridgeError::InvalidMagic);
+    assert_eq!(error, Error::InvalidCartridge(CartridgeError::InvalidMagic));
+    assert_eq!(
+        error.to_string(),
+        "Invalid catridge: Missing 'NES\\x1a' magic in header!"
+    );
+    assert!(error.source().is_some());
+}
@@ -0,0 +1,98 @@
+extern crate nes_core;
+
+use nes_core::{BusCycle, CpuBus, FlatBus, CPU};
+
+// only implements what the trait requires so the rest comes from the default methods
+struct RamBus {
+    memory: Vec<u8>,
+    ticks: u32,
+}
+
+impl CpuBus for RamBus {
+    fn read(&mut self, address: u16) -> u8 {
+        self.ticks += 1;
+        self.memory[address as usize]
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.ticks += 1;
+        self.memory[address as usize] = data;
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        self.memory[address as usize] = data;
+    }
+
+    fn cycles_count(&self) -> u32 {
+        self.ticks
+    }
+}
+
+#[test]
+fn has_default_bus_methods() {
+    let mut bus = RamBus {
+        memory: vec![0; 0x10000],
+        ticks: 0,
+    };
+    bus.memory[0xffff] = 0x34;
+    bus.memory[0x0000] = 0x12;
+
+    // words wrap around the end of the address space
+    assert_eq!(bus.read_word(0xffff), 0x1234);
+    bus.tick_multiple(3);
+    assert_eq!(bus.cycles_count(), 5);
+    assert_eq!(bus.take_error(), None);
+}
+
+#[test]
+fn runs_the_cpu_on_any_bus() {
+    // lda #$42, sta $ffff
+    let mut memory = vec![0; 0x10000];
+    memory[0x8000..0x8005].copy_from_slice(&[0xa9, 0x42, 0x8d, 0xff, 0xff]);
+    let mut cpu = CPU::with_bus(RamBus { memory, ticks: 0 });
+    cpu.set_pc(0x8000);
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.bus.memory[0xffff], 0x42);
+    assert_eq!(cpu.bus.cycles_count(), 6);
+}
+
+#[test]
+fn uses_the_whole_address_space_as_ram() {
+    let mut bus = FlatBus::new();
+    bus.cycle_log = Some(Vec::new());
+
+    bus.write(0x2000, 0x12);
+    bus.write(0xffff, 0x34);
+    assert_eq!(bus.read(0x2000), 0x12);
+    bus.tick();
+    assert_eq!(bus.peek(0xffff), 0x34);
+
+    assert_eq!(bus.cycles_count(), 4);
+    assert_eq!(
+        bus.cycle_log.unwrap(),
+        [
+            BusCycle::Write {
+                address: 0x2000,
+                data: 0x12
+            },
+            BusCycle::Write {
+                address: 0xffff,
+                data: 0x34
+            },
+            BusCycle::Read {
+                address: 0x2000,
+                data: 0x12
+            },
+            BusCycle::Internal,
+        ]
+    );
+}
@@ -0,0 +1,145 @@
+extern crate nes_core;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use nes_core::{assemble, GdbServer, CPU};
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    fn request(&mut self, data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        write!(self.stream, "${}#{:02x}", data, checksum).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+        self.reply()
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut data = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                b'}' => data.push(self.read_byte() ^ 0x20),
+                byte => data.push(byte),
+            }
+        }
+
+        let checksum = [self.read_byte(), self.read_byte()];
+        let checksum = u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16).unwrap();
+        assert_eq!(
+            checksum,
+            data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+        );
+        String::from_utf8(data).unwrap()
+    }
+}
+
+// cpus can't be sent between threads so the server runs on the test thread and the client on
+// its own
+fn with_server<F: FnOnce(&mut Client) + Send + 'static>(client: F) -> CPU {
+    let program = assemble(
+        "
+        .org $0200
+        start:  lda #$12
+                sta $10
+                jsr sub
+        loop:   jmp loop
+        sub:    inc $11
+                rts
+        ",
+    )
+    .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let stream = TcpStream::connect(address).unwrap();
+        client(&mut Client { stream });
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let mut cpu = CPU::new();
+    program.write_to_bus(&mut cpu.bus);
+    cpu.set_pc(0x0200);
+    cpu.set_sp(0xfd);
+
+    let mut server = GdbServer::new(cpu, stream);
+    server.serve().unwrap();
+    client.join().unwrap();
+    server.cpu
+}
+
+#[test]
+fn reads_and_writes_registers_and_memory() {
+    let cpu = with_server(|client| {
+        assert_eq!(client.request("?"), "S05");
+        assert!(client.request("qSupported:swbreak+").contains("PacketSize"));
+        assert!(client
+            .request("qXfer:features:read:target.xml:0,1000")
+            .starts_with("l<?xml"));
+
+        // pc, sp, a, x, y and flags
+        assert_eq!(client.request("g"), "0002fd00000000");
+        assert_eq!(client.request("P2=7f"), "OK");
+        assert_eq!(client.request("p2"), "7f");
+        assert_eq!(client.request("G0003fe01020324"), "OK");
+        assert_eq!(client.request("g"), "0003fe01020324");
+
+        assert_eq!(client.request("m0200,3"), "a91285");
+        assert_eq!(client.request("M0300,2:eaea"), "OK");
+        assert_eq!(client.request("m0300,2"), "eaea");
+        assert_eq!(client.request("mzz"), "E01");
+        assert_eq!(client.request("m0000,800").len(), 0x1000);
+        assert_eq!(client.request("m0000,ffffffff"), "E01");
+        assert_eq!(client.request("vMustReplyEmpty"), "");
+
+        assert_eq!(client.request("D"), "OK");
+    });
+    assert_eq!(cpu.pc(), 0x0300);
+    assert_eq!(cpu.bus.ram[0x0301], 0xea);
+}
+
+#[test]
+fn steps_and_stops_on_breakpoints_and_watchpoints() {
+    let cpu = with_server(|client| {
+        assert_eq!(client.request("s"), "S05");
+        assert_eq!(client.request("p0"), "0202");
+
+        assert_eq!(client.request("Z2,0011,1"), "OK");
+        assert_eq!(client.request("c"), "T05watch:0011;");
+        assert_eq!(client.request("m0011,1"), "01");
+        assert_eq!(client.request("z2,0011,1"), "OK");
+        assert_eq!(client.request("Z2,0011,10001"), "E01");
+
+        // the range wraps past $ffff so it also covers the fetch of the rts at $020c
+        assert_eq!(client.request("Z3,ff00,10000"), "OK");
+        assert_eq!(client.request("c"), "T05rwatch:020c;");
+        assert_eq!(client.request("z3,ff00,10000"), "OK");
+
+        assert_eq!(client.request("Z0,0207,1"), "OK");
+        assert_eq!(client.request("c"), "T05swbreak:;");
+        assert_eq!(client.request("p0"), "0702");
+
+        // the program ends in an endless loop so only ctrl-c stops it
+        assert_eq!(client.request("z0,0207,1"), "OK");
+        client.stream.write_all(b"$c#63").unwrap();
+        assert_eq!(client.read_byte(), b'+');
+        client.stream.write_all(&[0x03]).unwrap();
+        assert_eq!(client.reply(), "S02");
+
+        client.stream.write_all(b"$k#6b").unwrap();
+        assert_eq!(client.read_byte(), b'+');
+    });
+    assert_eq!(cpu.bus.ram[0x10], 0x12);
+}
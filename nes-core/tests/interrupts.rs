@@ -0,0 +1,161 @@
+extern crate nes_core;
+
+use nes_core::{assemble, Flag, FlatBus, StatusFlags, CPU};
+
+const NMI_HANDLER: u16 = 0x0700;
+const IRQ_HANDLER: u16 = 0x0800;
+
+fn interrupt_cpu(source: &str, flags: u8) -> CPU<FlatBus> {
+    // the handlers just have a nop so the next instruction after them is easy to spot
+    let program = assemble(&format!(
+        "
+        .org $0600
+        {source}
+        .org ${nmi:04x}
+        nop
+        .org ${irq:04x}
+        nop
+        .org $fffa
+        .word ${nmi:04x}, $0600, ${irq:04x}
+        ",
+        source = source,
+        nmi = NMI_HANDLER,
+        irq = IRQ_HANDLER
+    ))
+    .unwrap();
+
+    let mut cpu = CPU::with_bus(FlatBus::new());
+    program.write_to_bus(&mut cpu.bus);
+    cpu.set_pc(0x0600);
+    cpu.set_flags(StatusFlags::from_bits(flags));
+    cpu
+}
+
+// runs the cpu the given number of cycles from the start of the program
+fn clock(cpu: &mut CPU<FlatBus>, cycles: u32) {
+    while cpu.bus.cycles_count < cycles {
+        cpu.clock().unwrap();
+    }
+}
+
+// the cycle the cpu got to the handler on
+fn run_to(cpu: &mut CPU<FlatBus>, handler: u16) -> u32 {
+    for _ in 0..10 {
+        cpu.execute_next_instruction().unwrap();
+        if cpu.pc() == handler {
+            return cpu.bus.cycles_count;
+        }
+    }
+    panic!("Never got to ${:04X}", handler);
+}
+
+// the address of the instruction the interrupt replaced and the pushed flags
+fn pushed_state(cpu: &CPU<FlatBus>) -> (u16, StatusFlags) {
+    let stack = |offset: u8| cpu.bus.memory[0x0100 + cpu.sp().wrapping_add(offset) as usize];
+    let address = stack(2) as u16 | (stack(3) as u16) << 8;
+    (address, StatusFlags::from_bits(stack(1)))
+}
+
+#[test]
+fn cli_and_plp_let_irqs_in_after_the_next_instruction() {
+    let mut cpu = interrupt_cpu("cli\nnop\nnop", 0x04);
+    cpu.set_irq_line(true);
+    assert_eq!(run_to(&mut cpu, IRQ_HANDLER), 2 + 2 + 7);
+    assert_eq!(pushed_state(&cpu).0, 0x0602);
+
+    let mut cpu = interrupt_cpu("plp\nnop\nnop", 0x04);
+    cpu.set_sp(0xfc);
+    cpu.bus.memory[0x01fd] = 0x00;
+    cpu.set_irq_line(true);
+    assert_eq!(run_to(&mut cpu, IRQ_HANDLER), 4 + 2 + 7);
+    assert_eq!(pushed_state(&cpu).0, 0x0602);
+}
+
+#[test]
+fn sei_still_lets_a_pending_irq_in() {
+    let mut cpu = interrupt_cpu("sei\nnop", 0x00);
+    cpu.set_irq_line(true);
+    assert_eq!(run_to(&mut cpu, IRQ_HANDLER), 2 + 7);
+
+    // the flags pushed already have interrupts disabled
+    let (address, flags) = pushed_state(&cpu);
+    assert_eq!(address, 0x0601);
+    assert!(flags.get(Flag::InterruptDisable));
+    assert!(!flags.get(Flag::Break));
+}
+
+#[test]
+fn nmis_hijack_brk_and_irqs() {
+    // the nmi shows up while brk is pushing the return address
+    let mut cpu = interrupt_cpu("brk\nnop\nnop", 0x00);
+    clock(&mut cpu, 3);
+    cpu.set_nmi_line(true);
+    assert_eq!(run_to(&mut cpu, NMI_HANDLER), 7);
+
+    let (address, flags) = pushed_state(&cpu);
+    assert_eq!(address, 0x0602);
+    assert!(flags.get(Flag::Break));
+
+    // and only runs once
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.pc(), NMI_HANDLER + 1);
+
+    let mut cpu = interrupt_cpu("nop\nnop", 0x00);
+    cpu.set_irq_line(true);
+    clock(&mut cpu, 5);
+    cpu.set_nmi_line(true);
+    assert_eq!(run_to(&mut cpu, NMI_HANDLER), 2 + 7);
+
+    let (address, flags) = pushed_state(&cpu);
+    assert_eq!(address, 0x0601);
+    assert!(!flags.get(Flag::Break));
+
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.pc(), NMI_HANDLER + 1);
+}
+
+#[test]
+fn taken_branches_without_a_page_cross_delay_new_interrupts() {
+    let source = "
+        clc
+        bcc @next
+    @next:
+        nop
+        nop
+    ";
+
+    // the irq shows up before the branch's operand gets read so it's taken right after
+    let mut cpu = interrupt_cpu(source, 0x00);
+    clock(&mut cpu, 2);
+    cpu.set_irq_line(true);
+    assert_eq!(run_to(&mut cpu, IRQ_HANDLER), 2 + 3 + 7);
+    assert_eq!(pushed_state(&cpu).0, 0x0603);
+
+    // one cycle later the branch doesn't see it in time and another instruction runs first
+    let mut cpu = interrupt_cpu(source, 0x00);
+    clock(&mut cpu, 3);
+    cpu.set_irq_line(true);
+    assert_eq!(run_to(&mut cpu, IRQ_HANDLER), 2 + 3 + 2 + 7);
+    assert_eq!(pushed_state(&cpu).0, 0x0604);
+
+    // nmis get delayed the same way
+    let mut cpu = interrupt_cpu(source, 0x00);
+    clock(&mut cpu, 2);
+    cpu.set_nmi_line(true);
+    assert_eq!(run_to(&mut cpu, NMI_HANDLER), 2 + 3 + 7);
+    assert_eq!(pushed_state(&cpu).0, 0x0603);
+
+    let mut cpu = interrupt_cpu(source, 0x00);
+    clock(&mut cpu, 3);
+    cpu.set_nmi_line(true);
+    assert_eq!(run_to(&mut cpu, NMI_HANDLER), 2 + 3 + 2 + 7);
+    assert_eq!(pushed_state(&cpu).0, 0x0604);
+
+    // even when the line is let go straight after
+    let mut cpu = interrupt_cpu(source, 0x00);
+    clock(&mut cpu, 3);
+    cpu.set_nmi_line(true);
+    clock(&mut cpu, 4);
+    cpu.set_nmi_line(false);
+    assert_eq!(run_to(&mut cpu, NMI_HANDLER), 2 + 3 + 2 + 7);
+}
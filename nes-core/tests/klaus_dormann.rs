@@ -0,0 +1,41 @@
+extern crate nes_core;
+
+mod common;
+
+use nes_core::{FlatBus, CPU};
+
+// the address of the final trap in a build of 6502_functional_test.a65 with the default options
+const SUCCESS_TRAP: u16 = 0x3469;
+const START_ADDRESS: u16 = 0x0400;
+const MAX_CYCLES: u32 = 200_000_000;
+
+#[test]
+#[ignore = "needs 6502_functional_test.bin, see tests/roms/README.md"]
+fn functional_test() {
+    let image = common::load_test_file("6502_functional_test.bin");
+
+    let mut cpu = CPU::with_bus(FlatBus::new());
+    cpu.bus.memory[..image.len()].copy_from_slice(&image);
+    // this is a test of a generic 6502 so it checks decimal mode too
+    cpu.set_decimal_mode_enabled(true);
+    cpu.set_pc(START_ADDRESS);
+
+    while cpu.bus.cycles_count < MAX_CYCLES {
+        let pc = cpu.pc();
+        if let Err(error) = cpu.execute_next_instruction() {
+            panic!("{}", error);
+        }
+
+        // failures and the final success are all jumps or branches to themselves
+        if cpu.pc() == pc {
+            assert_eq!(
+                pc, SUCCESS_TRAP,
+                "Functional test failed at trap 0x{:04x}",
+                pc
+            );
+            return;
+        }
+    }
+
+    panic!("Functional test didn't finish within {} cycles", MAX_CYCLES);
+}
@@ -0,0 +1,75 @@
+extern crate nes_core;
+
+use nes_core::{Bus, Catridge, CpuBus, CPU};
+
+// an nrom catridge with prg at the start of prg rom, nops after it and the reset vector pointing
+// at reset
+fn nrom(prg_banks: u8, prg: &[u8], reset: u16) -> Catridge {
+    let mut rom = b"NES\x1a\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom[4] = prg_banks;
+
+    let mut prg_rom = vec![0xea; prg_banks as usize * 0x4000];
+    prg_rom[..prg.len()].copy_from_slice(prg);
+    let vectors = prg_rom.len() - 6;
+    prg_rom[vectors + 2] = reset as u8;
+    prg_rom[vectors + 3] = (reset >> 8) as u8;
+
+    rom.extend(prg_rom);
+    rom.resize(rom.len() + 0x2000, 0);
+    Catridge::new(&rom).unwrap()
+}
+
+#[test]
+fn mirrors_a_single_prg_bank() {
+    let mut bus = Bus::new();
+    bus.connect_catridge(nrom(1, &[0x12, 0x34], 0x8000));
+    assert_eq!(bus.read_byte(0x8000), 0x12);
+    assert_eq!(bus.read_byte(0xc001), 0x34);
+    assert_eq!(bus.read_word(0xfffc), 0x8000);
+}
+
+#[test]
+fn maps_both_banks_of_32kb_prg() {
+    let mut prg = vec![0; 0x4001];
+    prg[0] = 0x12;
+    prg[0x4000] = 0x34;
+    let mut bus = Bus::new();
+    bus.connect_catridge(nrom(2, &prg, 0x8000));
+    assert_eq!(bus.read_byte(0x8000), 0x12);
+    assert_eq!(bus.read_byte(0xc000), 0x34);
+}
+
+#[test]
+fn writes_to_prg_ram_but_not_prg_rom() {
+    let mut bus = Bus::new();
+    bus.connect_catridge(nrom(1, &[0x12], 0x8000));
+
+    bus.write_byte(0x6000, 0x56);
+    bus.write_byte(0x7fff, 0x78);
+    assert_eq!(bus.read_byte(0x6000), 0x56);
+    assert_eq!(bus.read_byte(0x7fff), 0x78);
+
+    bus.write_byte(0x8000, 0x56);
+    assert_eq!(bus.read_byte(0x8000), 0x12);
+}
+
+#[test]
+fn reads_nothing_without_a_catridge() {
+    let mut bus = Bus::new();
+    assert_eq!(bus.read_byte(0x6000), 0);
+    assert_eq!(bus.read_byte(0x8000), 0);
+}
+
+#[test]
+fn starts_at_the_reset_vector() {
+    // lda #$42, sta $0200 at $c000
+    let mut prg = vec![0; 0x4005];
+    prg[0x4000..].copy_from_slice(&[0xa9, 0x42, 0x8d, 0x00, 0x02]);
+    let mut cpu = CPU::new();
+    cpu.bus.connect_catridge(nrom(2, &prg, 0xc000));
+
+    cpu.reset();
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.bus.ram[0x0200], 0x42);
+}
@@ -0,0 +1,76 @@
+extern crate nes_core;
+
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use nes_core::{Catridge, TraceEntry, CPU};
+
+// values read from the apu and io registers at $4000-$401f depend on more than just the cpu so
+// they get masked out of operands like "LDA $4015 = FF"
+fn mask_io_values(instruction: &str) -> String {
+    let mut instruction = instruction.to_string();
+    let mut start = 0;
+    while let Some(offset) = instruction[start..].find(" = ") {
+        let equals = start + offset;
+        let address = instruction[..equals]
+            .rsplit(|c: char| !c.is_ascii_hexdigit())
+            .next()
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+        if let Some(0x4000..=0x401f) = address {
+            instruction.replace_range(equals + 3..equals + 5, "??");
+        }
+        start = equals + 3;
+    }
+    instruction
+}
+
+// the ppu position depends on more than the cpu too, automation mode skips the ppu's warm up, so
+// everything but it gets compared
+fn comparable_fields(line: &str) -> String {
+    let ppu = line
+        .find("PPU:")
+        .expect("Missing ppu position in trace line");
+    let cycles = line.find("CYC:").expect("Missing cycles in trace line");
+    format!("{}{}", mask_io_values(&line[..ppu]), &line[cycles..])
+}
+
+#[test]
+#[ignore = "needs nestest.nes and nestest.log, see tests/roms/README.md"]
+fn nestest() {
+    let rom = common::load_test_file("nestest.nes");
+    let log = common::load_test_file("nestest.log");
+
+    let mut cpu = CPU::new();
+    cpu.bus.connect_catridge(Catridge::new(&rom).unwrap());
+    cpu.reset();
+    // automation mode starts at 0xc000 instead of the reset vector and doesn't need a ppu
+    cpu.set_pc(0xc000);
+
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let sink_lines = Rc::clone(&lines);
+    cpu.set_trace_sink(Some(Box::new(move |entry: &TraceEntry| {
+        sink_lines.borrow_mut().push(entry.to_string())
+    })));
+
+    for (number, expected) in String::from_utf8_lossy(&log).lines().enumerate() {
+        let result = cpu.execute_next_instruction();
+        let actual = lines.borrow_mut().pop().unwrap();
+
+        assert_eq!(
+            comparable_fields(&actual),
+            comparable_fields(expected),
+            "Trace differs from nestest.log at line {}",
+            number + 1
+        );
+
+        if let Err(error) = result {
+            panic!("{} at line {}", error, number + 1);
+        }
+    }
+
+    // the official and unofficial opcode tests store their error codes here
+    let ram = cpu.bus.ram;
+    assert_eq!((ram[0x02], ram[0x03]), (0, 0), "nestest reported errors");
+}
@@ -0,0 +1,601 @@
+extern crate nes_core;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use nes_core::{assemble, Bus, Catridge, CpuBus, FlatBus, TraceEntry, CPU};
+
+// nrom with chr ram and vertical mirroring
+fn bus() -> Bus {
+    let mut rom = b"NES\x1a\x01\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(16 + 0x4000, 0);
+    let mut bus = Bus::new();
+    bus.connect_catridge(Catridge::new(&rom).unwrap());
+    bus
+}
+
+fn set_address(bus: &mut Bus, address: u16) {
+    bus.write_byte(0x2006, (address >> 8) as u8);
+    bus.write_byte(0x2006, address as u8);
+}
+
+#[test]
+fn buffers_vram_reads() {
+    let mut bus = bus();
+    set_address(&mut bus, 0x2005);
+    bus.write_byte(0x2007, 0x11);
+    bus.write_byte(0x2007, 0x22);
+
+    // $2800 mirrors $2000 with vertical mirroring and the first read returns the stale buffer
+    set_address(&mut bus, 0x2805);
+    bus.read_byte(0x2007);
+    assert_eq!(bus.read_byte(0x2007), 0x11);
+    assert_eq!(bus.read_byte(0x2007), 0x22);
+    assert_eq!(bus.ppu.vram_address(), 0x2808);
+
+    // the registers are mirrored every 8 bytes up to $3fff
+    bus.write_byte(0x3ff8, 0x04);
+    set_address(&mut bus, 0x2400);
+    for data in 1..=3 {
+        bus.write_byte(0x2007, data);
+    }
+    assert_eq!(bus.ppu.peek_memory(0x2440, bus.catridge.as_ref()), 3);
+    assert_eq!(bus.ppu.peek_memory(0x2c20, bus.catridge.as_ref()), 2);
+    bus.write_byte(0x2000, 0x00);
+
+    // chr ram goes through the catridge
+    set_address(&mut bus, 0x0010);
+    bus.write_byte(0x2007, 0x5a);
+    assert_eq!(bus.catridge.as_ref().unwrap().chr_memory()[0x10], 0x5a);
+    set_address(&mut bus, 0x0010);
+    bus.read_byte(0x2007);
+    assert_eq!(bus.read_byte(0x2007), 0x5a);
+}
+
+#[test]
+fn reads_palettes_without_the_buffer() {
+    let mut bus = bus();
+    set_address(&mut bus, 0x2f00);
+    bus.write_byte(0x2007, 0x77);
+
+    // the sprite backdrop mirrors the background one and entries only have 6 bits
+    set_address(&mut bus, 0x3f10);
+    bus.write_byte(0x2007, 0xec);
+    set_address(&mut bus, 0x3f00);
+    assert_eq!(bus.read_byte(0x2007) & 0x3f, 0x2c);
+
+    // the buffer gets the nametable underneath instead
+    set_address(&mut bus, 0x2000);
+    assert_eq!(bus.read_byte(0x2007), 0x77);
+
+    // greyscale masks off the color
+    bus.write_byte(0x2001, 0x01);
+    set_address(&mut bus, 0x3f00);
+    assert_eq!(bus.read_byte(0x2007) & 0x3f, 0x20);
+}
+
+#[test]
+fn clears_vblank_and_the_write_toggle_on_status_reads() {
+    let mut bus = bus();
+    while bus.ppu.status() & 0x80 == 0 {
+        bus.clock();
+    }
+    assert_eq!(bus.ppu.scanline(), 241);
+
+    // the unused bits come from whatever was last on the data bus
+    bus.write_byte(0x2000, 0x1f);
+    assert_eq!(bus.read_byte(0x2002), 0x9f);
+    assert_eq!(bus.read_byte(0x2002) & 0x80, 0);
+
+    // a status read between the ppuaddr writes starts the pair over
+    bus.write_byte(0x2006, 0x21);
+    bus.read_byte(0x2002);
+    set_address(&mut bus, 0x2300);
+    assert_eq!(bus.ppu.vram_address(), 0x2300);
+
+    // write only registers read back the data bus
+    bus.write_byte(0x2005, 0x34);
+    assert!(bus.ppu.write_toggle());
+    assert_eq!(bus.read_byte(0x2000), 0x34);
+}
+
+// runs until the ppu is at or past the dot on the scanline
+fn run_to(bus: &mut Bus, scanline: u16, dot: u16) {
+    while bus.ppu.scanline() != scanline || bus.ppu.dot() < dot {
+        bus.clock();
+    }
+}
+
+#[test]
+fn fills_in_the_scroll_registers() {
+    let mut bus = bus();
+    bus.write_byte(0x2000, 0x00);
+    bus.read_byte(0x2002);
+
+    // coarse x 15 with fine x 5, then fine y 6 with coarse y 11
+    bus.write_byte(0x2005, 0x7d);
+    assert_eq!(bus.ppu.temp_vram_address(), 0x000f);
+    assert_eq!(bus.ppu.fine_x_scroll(), 5);
+    bus.write_byte(0x2005, 0x5e);
+    assert_eq!(bus.ppu.temp_vram_address(), 0x616f);
+
+    // the high byte of ppuaddr clears the top bit of fine y
+    bus.write_byte(0x2006, 0x3d);
+    assert_eq!(bus.ppu.temp_vram_address(), 0x3d6f);
+    assert_eq!(bus.ppu.vram_address(), 0x0000);
+    bus.write_byte(0x2006, 0xf0);
+    assert_eq!(bus.ppu.vram_address(), 0x3df0);
+
+    // the nametable bits come from ppuctrl
+    bus.write_byte(0x2000, 0x00);
+    assert_eq!(bus.ppu.temp_vram_address(), 0x31f0);
+    assert_eq!(bus.ppu.fine_x_scroll(), 5);
+}
+
+#[test]
+fn scrolls_while_rendering() {
+    let mut bus = bus();
+    bus.write_byte(0x2000, 0x01);
+    bus.write_byte(0x2005, 0x10);
+    bus.write_byte(0x2005, 0x00);
+    bus.write_byte(0x2001, 0x08);
+
+    // the pre-render line copies t over and fetches the first two tiles
+    run_to(&mut bus, 261, 305);
+    assert_eq!(bus.ppu.vram_address() & 0x7be0, 0x0000);
+    run_to(&mut bus, 0, 0);
+    assert_eq!(bus.ppu.vram_address(), 0x0404);
+
+    // the end of each line moves down a row and back to the left edge
+    run_to(&mut bus, 0, 258);
+    assert_eq!(bus.ppu.vram_address(), 0x1402);
+    run_to(&mut bus, 7, 258);
+    assert_eq!(bus.ppu.vram_address(), 0x0422);
+
+    // a split like a status bar, ppuaddr and ppuscroll writes set coarse y 29 and fine y 7 for
+    // the next line
+    run_to(&mut bus, 100, 100);
+    bus.write_byte(0x2006, 0x00);
+    bus.write_byte(0x2005, 0xef);
+    bus.write_byte(0x2005, 0x00);
+    bus.write_byte(0x2006, 0xa0);
+    assert_eq!(bus.ppu.vram_address(), 0x73a0);
+
+    // coarse y wraps from 29 into the nametable below
+    run_to(&mut bus, 100, 258);
+    assert_eq!(bus.ppu.vram_address(), 0x0800);
+
+    // nothing moves once rendering is off
+    bus.write_byte(0x2001, 0x00);
+    set_address(&mut bus, 0x2400);
+    run_to(&mut bus, 110, 0);
+    assert_eq!(bus.ppu.vram_address(), 0x2400);
+}
+
+#[test]
+fn reads_and_writes_oam() {
+    let mut bus = bus();
+    bus.write_byte(0x2003, 0x10);
+    for data in [0x20, 0x01, 0xff, 0x40] {
+        bus.write_byte(0x2004, data);
+    }
+    assert_eq!(bus.ppu.oam()[0x10..0x14], [0x20, 0x01, 0xe3, 0x40]);
+
+    // reads don't move the address
+    bus.write_byte(0x2003, 0x12);
+    assert_eq!(bus.read_byte(0x2004), 0xe3);
+    assert_eq!(bus.read_byte(0x2004), 0xe3);
+}
+
+fn write_vram(bus: &mut Bus, address: u16, data: &[u8]) {
+    set_address(bus, address);
+    for &byte in data {
+        bus.write_byte(0x2007, byte);
+    }
+}
+
+// draws one frame from the start of the pre-render line
+fn render_frame(bus: &mut Bus, mask: u8, scroll_x: u8) {
+    run_to(bus, 261, 0);
+    bus.write_byte(0x2005, scroll_x);
+    bus.write_byte(0x2005, 0x00);
+    bus.write_byte(0x2001, mask);
+    run_to(bus, 240, 0);
+    bus.write_byte(0x2001, 0x00);
+}
+
+#[test]
+fn renders_the_background() {
+    let mut bus = bus();
+    // tile 1 has color 1 on its top row and color 3 below that
+    write_vram(&mut bus, 0x0010, &[0xff; 8]);
+    write_vram(&mut bus, 0x0019, &[0xff; 7]);
+    // the top row of tiles alternates between tile 1 and the empty tile 0
+    write_vram(&mut bus, 0x2000, &[0x01, 0x00, 0x01]);
+    // the top left 2x2 tiles use palette 0 and the ones right of them palette 1
+    write_vram(&mut bus, 0x23c0, &[0x04]);
+    write_vram(&mut bus, 0x3f00, &[0x0f, 0x16, 0x00, 0x27]);
+    write_vram(&mut bus, 0x3f05, &[0x2a]);
+    bus.write_byte(0x2000, 0x00);
+
+    render_frame(&mut bus, 0x0a, 0);
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(frame.len(), 256 * 240);
+    assert_eq!(frame[0..8], [0x16; 8]);
+    assert_eq!(frame[8..16], [0x0f; 8]);
+    assert_eq!(frame[16..24], [0x2a; 8]);
+    assert_eq!(frame[256..264], [0x27; 8]);
+    assert_eq!(frame[8 * 256], 0x0f);
+
+    // fine x scroll moves the picture left by single pixels
+    render_frame(&mut bus, 0x0a, 4);
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(frame[0..4], [0x16; 4]);
+    assert_eq!(frame[4..12], [0x0f; 8]);
+    assert_eq!(frame[12..20], [0x2a; 8]);
+
+    // the leftmost 8 pixels can be hidden
+    render_frame(&mut bus, 0x08, 0);
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(frame[0..8], [0x0f; 8]);
+    assert_eq!(frame[16], 0x2a);
+
+    // nothing but the backdrop without rendering
+    render_frame(&mut bus, 0x00, 0);
+    assert!(bus.ppu.frame_buffer().iter().all(|&color| color == 0x0f));
+}
+
+// copies the sprites into oam the way games do with dma from a page of ram
+fn set_sprites(bus: &mut Bus, sprites: &[[u8; 4]]) {
+    for index in 0..64 {
+        let sprite = sprites.get(index).unwrap_or(&[0xff; 4]);
+        bus.ram[0x200 + index * 4..0x200 + index * 4 + 4].copy_from_slice(sprite);
+    }
+    bus.write_byte(0x2003, 0x00);
+    bus.write_byte(0x4014, 0x02);
+    while bus.dma_cycle() {}
+}
+
+#[test]
+fn copies_oam_with_dma() {
+    let mut bus = bus();
+    set_sprites(&mut bus, &[[0x10, 0x01, 0x02, 0x20]]);
+    assert_eq!(
+        bus.ppu.oam()[0..8],
+        [0x10, 0x01, 0x02, 0x20, 0xff, 0xff, 0xe3, 0xff]
+    );
+
+    // the cpu waits 513 cycles, one more when the dma starts on an odd cycle
+    for start in 0..2 {
+        while bus.cycles_count % 2 != start {
+            bus.clock();
+        }
+        let cycles = bus.cycles_count;
+        bus.write_byte(0x4014, 0x02);
+        while bus.dma_cycle() {}
+        assert_eq!(bus.cycles_count - cycles, 1 + 513 + start);
+    }
+}
+
+#[test]
+fn halts_the_cpu_a_cycle_at_a_time_for_dma() {
+    let program = assemble(
+        "
+        .org $c000
+        reset:  lda #$02
+                sta $4014
+                nop
+        ",
+    )
+    .unwrap();
+    let mut rom = b"NES\x1a\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.extend(program.prg_image(0xc000, 0x4000).unwrap());
+    rom.resize(rom.len() + 0x2000, 0);
+
+    let mut cpu = CPU::new();
+    cpu.bus.connect_catridge(Catridge::new(&rom).unwrap());
+    cpu.set_pc(0xc000);
+    cpu.bus.ram[0x0205] = 0x42;
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+
+    // the dma's own reads and writes aren't the cpu's
+    cpu.bus.cycle_log = Some(Vec::new());
+    let start = cpu.bus.cycles_count;
+    let mut clocks = 0;
+    while !cpu.clock().unwrap() {
+        clocks += 1;
+    }
+    let cycles = cpu.bus.cycles_count - start;
+    assert_eq!(clocks + 1, cycles);
+    assert!(
+        cycles == 513 + 2 || cycles == 514 + 2,
+        "took {} cycles",
+        cycles
+    );
+    assert_eq!(cpu.bus.cycle_log.as_ref().unwrap().len(), 2);
+    assert_eq!(cpu.pc(), 0xc006);
+    assert_eq!(cpu.bus.ppu.oam()[5], 0x42);
+}
+
+// sprite tiles are 1 with color 1 everywhere, 2 with color 2 on the left half and 3 with color 3
+// on its top row
+fn sprite_bus() -> Bus {
+    let mut bus = bus();
+    write_vram(&mut bus, 0x0010, &[0xff; 8]);
+    write_vram(&mut bus, 0x0028, &[0xf0; 8]);
+    write_vram(&mut bus, 0x0030, &[0x80]);
+    write_vram(&mut bus, 0x0038, &[0x80]);
+    write_vram(&mut bus, 0x3f00, &[0x0f, 0x01]);
+    write_vram(&mut bus, 0x3f11, &[0x11, 0x12, 0x13]);
+    write_vram(&mut bus, 0x3f15, &[0x15, 0x16]);
+    bus.write_byte(0x2000, 0x00);
+    bus
+}
+
+#[test]
+fn renders_sprites() {
+    let mut bus = sprite_bus();
+    // a background tile at the top left to go in front of and behind
+    write_vram(&mut bus, 0x2000, &[0x01]);
+    bus.write_byte(0x2000, 0x00);
+
+    set_sprites(
+        &mut bus,
+        &[
+            [19, 0x02, 0x00, 40],
+            // flipped horizontally with palette 1
+            [19, 0x02, 0x41, 60],
+            // sprites earlier in oam win even when they go behind the background
+            [0, 0x01, 0x20, 4],
+            [0, 0x03, 0x00, 0],
+            // flipped vertically so the top row ends up at the bottom
+            [29, 0x03, 0x80, 80],
+        ],
+    );
+    render_frame(&mut bus, 0x1e, 0);
+    let frame = bus.ppu.frame_buffer();
+    let row = |y: usize, x: usize| &frame[y * 256 + x..y * 256 + x + 8];
+
+    // sprites show up a line below their y position
+    assert_eq!(row(19, 40), [0x0f; 8]);
+    assert_eq!(
+        row(20, 40),
+        [0x12, 0x12, 0x12, 0x12, 0x0f, 0x0f, 0x0f, 0x0f]
+    );
+    assert_eq!(
+        row(20, 60),
+        [0x0f, 0x0f, 0x0f, 0x0f, 0x16, 0x16, 0x16, 0x16]
+    );
+    assert_eq!(row(27, 40)[0], 0x12);
+    assert_eq!(row(28, 40)[0], 0x0f);
+
+    // the background shows through the low priority sprite where it's opaque but not through
+    // the one at the top left corner
+    assert_eq!(row(1, 0), [0x13, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01]);
+    assert_eq!(row(1, 8)[0..4], [0x11; 4]);
+    assert_eq!(row(1, 12)[0], 0x0f);
+
+    assert_eq!(row(36, 80)[0], 0x0f);
+    assert_eq!(row(37, 80)[0..2], [0x13, 0x0f]);
+
+    // 8x16 sprites take the pattern table from the tile number and use the next tile below
+    set_sprites(&mut bus, &[[99, 0x02, 0x00, 40]]);
+    bus.write_byte(0x2000, 0x20);
+    render_frame(&mut bus, 0x1e, 0);
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(frame[100 * 256 + 40], 0x12);
+    assert_eq!(frame[108 * 256 + 40], 0x13);
+    assert_eq!(frame[109 * 256 + 40], 0x0f);
+}
+
+#[test]
+fn sets_sprite_zero_hit_where_it_overlaps_the_background() {
+    let mut bus = sprite_bus();
+    // background tiles along row 10
+    write_vram(&mut bus, 0x2140, &[0x01; 32]);
+    bus.write_byte(0x2000, 0x00);
+
+    // other sprites don't count
+    set_sprites(&mut bus, &[[150, 0x01, 0x00, 100], [79, 0x01, 0x00, 100]]);
+    render_frame(&mut bus, 0x1e, 0);
+    assert_eq!(bus.ppu.status() & 0x40, 0);
+
+    // the flag goes up on the line with the first overlapping pixel
+    set_sprites(&mut bus, &[[79, 0x01, 0x00, 100]]);
+    bus.write_byte(0x2000, 0x00);
+    run_to(&mut bus, 261, 0);
+    bus.write_byte(0x2001, 0x1e);
+    run_to(&mut bus, 79, 0);
+    assert_eq!(bus.ppu.status() & 0x40, 0);
+    run_to(&mut bus, 80, 110);
+    assert_eq!(bus.ppu.status() & 0x40, 0x40);
+
+    // and comes down at the end of vblank
+    run_to(&mut bus, 261, 2);
+    assert_eq!(bus.ppu.status() & 0x40, 0);
+}
+
+#[test]
+fn sets_sprite_overflow_with_the_hardware_bug() {
+    let line = |count: usize| vec![[50, 0x01, 0x00, 0]; count];
+
+    let mut bus = sprite_bus();
+    set_sprites(&mut bus, &line(8));
+    render_frame(&mut bus, 0x18, 0);
+    assert_eq!(bus.ppu.status() & 0x20, 0);
+
+    set_sprites(&mut bus, &line(9));
+    render_frame(&mut bus, 0x18, 0);
+    assert_eq!(bus.ppu.status() & 0x20, 0x20);
+
+    // after 8 sprites the tile of the 10th sprite gets checked as its y position
+    let mut sprites = line(8);
+    sprites.push([200, 0x01, 0x00, 0]);
+    sprites.push([200, 50, 0x00, 0]);
+    set_sprites(&mut bus, &sprites);
+    render_frame(&mut bus, 0x18, 0);
+    assert_eq!(bus.ppu.status() & 0x20, 0x20);
+
+    // so a real ninth sprite after that can get missed
+    let mut sprites = line(8);
+    sprites.push([200, 0x01, 0x00, 0]);
+    sprites.push([50, 0xf0, 0x00, 0]);
+    set_sprites(&mut bus, &sprites);
+    render_frame(&mut bus, 0x18, 0);
+    assert_eq!(bus.ppu.status() & 0x20, 0);
+}
+
+// clocks until the ppu is exactly at the dot, the cpu and ppu line up differently each frame so
+// this can take a few frames
+fn clock_to_exactly(bus: &mut Bus, scanline: u16, dot: u16) {
+    while bus.ppu.scanline() != scanline || bus.ppu.dot() != dot {
+        bus.clock();
+    }
+}
+
+#[test]
+fn races_status_reads_against_vblank() {
+    let mut bus = bus();
+    bus.write_byte(0x2000, 0x80);
+
+    // a read that lands a dot before vblank keeps the flag and the nmi from happening at all
+    clock_to_exactly(&mut bus, 240, 338);
+    assert_eq!(bus.read_byte(0x2002) & 0x80, 0);
+    assert_eq!((bus.ppu.scanline(), bus.ppu.dot()), (241, 0));
+    run_to(&mut bus, 241, 10);
+    assert_eq!(bus.ppu.status() & 0x80, 0);
+    assert!(!bus.ppu.nmi_line());
+
+    // one that lands right as it starts sees the flag but takes it away before the cpu notices
+    clock_to_exactly(&mut bus, 240, 339);
+    assert_eq!(bus.read_byte(0x2002) & 0x80, 0x80);
+    assert!(!bus.ppu.nmi_line());
+
+    // otherwise the line stays up until the end of vblank
+    run_to(&mut bus, 0, 0);
+    run_to(&mut bus, 241, 10);
+    assert!(bus.ppu.nmi_line());
+    run_to(&mut bus, 261, 2);
+    assert!(!bus.ppu.nmi_line());
+}
+
+fn nmi_cpu(enable_nmi: bool) -> CPU {
+    let program = assemble(&format!(
+        "
+        .org $c000
+        reset:  lda #${:02x}
+                sta $2000
+        loop:   jmp loop
+        nmi:    inc $10
+                rti
+        irq:    rti
+        .org $fffa
+        .word nmi, reset, irq
+        ",
+        if enable_nmi { 0x80 } else { 0x00 }
+    ))
+    .unwrap();
+
+    let mut rom = b"NES\x1a\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.extend(program.prg_image(0xc000, 0x4000).unwrap());
+    rom.resize(rom.len() + 0x2000, 0);
+
+    let mut cpu = CPU::new();
+    cpu.bus.connect_catridge(Catridge::new(&rom).unwrap());
+    cpu.reset();
+    cpu
+}
+
+#[test]
+fn raises_nmis_at_the_start_of_vblank() {
+    let mut cpu = nmi_cpu(true);
+    let nmi = 0xc008;
+    let mut nmis = Vec::new();
+    while cpu.bus.ppu.frame() < 3 {
+        cpu.execute_next_instruction().unwrap();
+        if cpu.pc() == nmi {
+            nmis.push((
+                cpu.bus.ppu.frame(),
+                cpu.bus.ppu.scanline(),
+                cpu.bus.ppu.dot(),
+            ));
+        }
+    }
+
+    assert_eq!(cpu.bus.ram[0x10], 3);
+    for (frame, &(nmi_frame, scanline, dot)) in nmis.iter().enumerate() {
+        // the interrupt sequence takes 7 cycles after the instruction the nmi showed up in
+        assert_eq!((nmi_frame, scanline), (frame as u64, 241));
+        assert!(dot > 21 && dot < 21 + 3 * 7 + 6, "nmi at dot {}", dot);
+    }
+}
+
+#[test]
+fn raises_nmis_when_turned_on_during_vblank() {
+    let mut cpu = nmi_cpu(false);
+    while cpu.bus.ppu.scanline() != 250 {
+        cpu.execute_next_instruction().unwrap();
+    }
+    assert_eq!(cpu.bus.ram[0x10], 0);
+
+    // each time the enable bit goes up while the flag is set
+    for count in 1..=2 {
+        cpu.bus.write_byte(0x2000, 0x80);
+        for _ in 0..4 {
+            cpu.execute_next_instruction().unwrap();
+        }
+        assert_eq!(cpu.bus.ram[0x10], count);
+
+        // the cpu has to see the line go down in between
+        cpu.bus.write_byte(0x2000, 0x00);
+        cpu.execute_next_instruction().unwrap();
+    }
+
+    // but not once the flag has been read
+    cpu.bus.read_byte(0x2002);
+    cpu.bus.write_byte(0x2000, 0x80);
+    for _ in 0..4 {
+        cpu.execute_next_instruction().unwrap();
+    }
+    assert_eq!(cpu.bus.ram[0x10], 2);
+}
+
+#[test]
+fn traces_the_real_ppu_position() {
+    let mut cpu = nmi_cpu(false);
+    // rendering makes odd frames a dot shorter
+    cpu.bus.write_byte(0x2001, 0x08);
+
+    let positions = Rc::new(RefCell::new(Vec::new()));
+    let sink_positions = positions.clone();
+    cpu.set_trace_sink(Some(Box::new(move |entry: &TraceEntry| {
+        sink_positions
+            .borrow_mut()
+            .push((entry.ppu_scanline, entry.ppu_dot, entry.cycles));
+    })));
+
+    let mut expected = Vec::new();
+    while cpu.bus.ppu.frame() < 3 {
+        let ppu = &cpu.bus.ppu;
+        expected.push((ppu.scanline() as u32, ppu.dot() as u32));
+        cpu.execute_next_instruction().unwrap();
+    }
+
+    let positions = positions.borrow();
+    assert_eq!(positions.len(), expected.len());
+    for (&(scanline, dot, _), &position) in positions.iter().zip(&expected) {
+        assert_eq!((scanline, dot), position);
+    }
+
+    // just counting cycles falls behind after the skipped dot, which is all a bus without a ppu
+    // can do
+    let (scanline, dot, cycles) = *positions.last().unwrap();
+    let mut flat_bus = FlatBus::new();
+    flat_bus.cycles_count = cycles;
+    assert_ne!(flat_bus.ppu_position(), (scanline as u16, dot as u16));
+
+    flat_bus.cycles_count = 7 + 341;
+    let entry = TraceEntry::new(cpu.state(), &flat_bus);
+    assert_eq!((entry.ppu_scanline, entry.ppu_dot), (3, 21));
+}
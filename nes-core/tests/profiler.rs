@@ -0,0 +1,186 @@
+extern crate nes_core;
+
+use nes_core::{assemble, CpuBus, FlatBus, Profiler, RoutineKind, RoutineProfile, Symbols, CPU};
+
+const PROGRAM: &str = "
+    .org $0200
+    start:  ldx #$ff
+            txs
+            jsr outer
+            jsr outer
+            brk
+            .byte 0
+            jsr trick
+    done:   jmp done
+
+    outer:  jsr inner
+            nop
+            rts
+
+    inner:  ldy #3
+    @loop:  dey
+            bne @loop
+            rts
+
+    irq:    jsr inner
+            rti
+
+    ; returning to a pushed address is a jump and not the end of trick
+    trick:  lda #>(target-1)
+            pha
+            lda #<(target-1)
+            pha
+            rts
+    target: rts
+
+    ; the nmi handler never returns and starts over with a fresh stack
+    nmi:    ldx #$ff
+            txs
+            jsr inner
+    again:  jmp again
+
+    .org $fffa
+    .word nmi, start, irq
+";
+
+fn cpu() -> (CPU<FlatBus>, Symbols) {
+    let program = assemble(PROGRAM).unwrap();
+    let mut cpu = CPU::with_bus(FlatBus::new());
+    program.write_to_bus(&mut cpu.bus);
+    cpu.set_pc(0x0200);
+    cpu.set_profiler(Some(Profiler::new()));
+
+    let mut symbols = Symbols::new();
+    for (name, &address) in &program.symbols {
+        symbols.add_cpu_label(address, name, 1);
+    }
+    (cpu, symbols)
+}
+
+fn run_until(cpu: &mut CPU<FlatBus>, symbols: &Symbols, label: &str) {
+    let address = symbols.address_of(&cpu.bus, label).unwrap();
+    while cpu.pc() != address {
+        cpu.execute_next_instruction().unwrap();
+    }
+}
+
+fn routine<'a>(
+    routines: &'a [RoutineProfile],
+    symbols: &Symbols,
+    name: &str,
+    kind: RoutineKind,
+) -> &'a RoutineProfile {
+    routines
+        .iter()
+        .find(|routine| routine.name(Some(symbols)) == name && routine.kind == kind)
+        .unwrap()
+}
+
+#[test]
+fn attributes_cycles_to_routines() {
+    let (mut cpu, symbols) = cpu();
+    run_until(&mut cpu, &symbols, "done");
+
+    let profiler = cpu.profiler().unwrap();
+    assert_eq!(profiler.depth(), 0);
+
+    let cycles = cpu.bus.cycles_count();
+    let routines = profiler.routines(cycles);
+    let inner = routine(&routines, &symbols, "inner", RoutineKind::Subroutine);
+    let outer = routine(&routines, &symbols, "outer", RoutineKind::Subroutine);
+    let irq = routine(&routines, &symbols, "irq", RoutineKind::Brk);
+    let trick = routine(&routines, &symbols, "trick", RoutineKind::Subroutine);
+
+    assert_eq!(inner.calls, 3);
+    assert_eq!(inner.inclusive_cycles, inner.exclusive_cycles);
+    let inner_call = inner.inclusive_cycles / 3;
+    assert_eq!(inner.longest_call, inner_call);
+
+    assert_eq!(outer.calls, 2);
+    assert_eq!(
+        outer.inclusive_cycles,
+        outer.exclusive_cycles + 2 * inner_call
+    );
+    assert_eq!(irq.calls, 1);
+    assert_eq!(irq.inclusive_cycles, irq.exclusive_cycles + inner_call);
+
+    // the rts to target stays inside trick until target returns
+    assert_eq!(trick.calls, 1);
+    assert!(routines
+        .iter()
+        .all(|routine| routine.name(Some(&symbols)) != "target"));
+
+    let top_level = outer.inclusive_cycles + irq.inclusive_cycles + trick.inclusive_cycles;
+    assert!(top_level < profiler.total_cycles(cycles));
+
+    let mut folded = Vec::new();
+    profiler
+        .write_folded(&mut folded, cycles, Some(&symbols))
+        .unwrap();
+    let folded = String::from_utf8(folded).unwrap();
+    let line = |stack: &str| {
+        folded
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{} ", stack)))
+            .map(|cycles| cycles.parse::<u64>().unwrap())
+    };
+    assert_eq!(line("outer;inner"), Some(2 * inner_call));
+    assert_eq!(line("[BRK] irq;inner"), Some(inner_call));
+    assert_eq!(line("outer"), Some(outer.exclusive_cycles));
+    let sum: u64 = folded
+        .lines()
+        .map(|line| line.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+        .sum();
+    assert_eq!(sum, profiler.total_cycles(cycles));
+
+    let mut table = Vec::new();
+    profiler
+        .write_table(&mut table, cycles, Some(&symbols))
+        .unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.lines().nth(1).unwrap().ends_with("<root>"));
+    assert!(table.contains("irq (BRK)"));
+}
+
+#[test]
+fn drops_routines_abandoned_by_resetting_the_stack() {
+    let (mut cpu, symbols) = cpu();
+    run_until(&mut cpu, &symbols, "inner");
+    assert_eq!(cpu.profiler().unwrap().depth(), 2);
+
+    cpu.set_nmi_line(true);
+    run_until(&mut cpu, &symbols, "nmi");
+    assert_eq!(cpu.profiler().unwrap().depth(), 3);
+
+    // the call from the fresh stack ends the nmi and everything it interrupted
+    run_until(&mut cpu, &symbols, "again");
+    let profiler = cpu.profiler().unwrap();
+    assert_eq!(profiler.depth(), 0);
+
+    let routines = profiler.routines(cpu.bus.cycles_count());
+    let nmi = routine(&routines, &symbols, "nmi", RoutineKind::Nmi);
+    assert_eq!(nmi.calls, 1);
+    assert_eq!(
+        routine(&routines, &symbols, "inner", RoutineKind::Subroutine).calls,
+        2
+    );
+}
+
+#[test]
+fn keeps_counting_when_the_bus_cycle_counter_wraps() {
+    let expected = {
+        let (mut cpu, symbols) = cpu();
+        run_until(&mut cpu, &symbols, "done");
+        let cycles = cpu.bus.cycles_count();
+        cpu.profiler().unwrap().total_cycles(cycles)
+    };
+
+    let (mut cpu, symbols) = cpu();
+    cpu.bus.cycles_count = u32::MAX - 100;
+    cpu.set_profiler(Some(Profiler::new()));
+    run_until(&mut cpu, &symbols, "done");
+    assert!(cpu.bus.cycles_count < 1000);
+
+    let profiler = cpu.profiler().unwrap();
+    assert_eq!(profiler.total_cycles(cpu.bus.cycles_count()), expected);
+}
@@ -0,0 +1,157 @@
+extern crate nes_core;
+
+mod common;
+
+use std::fs;
+
+use common::json::{self, Value};
+use nes_core::{BusCycle, CpuState, FlatBus, StatusFlags, CPU};
+
+// the vectors for the jam opcodes expect reads forever
+const SKIPPED_OPCODES: [u8; 12] = [
+    0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xb2, 0xd2, 0xf2,
+];
+// only show the first few failures of each opcode since one bug usually breaks lots of tests
+const MAX_FAILURES_PER_OPCODE: usize = 3;
+
+fn number(value: &Value, key: &str) -> Result<u16, String> {
+    value
+        .get(key)
+        .and_then(Value::as_number)
+        .map(|number| number as u16)
+        .ok_or_else(|| format!("Missing '{}'", key))
+}
+
+fn parse_state(value: &Value) -> Result<(CpuState, Vec<(u16, u8)>), String> {
+    let state = CpuState {
+        pc: number(value, "pc")?,
+        sp: number(value, "s")? as u8,
+        a: number(value, "a")? as u8,
+        x: number(value, "x")? as u8,
+        y: number(value, "y")? as u8,
+        flags: StatusFlags::from_bits(number(value, "p")? as u8),
+    };
+
+    let ram = value
+        .get("ram")
+        .and_then(Value::as_array)
+        .ok_or("Missing 'ram'")?
+        .iter()
+        .map(|entry| match entry.as_array() {
+            Some([address, data]) => match (address.as_number(), data.as_number()) {
+                (Some(address), Some(data)) => Ok((address as u16, data as u8)),
+                _ => Err("Invalid ram entry".to_string()),
+            },
+            _ => Err("Invalid ram entry".to_string()),
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((state, ram))
+}
+
+fn parse_cycles(value: &Value) -> Result<Vec<BusCycle>, String> {
+    value
+        .as_array()
+        .ok_or("Missing 'cycles'")?
+        .iter()
+        .map(|cycle| match cycle.as_array() {
+            Some([address, data, kind]) => {
+                let address = address.as_number().ok_or("Invalid cycle address")? as u16;
+                let data = data.as_number().ok_or("Invalid cycle data")? as u8;
+                match kind.as_str() {
+                    Some("read") => Ok(BusCycle::Read { address, data }),
+                    Some("write") => Ok(BusCycle::Write { address, data }),
+                    _ => Err("Invalid cycle kind".to_string()),
+                }
+            }
+            _ => Err("Invalid cycle".to_string()),
+        })
+        .collect()
+}
+
+fn run_test(test: &Value) -> Result<(), String> {
+    let (initial_state, initial_ram) =
+        parse_state(test.get("initial").ok_or("Missing 'initial'")?)?;
+    let (final_state, final_ram) = parse_state(test.get("final").ok_or("Missing 'final'")?)?;
+    let expected_cycles = parse_cycles(test.get("cycles").ok_or("Missing 'cycles'")?)?;
+
+    let mut cpu = CPU::with_bus(FlatBus::new());
+    for (address, data) in initial_ram {
+        cpu.bus.memory[address as usize] = data;
+    }
+    cpu.set_state(initial_state);
+    cpu.bus.cycle_log = Some(Vec::new());
+
+    cpu.execute_next_instruction()
+        .map_err(|error| error.to_string())?;
+
+    if cpu.state() != final_state {
+        return Err(format!(
+            "Expected {:?} but got {:?}",
+            final_state,
+            cpu.state()
+        ));
+    }
+
+    let memory = &cpu.bus.memory;
+    for (address, data) in final_ram {
+        if memory[address as usize] != data {
+            return Err(format!(
+                "Expected 0x{:02x} at 0x{:04x} but got 0x{:02x}",
+                data, address, memory[address as usize]
+            ));
+        }
+    }
+
+    let cycles = cpu.bus.cycle_log.take().unwrap();
+    if cycles != expected_cycles {
+        return Err(format!(
+            "Expected cycles {:?} but got {:?}",
+            expected_cycles, cycles
+        ));
+    }
+
+    Ok(())
+}
+
+// runs the nes6502 vectors from https://github.com/SingleStepTests/65x02 which should be put in
+// tests/roms/single_step as 00.json to ff.json
+#[test]
+#[ignore = "needs the nes6502 vectors in single_step/, see tests/roms/README.md"]
+fn single_step_tests() {
+    let directory = common::test_file_path("single_step");
+
+    let mut failures = Vec::new();
+    for opcode in 0..=0xffu8 {
+        if SKIPPED_OPCODES.contains(&opcode) {
+            continue;
+        }
+
+        let path = directory.join(format!("{:02x}.json", opcode));
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                failures.push(format!("{}: {}", path.display(), error));
+                continue;
+            }
+        };
+
+        let tests =
+            json::parse(&text).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        let opcode_failures = tests
+            .as_array()
+            .unwrap_or_else(|| panic!("{}: Expected an array of tests", path.display()))
+            .iter()
+            .filter_map(|test| {
+                let name = test.get("name").and_then(Value::as_str).unwrap_or("?");
+                run_test(test)
+                    .err()
+                    .map(|error| format!("0x{:02x} '{}': {}", opcode, name, error))
+            })
+            .take(MAX_FAILURES_PER_OPCODE);
+
+        failures.extend(opcode_failures);
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
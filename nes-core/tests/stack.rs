@@ -0,0 +1,94 @@
+extern crate nes_core;
+
+use nes_core::{Flag, StatusFlags, CPU};
+
+// the program goes at $0200 in ram and brk goes to $0000 since there is no catridge
+fn cpu_running(program: &[u8], flags: u8) -> CPU {
+    let mut cpu = CPU::new();
+    cpu.bus.ram[0x0200..0x0200 + program.len()].copy_from_slice(program);
+    cpu.set_pc(0x0200);
+    cpu.set_sp(0xfd);
+    cpu.set_flags(StatusFlags::from_bits(flags));
+    cpu
+}
+
+#[test]
+fn pushes_the_break_flag_from_php_and_brk() {
+    let mut cpu = cpu_running(&[0x08], 0x01);
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.bus.ram[0x01fd], 0x31);
+
+    let mut cpu = cpu_running(&[0x00], 0x01);
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.bus.ram[0x01fb], 0x31);
+    assert_eq!(cpu.flags().bits(), 0x05);
+}
+
+#[test]
+fn pulls_flags_without_the_break_flag() {
+    // plp
+    let mut cpu = cpu_running(&[0x28], 0x00);
+    cpu.bus.ram[0x01fe] = 0xdf;
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.flags().bits(), 0xef);
+
+    // brk into an rti
+    let mut cpu = cpu_running(&[0x00], 0x81);
+    cpu.bus.ram[0x0000] = 0x40;
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.flags().bits(), 0xa1);
+}
+
+#[test]
+fn transfers_to_sp_without_touching_flags() {
+    // ldx #$00, lda #$01, txs
+    let mut cpu = cpu_running(&[0xa2, 0x00, 0xa9, 0x01, 0x9a], 0x00);
+    for _ in 0..3 {
+        cpu.execute_next_instruction().unwrap();
+    }
+    assert_eq!(cpu.sp(), 0x00);
+    assert!(!cpu.flags().get(Flag::Zero));
+}
+
+// the cycles each instruction of the program took
+fn cycles(program: &[u8], instructions: usize) -> Vec<u32> {
+    let mut cpu = cpu_running(program, 0x00);
+    cpu.bus.ram[0x0000] = 0x40;
+    (0..instructions)
+        .map(|_| {
+            let start = cpu.bus.cycles_count;
+            cpu.execute_next_instruction().unwrap();
+            cpu.bus.cycles_count - start
+        })
+        .collect()
+}
+
+#[test]
+fn takes_the_stack_cycles() {
+    // pha, php, pla, plp
+    assert_eq!(cycles(&[0x48, 0x08, 0x68, 0x28], 4), [3, 3, 4, 4]);
+    // jsr $0204, nop, rts
+    assert_eq!(cycles(&[0x20, 0x04, 0x02, 0xea, 0x60], 2), [6, 6]);
+    // brk into an rti
+    assert_eq!(cycles(&[0x00], 2), [7, 6]);
+}
+
+#[test]
+fn skips_the_byte_after_brk() {
+    let mut cpu = cpu_running(&[0x00, 0xff, 0xea], 0x00);
+    cpu.bus.ram[0x0000] = 0x40;
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.pc(), 0x0202);
+}
+
+#[test]
+fn skips_the_offset_of_branches_not_taken() {
+    // clc, bcs +5
+    let mut cpu = cpu_running(&[0x18, 0xb0, 0x05], 0x00);
+    cpu.execute_next_instruction().unwrap();
+    cpu.execute_next_instruction().unwrap();
+    assert_eq!(cpu.pc(), 0x0203);
+    assert_eq!(cycles(&[0x18, 0xb0, 0x05], 2), [2, 2]);
+}
@@ -0,0 +1,192 @@
+extern crate nes_core;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use nes_core::{
+    assemble, disassemble_bus, disassemble_prg_bank, Bus, Catridge, SymbolFormat, Symbols,
+    TraceEntry, CPU,
+};
+
+// a 32kb nrom game so the two halves of prg rom have different offsets
+fn catridge() -> Catridge {
+    let program = assemble(
+        "
+        buffer = $0300
+        .org $8000
+        reset:  lda buffer+1
+                jsr $c000
+                jmp reset
+        .org $c000
+                sta $2000
+                rts
+        ",
+    )
+    .unwrap();
+
+    let mut rom = b"NES\x1a\x02\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.extend(program.prg_image(0x8000, 0x8000).unwrap());
+    Catridge::new(&rom).unwrap()
+}
+
+const DBG: &str = r#"version	major=2,minor=0
+info	csym=0,file=1,lib=0,line=10,mod=1,scope=2,seg=4,span=8,sym=4,type=2
+seg	id=0,name="HEADER",start=0x000000,size=0x0010,addrsize=absolute,type=ro,oname="game.nes",ooffs=0
+seg	id=1,name="CODE",start=0x008000,size=0x4000,addrsize=absolute,type=ro,oname="game.nes",ooffs=16
+seg	id=2,name="BSS",start=0x000300,size=0x0010,addrsize=absolute,type=rw
+seg	id=3,name="CODE2",start=0x00C000,size=0x4000,addrsize=absolute,type=ro,oname="game.nes",ooffs=16400
+sym	id=0,name="reset",addrsize=absolute,scope=0,def=1,ref=2,val=0x8000,seg=1,type=lab
+sym	id=1,name="buffer",addrsize=absolute,size=16,scope=0,def=3,val=0x300,seg=2,type=lab
+sym	id=2,name="PPUCTRL",addrsize=absolute,scope=0,def=4,val=0x2000,type=equ
+sym	id=3,name="@loop",addrsize=absolute,scope=0,def=5,val=0x8003,seg=1,type=lab,parent=0
+sym	id=4,name="set_ctrl",addrsize=absolute,scope=0,def=6,val=0xC000,seg=3,type=lab
+"#;
+
+#[test]
+fn parses_ca65_debug_info() {
+    let symbols = Symbols::parse(SymbolFormat::Dbg, DBG).unwrap();
+    assert_eq!(symbols.label(0x8000, Some(0)), Some("reset"));
+    assert_eq!(symbols.label(0x8003, Some(3)), Some("reset@loop"));
+    assert_eq!(symbols.label(0xc000, Some(0x4000)), Some("set_ctrl"));
+    assert_eq!(symbols.label(0x0300, None), Some("buffer"));
+    assert_eq!(symbols.label(0x0301, None), Some("buffer+1"));
+    // equates aren't labels
+    assert_eq!(symbols.label(0x2000, None), None);
+}
+
+#[test]
+fn parses_fceux_and_mesen_labels() {
+    assert_eq!(
+        SymbolFormat::from_file_name("game.nes.ram.nl"),
+        Some(SymbolFormat::Nl { bank: None })
+    );
+    assert_eq!(
+        SymbolFormat::from_file_name("game.nes.1.nl"),
+        Some(SymbolFormat::Nl { bank: Some(1) })
+    );
+    assert_eq!(
+        SymbolFormat::from_file_name("game.mlb"),
+        Some(SymbolFormat::Mlb)
+    );
+    assert_eq!(SymbolFormat::from_file_name("game.txt"), None);
+
+    let ram = Symbols::parse(
+        SymbolFormat::Nl { bank: None },
+        "$0300/10#buffer#scratch space\n$2000#PPUCTRL#\n",
+    )
+    .unwrap();
+    assert_eq!(ram.label(0x030f, None), Some("buffer+15"));
+    assert_eq!(ram.label(0x2000, None), Some("PPUCTRL"));
+
+    let bank = Symbols::parse(SymbolFormat::Nl { bank: Some(1) }, "$C000#set_ctrl#\n").unwrap();
+    assert_eq!(bank.label(0xc000, Some(0x4000)), Some("set_ctrl"));
+    assert_eq!(bank.label(0xc000, Some(0)), None);
+
+    let mlb = Symbols::parse(
+        SymbolFormat::Mlb,
+        "P:0000:reset:entry point\nR:0300-030F:buffer\nG:2000:PPUCTRL\nP:0010::just a comment\nNesPrgRom:4000:set_ctrl\n",
+    )
+    .unwrap();
+    assert_eq!(mlb.label(0x8000, Some(0)), Some("reset"));
+    assert_eq!(mlb.label(0x0302, None), Some("buffer+2"));
+    assert_eq!(mlb.label(0x2000, None), Some("PPUCTRL"));
+    assert_eq!(mlb.label(0xc000, Some(0x4000)), Some("set_ctrl"));
+
+    assert!(Symbols::parse(SymbolFormat::Mlb, "R:zz:broken").is_err());
+}
+
+#[test]
+fn rejects_labels_outside_of_the_address_space() {
+    let error = |format: SymbolFormat, text: &str| Symbols::parse(format, text).unwrap_err();
+
+    let dbg = DBG.replace("val=0x8003,seg=1", "val=0x7000,seg=1");
+    assert_eq!(
+        error(SymbolFormat::Dbg, &dbg).to_string(),
+        "Line 10: Label outside of its segment"
+    );
+    let dbg = DBG.replace("size=16", "size=0x20000");
+    assert_eq!(error(SymbolFormat::Dbg, &dbg).line, 8);
+
+    let ram = SymbolFormat::Nl { bank: None };
+    assert_eq!(error(ram, "$FFFF/2#wraps#").message, "Invalid address");
+    assert_eq!(error(ram, "$0000/FFFFFFFF#huge#").message, "Invalid size");
+    assert_eq!(error(ram, "$10000#too_far#").message, "Invalid address");
+
+    let bank = SymbolFormat::from_file_name("game.nes.ffffffffffffffff.nl").unwrap();
+    assert_eq!(
+        bank,
+        SymbolFormat::Nl {
+            bank: Some(usize::MAX)
+        }
+    );
+    assert_eq!(error(bank, "$C000#huge_bank#").message, "Invalid bank");
+
+    let mlb = SymbolFormat::Mlb;
+    assert_eq!(
+        error(mlb, "S:1FFF-2000:past_ram").message,
+        "Invalid address"
+    );
+    assert_eq!(error(mlb, "S:FFFF:past_ram").message, "Invalid address");
+    assert_eq!(error(mlb, "P:0000-FFFFFFFF:huge").message, "Invalid size");
+    assert_eq!(error(mlb, "G:FFFF-10000:wraps").message, "Invalid address");
+
+    let mlb = Symbols::parse(mlb, "W:1FF0-1FFF:end_of_ram\nG:0000-FFFF:everything").unwrap();
+    assert_eq!(mlb.label(0x7fff, None), Some("end_of_ram+15"));
+    assert_eq!(mlb.label(0xffff, None), Some("everything+65535"));
+}
+
+#[test]
+fn names_addresses_through_the_mapper() {
+    let mut symbols = Symbols::parse(SymbolFormat::Dbg, DBG).unwrap();
+    symbols.add_cpu_label(0x2000, "PPUCTRL", 1);
+
+    let mut bus = Bus::new();
+    bus.connect_catridge(catridge());
+    assert_eq!(symbols.cpu_label(&bus, 0xc000), Some("set_ctrl"));
+    assert_eq!(symbols.address_of(&bus, "set_ctrl"), Some(0xc000));
+    assert_eq!(symbols.address_of(&bus, "buffer"), Some(0x0300));
+
+    let lines: Vec<String> = disassemble_bus(&bus, 0x8000, 0x8008, Some(&symbols))
+        .iter()
+        .map(|instruction| instruction.to_string())
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "reset:\n8000  AD 01 03  LDA buffer+1",
+            "reset@loop:\n8003  20 00 C0  JSR set_ctrl",
+            "8006  4C 00 80  JMP reset",
+        ]
+    );
+
+    let bank =
+        disassemble_prg_bank(bus.catridge.as_ref().unwrap(), 1, 0xc000, Some(&symbols)).unwrap();
+    assert_eq!(
+        bank[0].to_string(),
+        "set_ctrl:\nC000  8D 00 20  STA PPUCTRL"
+    );
+}
+
+#[test]
+fn traces_with_labels() {
+    let symbols = Rc::new(Symbols::parse(SymbolFormat::Dbg, DBG).unwrap());
+    let mut cpu = CPU::new();
+    cpu.bus.connect_catridge(catridge());
+    cpu.set_pc(0x8000);
+    cpu.set_symbols(Some(symbols));
+
+    let operands = Rc::new(RefCell::new(Vec::new()));
+    let sink_operands = operands.clone();
+    cpu.set_trace_sink(Some(Box::new(move |entry: &TraceEntry| {
+        sink_operands.borrow_mut().push(entry.operand.clone())
+    })));
+
+    for _ in 0..3 {
+        cpu.execute_next_instruction().unwrap();
+    }
+
+    assert_eq!(
+        *operands.borrow(),
+        ["buffer+1 = 00", "set_ctrl", "$2000 = 00"]
+    );
+}
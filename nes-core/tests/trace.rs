@@ -0,0 +1,125 @@
+extern crate nes_core;
+
+use nes_core::{CpuState, FlatBus, StatusFlags, TraceEntry};
+
+// traces the instruction in bytes at $0400 with x at $05 and y at $10, memory holds the values
+// the operand points to
+fn trace(bytes: &[u8], memory: &[(u16, u8)]) -> String {
+    let mut bus = FlatBus::new();
+    bus.memory[0x0400..0x0400 + bytes.len()].copy_from_slice(bytes);
+    for &(address, value) in memory {
+        bus.memory[address as usize] = value;
+    }
+    bus.cycles_count = 7;
+
+    let state = CpuState {
+        pc: 0x0400,
+        sp: 0xfd,
+        a: 0x00,
+        x: 0x05,
+        y: 0x10,
+        flags: StatusFlags::from_bits(0x24),
+    };
+    TraceEntry::new(state, &bus).to_string()
+}
+
+#[test]
+fn formats_operands_without_memory() {
+    assert_eq!(
+        trace(&[0xa9, 0x10], &[]),
+        "0400  A9 10     LDA #$10                        A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0x0a], &[]),
+        "0400  0A        ASL A                           A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xe8], &[]),
+        "0400  E8        INX                             A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0x20, 0x34, 0x12], &[]),
+        "0400  20 34 12  JSR $1234                       A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0x4c, 0x00, 0x05], &[]),
+        "0400  4C 00 05  JMP $0500                       A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xd0, 0xfe], &[]),
+        "0400  D0 FE     BNE $0400                       A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+}
+
+#[test]
+fn shows_the_value_at_the_address() {
+    assert_eq!(
+        trace(&[0xa5, 0x10], &[(0x0010, 0x42)]),
+        "0400  A5 10     LDA $10 = 42                    A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xad, 0x00, 0x03], &[(0x0300, 0x42)]),
+        "0400  AD 00 03  LDA $0300 = 42                  A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0x95, 0x20], &[(0x0025, 0x11)]),
+        "0400  95 20     STA $20,X @ 25 = 11             A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xb6, 0xf8], &[(0x0008, 0x11)]),
+        "0400  B6 F8     LDX $F8,Y @ 08 = 11             A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xb9, 0x00, 0x03], &[(0x0310, 0x33)]),
+        "0400  B9 00 03  LDA $0300,Y @ 0310 = 33         A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0x9d, 0xfe, 0xff], &[(0x0003, 0x33)]),
+        "0400  9D FE FF  STA $FFFE,X @ 0003 = 33         A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+}
+
+#[test]
+fn shows_the_pointers_of_indirect_operands() {
+    assert_eq!(
+        trace(&[0xa1, 0x40], &[(0x0045, 0x00), (0x0046, 0x03), (0x0300, 0x77)]),
+        "0400  A1 40     LDA ($40,X) @ 45 = 0300 = 77    A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xb1, 0x40], &[(0x0040, 0x00), (0x0041, 0x03), (0x0310, 0x33)]),
+        "0400  B1 40     LDA ($40),Y = 0300 @ 0310 = 33  A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xb1, 0xff], &[(0x00ff, 0x00), (0x0000, 0x03), (0x0310, 0x33)]),
+        "0400  B1 FF     LDA ($FF),Y = 0300 @ 0310 = 33  A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0x6c, 0x00, 0x03], &[(0x0300, 0x34), (0x0301, 0x12)]),
+        "0400  6C 00 03  JMP ($0300) = 1234              A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+}
+
+#[test]
+fn reads_indirect_jumps_with_the_page_boundary_bug() {
+    // the high byte comes from $0200 instead of $0300
+    assert_eq!(
+        trace(&[0x6c, 0xff, 0x02], &[(0x02ff, 0x34), (0x0200, 0x12), (0x0300, 0x56)]),
+        "0400  6C FF 02  JMP ($02FF) = 1234              A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+}
+
+#[test]
+fn marks_unofficial_opcodes() {
+    assert_eq!(
+        trace(&[0x04, 0x10], &[(0x0010, 0x42)]),
+        "0400  04 10    *NOP $10 = 42                    A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xb3, 0x40], &[(0x0040, 0x00), (0x0041, 0x03), (0x0310, 0x33)]),
+        "0400  B3 40    *LAX ($40),Y = 0300 @ 0310 = 33  A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        trace(&[0xeb, 0x01], &[]),
+        "0400  EB 01    *SBC #$01                        A:00 X:05 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+}
@@ -0,0 +1,226 @@
+extern crate nes_core;
+
+use nes_core::{assemble, FlatBus, CPU};
+
+// runs each line of the program as one instruction, starting from $0600
+fn run(source: &str) -> CPU<FlatBus> {
+    let program = assemble(&format!(".org $0600\n{}", source)).unwrap();
+    let mut cpu = CPU::with_bus(FlatBus::new());
+    program.write_to_bus(&mut cpu.bus);
+    cpu.set_pc(0x0600);
+    for _ in source.lines().filter(|line| !line.trim().is_empty()) {
+        cpu.execute_next_instruction().unwrap();
+    }
+    cpu
+}
+
+#[test]
+fn xaa_and_lxa_use_a_fixed_magic_constant() {
+    let cpu = run("
+        ldx #$f0
+        lda #$01
+        xaa #$3c
+        ");
+    assert_eq!(cpu.a(), 0x20);
+    assert_eq!(cpu.flags().to_string(), "nvubdizc");
+
+    let cpu = run("
+        ldx #$11
+        lda #$00
+        xaa #$ff
+        ");
+    assert_eq!(cpu.a(), 0x00);
+    assert_eq!(cpu.flags().to_string(), "nvubdiZc");
+
+    let cpu = run("
+        lda #$20
+        lxa #$81
+        ");
+    assert_eq!((cpu.a(), cpu.x()), (0x80, 0x80));
+    assert_eq!(cpu.flags().to_string(), "Nvubdizc");
+}
+
+#[test]
+fn combines_an_immediate_and_with_another_operation() {
+    let cpu = run("
+        lda #$f0
+        anc #$81
+        ");
+    assert_eq!(cpu.a(), 0x80);
+    assert_eq!(cpu.flags().to_string(), "NvubdizC");
+
+    let cpu = run("
+        lda #$ff
+        alr #$03
+        ");
+    assert_eq!(cpu.a(), 0x01);
+    assert_eq!(cpu.flags().to_string(), "nvubdizC");
+
+    // carry comes from bit 6 and overflow from bit 6 xor bit 5 of the result
+    let cpu = run("
+        sec
+        lda #$ff
+        arr #$c0
+        ");
+    assert_eq!(cpu.a(), 0xe0);
+    assert_eq!(cpu.flags().to_string(), "NvubdizC");
+
+    let cpu = run("
+        clc
+        lda #$ff
+        arr #$40
+        ");
+    assert_eq!(cpu.a(), 0x20);
+    assert_eq!(cpu.flags().to_string(), "nVubdizc");
+
+    let cpu = run("
+        lda #$f0
+        ldx #$3c
+        axs #$10
+        ");
+    assert_eq!(cpu.x(), 0x20);
+    assert_eq!(cpu.flags().to_string(), "nvubdizC");
+
+    let cpu = run("
+        lda #$0f
+        ldx #$ff
+        axs #$10
+        ");
+    assert_eq!(cpu.x(), 0xff);
+    assert_eq!(cpu.flags().to_string(), "Nvubdizc");
+}
+
+#[test]
+fn loads_and_stores_two_registers_at_once() {
+    let cpu = run("
+        lda #$85
+        sta $10
+        lda #$00
+        lax $10
+        ");
+    assert_eq!((cpu.a(), cpu.x()), (0x85, 0x85));
+    assert_eq!(cpu.flags().to_string(), "Nvubdizc");
+
+    let cpu = run("
+        lda #$f0
+        ldx #$3c
+        sax $10
+        ");
+    assert_eq!(cpu.bus.memory[0x10], 0x30);
+    assert_eq!(cpu.flags().to_string(), "nvubdizc");
+
+    let cpu = run("
+        ldx #$f3
+        txs
+        lda #$3f
+        sta $0200
+        ldy #$00
+        las $0200,y
+        ");
+    assert_eq!((cpu.a(), cpu.x(), cpu.sp()), (0x33, 0x33, 0x33));
+    assert_eq!(cpu.flags().to_string(), "nvubdizc");
+}
+
+#[test]
+fn combines_a_read_modify_write_with_another_operation() {
+    let cpu = run("
+        lda #$81
+        sta $10
+        lda #$40
+        slo $10
+        ");
+    assert_eq!((cpu.bus.memory[0x10], cpu.a()), (0x02, 0x42));
+    assert_eq!(cpu.flags().to_string(), "nvubdizC");
+
+    let cpu = run("
+        lda #$81
+        sta $10
+        lda #$0f
+        sec
+        rla $10
+        ");
+    assert_eq!((cpu.bus.memory[0x10], cpu.a()), (0x03, 0x03));
+    assert_eq!(cpu.flags().to_string(), "nvubdizC");
+
+    let cpu = run("
+        lda #$81
+        sta $10
+        lda #$c0
+        sre $10
+        ");
+    assert_eq!((cpu.bus.memory[0x10], cpu.a()), (0x40, 0x80));
+    assert_eq!(cpu.flags().to_string(), "NvubdizC");
+
+    // the carry shifted out of the ror gets added in
+    let cpu = run("
+        lda #$03
+        sta $10
+        lda #$10
+        clc
+        rra $10
+        ");
+    assert_eq!((cpu.bus.memory[0x10], cpu.a()), (0x01, 0x12));
+    assert_eq!(cpu.flags().to_string(), "nvubdizc");
+
+    let cpu = run("
+        lda #$43
+        sta $10
+        lda #$42
+        dcp $10
+        ");
+    assert_eq!((cpu.bus.memory[0x10], cpu.a()), (0x42, 0x42));
+    assert_eq!(cpu.flags().to_string(), "nvubdiZC");
+
+    let cpu = run("
+        lda #$0f
+        sta $10
+        lda #$50
+        sec
+        isb $10
+        ");
+    assert_eq!((cpu.bus.memory[0x10], cpu.a()), (0x10, 0x40));
+    assert_eq!(cpu.flags().to_string(), "nvubdizC");
+}
+
+#[test]
+fn ands_unstable_stores_with_the_address_high_byte() {
+    let cpu = run("
+        ldx #$ff
+        ldy #$02
+        shx $0210,y
+        ");
+    assert_eq!(cpu.bus.memory[0x0212], 0x03);
+
+    let cpu = run("
+        ldy #$ff
+        ldx #$01
+        shy $0210,x
+        ");
+    assert_eq!(cpu.bus.memory[0x0211], 0x03);
+
+    let cpu = run("
+        lda #$ff
+        ldx #$f7
+        ldy #$01
+        sha $0210,y
+        ");
+    assert_eq!(cpu.bus.memory[0x0211], 0x03);
+
+    let cpu = run("
+        lda #$ff
+        ldx #$f7
+        ldy #$01
+        tas $0210,y
+        ");
+    assert_eq!(cpu.sp(), 0xf7);
+    assert_eq!(cpu.bus.memory[0x0211], 0x03);
+
+    // crossing a page puts the stored value in the high byte of the address
+    let cpu = run("
+        ldx #$05
+        ldy #$10
+        shx $02f8,y
+        ");
+    assert_eq!(cpu.bus.memory[0x0108], 0x01);
+    assert_eq!(cpu.bus.memory[0x0308], 0x00);
+}
@@ -0,0 +1,133 @@
+use std::io::{self, Write};
+use std::process;
+
+const USAGE: &str =
+    "Usage: nes-ui disasm <rom> [--bank <n>] [--origin <address>] [--symbols <file>]...
+       nes-ui disasm <rom> --cpu <start> <end> [--symbols <file>]...
+
+Without --cpu the prg rom banks are disassembled straight from the file, with --cpu the range is
+read from cpu memory after the catridge is connected. Addresses are in hex. Symbol files can be
+ca65 .dbg, fceux .nl or mesen .mlb files.";
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+fn parse_address(text: &str) -> u16 {
+    let digits = text.trim_start_matches('$').trim_start_matches("0x");
+    u16::from_str_radix(digits, 16)
+        .unwrap_or_else(|_| usage_error(&format!("Invalid address '{}'", text)))
+}
+
+fn write_instructions<W: Write>(
+    out: &mut W,
+    instructions: &[nes_core::DisassembledInstruction],
+) -> io::Result<()> {
+    for instruction in instructions {
+        writeln!(out, "{}", instruction)?;
+    }
+    Ok(())
+}
+
+pub fn run(args: &[String]) {
+    let mut path = None;
+    let mut bank = None;
+    let mut origin = None;
+    let mut cpu_range = None;
+    let mut symbols = nes_core::Symbols::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| usage_error(&format!("Missing value for {}", name)))
+                .clone()
+        };
+
+        match arg.as_str() {
+            "--bank" => {
+                let text = value("--bank");
+                bank = Some(text.parse::<usize>().unwrap_or_else(|_| {
+                    usage_error(&format!("Invalid bank '{}'", text));
+                }));
+            }
+            "--origin" => origin = Some(parse_address(&value("--origin"))),
+            "--symbols" => symbols.extend(crate::load_symbols(&value("--symbols"))),
+            "--cpu" => {
+                let start = parse_address(&value("--cpu"));
+                let end = parse_address(&value("--cpu"));
+                cpu_range = Some((start, end));
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => usage_error(&format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage_error("Missing rom path"));
+    let catridge = crate::load_catridge(&path);
+
+    // stopping early is fine since the output usually gets piped into something like head
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let symbols = if symbols.is_empty() {
+        None
+    } else {
+        Some(&symbols)
+    };
+    let _ = match cpu_range {
+        Some((start, end)) => write_cpu_range(&mut out, catridge, start, end, symbols),
+        None => write_prg_banks(&mut out, &catridge, bank, origin, symbols),
+    };
+}
+
+fn write_cpu_range<W: Write>(
+    out: &mut W,
+    catridge: nes_core::Catridge,
+    start: u16,
+    end: u16,
+    symbols: Option<&nes_core::Symbols>,
+) -> io::Result<()> {
+    let mut bus = nes_core::Bus::new();
+    bus.connect_catridge(catridge);
+    write_instructions(out, &nes_core::disassemble_bus(&bus, start, end, symbols))
+}
+
+fn write_prg_banks<W: Write>(
+    out: &mut W,
+    catridge: &nes_core::Catridge,
+    bank: Option<usize>,
+    origin: Option<u16>,
+    symbols: Option<&nes_core::Symbols>,
+) -> io::Result<()> {
+    let prg_banks = catridge.info().prg_banks as usize;
+    let banks = match bank {
+        Some(bank) if bank >= prg_banks => usage_error(&format!(
+            "Bank {} doesn't exist, the rom has {} banks",
+            bank, prg_banks
+        )),
+        Some(bank) => bank..bank + 1,
+        None => 0..prg_banks,
+    };
+
+    for bank in banks {
+        // a single bank gets mirrored so it's usually written for the top of memory
+        let origin = origin.unwrap_or(if prg_banks == 1 {
+            0xc000
+        } else {
+            0x8000 + (bank % 2) as u16 * 0x4000
+        });
+
+        writeln!(out, "; bank {} at ${:04X}", bank, origin)?;
+        if let Some(instructions) = nes_core::disassemble_prg_bank(catridge, bank, origin, symbols)
+        {
+            write_instructions(out, &instructions)?;
+        }
+    }
+
+    Ok(())
+}
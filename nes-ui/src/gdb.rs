@@ -0,0 +1,70 @@
+use std::net::TcpListener;
+use std::process;
+
+const USAGE: &str = "Usage: nes-ui gdb <rom> [--port <port>]
+
+Waits for a gdb remote protocol client on 127.0.0.1 (port 6502 by default), for example
+`target remote :6502` in gdb. The catridge is reset when the server starts and the cpu only runs
+when the client continues or steps. Once a client detaches the next one can connect.";
+
+const DEFAULT_PORT: u16 = 6502;
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+pub fn run(args: &[String]) {
+    let mut path = None;
+    let mut port = DEFAULT_PORT;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let text = args
+                    .next()
+                    .unwrap_or_else(|| usage_error("Missing value for --port"));
+                port = text
+                    .parse()
+                    .unwrap_or_else(|_| usage_error(&format!("Invalid port '{}'", text)));
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => usage_error(&format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage_error("Missing rom path"));
+    let mut cpu = nes_core::CPU::new();
+    cpu.bus.connect_catridge(crate::load_catridge(&path));
+    cpu.reset();
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap_or_else(|error| {
+        eprintln!("Failed to listen on port {}: {}", port, error);
+        process::exit(1);
+    });
+    eprintln!("Waiting for gdb on 127.0.0.1:{}", port);
+
+    // the machine carries over between clients so reattaching continues where the last one left
+    // off
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("Failed to accept a connection: {}", error);
+                continue;
+            }
+        };
+
+        let mut server = nes_core::GdbServer::new(cpu, stream);
+        if let Err(error) = server.serve() {
+            eprintln!("Connection closed: {}", error);
+        }
+        cpu = server.cpu;
+        eprintln!("Client disconnected, waiting for gdb on 127.0.0.1:{}", port);
+    }
+}
@@ -1,23 +1,102 @@
+extern crate nes_core;
+
+mod disasm;
+mod gdb;
+mod profile;
+mod screenshot;
+mod session;
+mod tui;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path;
+use std::process;
+
+fn load_catridge(path: &str) -> nes_core::Catridge {
+    let data = fs::read(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read {}: {}", path, error);
+        process::exit(1);
+    });
+
+    nes_core::Catridge::new(&data).unwrap_or_else(|error| {
+        eprintln!("Failed to load {}: {}", path, error);
+        process::exit(1);
+    })
+}
+
+// picks the format from the file name like game.dbg, game.mlb or game.nes.0.nl
+fn load_symbols(path: &str) -> nes_core::Symbols {
+    let file_name = path::Path::new(path)
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let format = nes_core::SymbolFormat::from_file_name(&file_name).unwrap_or_else(|| {
+        eprintln!(
+            "Unknown symbol file {}, expected a .dbg, .nl or .mlb file",
+            path
+        );
+        process::exit(1);
+    });
+
+    let text = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read {}: {}", path, error);
+        process::exit(1);
+    });
+
+    nes_core::Symbols::parse(format, &text).unwrap_or_else(|error| {
+        eprintln!("Failed to load {}: {}", path, error);
+        process::exit(1);
+    })
+}
+
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("disasm") => return disasm::run(&args[1..]),
+        Some("debug") => return tui::run(&args[1..]),
+        Some("gdb") => return gdb::run(&args[1..]),
+        Some("profile") => return profile::run(&args[1..]),
+        Some("screenshot") => return screenshot::run(&args[1..]),
+        _ => (),
+    }
+
     let mut cpu = nes_core::CPU::new();
-    // lda #$0a
-    cpu.bus.write_byte(0x0000, 0xa9);
-    cpu.bus.write_byte(0x0001, 0xff);
-    // sec
-    cpu.bus.write_byte(0x0002, 0x38);
-    // ror A
-    cpu.bus.write_byte(0x0003, 0x6a);
-    // sta $0200
-    cpu.bus.write_byte(0x0004, 0x8d);
-    cpu.bus.write_byte(0x0005, 0x00);
-    cpu.bus.write_byte(0x0006, 0x02);
+
+    let path = args.first();
+    if let Some(path) = path {
+        cpu.bus.connect_catridge(load_catridge(path));
+    } else {
+        let program = nes_core::assemble(
+            "
+            lda #$ff
+            sec
+            ror a
+            sta $0200
+            ",
+        )
+        .unwrap_or_else(|error| {
+            eprintln!("Failed to assemble the demo program: {}", error);
+            process::exit(1);
+        });
+        program.write_to_bus(&mut cpu.bus);
+    }
+
+    cpu.set_trace_sink(Some(Box::new(nes_core::TraceWriter::new(io::stdout()))));
     cpu.reset();
+    if path.is_none() {
+        // without a catridge there is no reset vector so start the program in ram directly
+        cpu.set_pc(0x0000);
+    }
 
     let mut prev_cycles = cpu.bus.cycles_count as i32;
     for _ in 0..4 {
-        cpu.execute_next_instruction();
+        if let Err(error) = cpu.execute_next_instruction() {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+
         let cycles_count = cpu.bus.cycles_count as i32;
-        println!("Clock cycles took: {}", cycles_count - &prev_cycles);
+        println!("Clock cycles took: {}", cycles_count - prev_cycles);
         prev_cycles = cycles_count;
     }
 
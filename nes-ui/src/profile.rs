@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+const USAGE: &str =
+    "Usage: nes-ui profile <rom> [--frames <n>] [--symbols <file>]... [--folded <file>]
+
+Runs the game from reset for a number of frames (60 by default) and prints how many cpu cycles
+each subroutine and interrupt handler took, with and without the routines it called. --folded
+also writes the call stacks in the folded format that flamegraph.pl and inferno read.";
+
+const DEFAULT_FRAMES: u64 = 60;
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+pub fn run(args: &[String]) {
+    let mut path = None;
+    let mut frames = DEFAULT_FRAMES;
+    let mut folded_path = None;
+    let mut symbols = nes_core::Symbols::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| usage_error(&format!("Missing value for {}", name)))
+                .clone()
+        };
+
+        match arg.as_str() {
+            "--frames" => {
+                let text = value("--frames");
+                frames = text
+                    .parse()
+                    .unwrap_or_else(|_| usage_error(&format!("Invalid frame count '{}'", text)));
+            }
+            "--symbols" => symbols.extend(crate::load_symbols(&value("--symbols"))),
+            "--folded" => folded_path = Some(value("--folded")),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => usage_error(&format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage_error("Missing rom path"));
+    let mut cpu = nes_core::CPU::new();
+    cpu.bus.connect_catridge(crate::load_catridge(&path));
+    cpu.reset();
+    cpu.set_profiler(Some(nes_core::Profiler::new()));
+
+    // whatever ran until an error is still worth reporting
+    let end_frame = cpu.bus.ppu.frame() + frames;
+    while cpu.bus.ppu.frame() < end_frame {
+        if let Err(error) = cpu.execute_next_instruction() {
+            eprintln!("Stopped at frame {}: {}", cpu.bus.ppu.frame(), error);
+            break;
+        }
+    }
+
+    let profiler = cpu.take_profiler().unwrap_or_default();
+    let cycles = cpu.bus.cycles_count;
+    let symbols = if symbols.is_empty() {
+        None
+    } else {
+        Some(&symbols)
+    };
+
+    if let Some(folded_path) = folded_path {
+        let result = fs::File::create(&folded_path).and_then(|file| {
+            let mut writer = io::BufWriter::new(file);
+            profiler.write_folded(&mut writer, cycles, symbols)?;
+            writer.flush()
+        });
+        if let Err(error) = result {
+            eprintln!("Failed to write {}: {}", folded_path, error);
+            process::exit(1);
+        }
+    }
+
+    // stopping early is fine since the output usually gets piped into something like head
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let _ = profiler.write_table(&mut out, cycles, symbols);
+}
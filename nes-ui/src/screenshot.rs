@@ -0,0 +1,148 @@
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+use nes_core::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const USAGE: &str = "Usage: nes-ui screenshot <rom> <output.ppm> [--frames <n>]
+
+Runs the game from reset for a number of frames (60 by default) and saves the last one as a ppm
+image.";
+
+const DEFAULT_FRAMES: u64 = 60;
+
+// rgb colors of the 64 entries in the nes's system palette
+const PALETTE: [(u8, u8, u8); 64] = [
+    (84, 84, 84),
+    (0, 30, 116),
+    (8, 16, 144),
+    (48, 0, 136),
+    (68, 0, 100),
+    (92, 0, 48),
+    (84, 4, 0),
+    (60, 24, 0),
+    (32, 42, 0),
+    (8, 58, 0),
+    (0, 64, 0),
+    (0, 60, 0),
+    (0, 50, 60),
+    (0, 0, 0),
+    (0, 0, 0),
+    (0, 0, 0),
+    (152, 150, 152),
+    (8, 76, 196),
+    (48, 50, 236),
+    (92, 30, 228),
+    (136, 20, 176),
+    (160, 20, 100),
+    (152, 34, 32),
+    (120, 60, 0),
+    (84, 90, 0),
+    (40, 114, 0),
+    (8, 124, 0),
+    (0, 118, 40),
+    (0, 102, 120),
+    (0, 0, 0),
+    (0, 0, 0),
+    (0, 0, 0),
+    (236, 238, 236),
+    (76, 154, 236),
+    (120, 124, 236),
+    (176, 98, 236),
+    (228, 84, 236),
+    (236, 88, 180),
+    (236, 106, 100),
+    (212, 136, 32),
+    (160, 170, 0),
+    (116, 196, 0),
+    (76, 208, 32),
+    (56, 204, 108),
+    (56, 180, 204),
+    (60, 60, 60),
+    (0, 0, 0),
+    (0, 0, 0),
+    (236, 238, 236),
+    (168, 204, 236),
+    (188, 188, 236),
+    (212, 178, 236),
+    (236, 174, 236),
+    (236, 174, 212),
+    (236, 180, 176),
+    (228, 196, 144),
+    (204, 210, 120),
+    (180, 222, 120),
+    (168, 226, 144),
+    (152, 226, 180),
+    (160, 214, 228),
+    (160, 162, 160),
+    (0, 0, 0),
+    (0, 0, 0),
+];
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+fn write_ppm<W: Write>(writer: &mut W, frame_buffer: &[u8]) -> io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", SCREEN_WIDTH, SCREEN_HEIGHT)?;
+    for &color in frame_buffer {
+        let (red, green, blue) = PALETTE[color as usize & 0x3f];
+        writer.write_all(&[red, green, blue])?;
+    }
+    Ok(())
+}
+
+pub fn run(args: &[String]) {
+    let mut paths = Vec::new();
+    let mut frames = DEFAULT_FRAMES;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                let text = args
+                    .next()
+                    .unwrap_or_else(|| usage_error("Missing value for --frames"));
+                frames = text
+                    .parse()
+                    .unwrap_or_else(|_| usage_error(&format!("Invalid frame count '{}'", text)));
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if paths.len() < 2 && !arg.starts_with("--") => paths.push(arg.clone()),
+            _ => usage_error(&format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    if paths.len() < 2 {
+        usage_error("Missing rom or output path");
+    }
+
+    let mut cpu = nes_core::CPU::new();
+    cpu.bus.connect_catridge(crate::load_catridge(&paths[0]));
+    cpu.reset();
+
+    // the frame buffer is complete once the ppu gets to vblank
+    let is_done = |ppu: &nes_core::PPU| {
+        ppu.frame() > frames || ppu.frame() == frames && ppu.scanline() >= SCREEN_HEIGHT as u16
+    };
+    while !is_done(&cpu.bus.ppu) {
+        if let Err(error) = cpu.execute_next_instruction() {
+            eprintln!("Stopped at frame {}: {}", cpu.bus.ppu.frame(), error);
+            break;
+        }
+    }
+
+    let result = fs::File::create(&paths[1]).and_then(|file| {
+        let mut writer = io::BufWriter::new(file);
+        write_ppm(&mut writer, cpu.bus.ppu.frame_buffer())?;
+        writer.flush()
+    });
+    if let Err(error) = result {
+        eprintln!("Failed to write {}: {}", paths[1], error);
+        process::exit(1);
+    }
+}
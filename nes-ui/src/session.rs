@@ -0,0 +1,417 @@
+use nes_core::{
+    disassemble_bus, Debugger, DisassembledInstruction, RunMode, StopReason, Symbols, WatchKind,
+    Watchpoint, CPU,
+};
+
+pub const HELP: &str = "s [n]          step n instructions      n              step over a jsr
+finish         run until the rts        c [n]          continue for up to n instructions
+b <addr>       add a breakpoint         d <addr>       delete a breakpoint
+w <addr> [r|w|rw] [value]  watch memory  unwatch <addr> delete the watchpoints at addr
+m <addr>       show memory at addr      poke <addr> <byte>...  edit memory
+pc <addr>      set the program counter  scanline <n>   run to the start of a scanline
+frame          run to the next frame    reset          reset the cpu
+q              quit                     empty line     repeat the last command
+Addresses can be labels or hex, values are in hex and counts are in decimal.";
+
+// continuing stops after this many instructions so a program without breakpoints can't hang the
+// debugger
+const DEFAULT_CONTINUE_LIMIT: u64 = 10_000_000;
+
+fn parse_hex(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches('$').trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid number '{}'", text))
+}
+
+fn parse_byte(text: &str) -> Result<u8, String> {
+    match parse_hex(text)? {
+        value @ 0..=0xff => Ok(value as u8),
+        _ => Err(format!("'{}' doesn't fit in a byte", text)),
+    }
+}
+
+// the debugger state and its commands, kept apart from the terminal so they can be tested
+pub struct Session {
+    pub cpu: CPU,
+    pub debugger: Debugger,
+    pub symbols: Symbols,
+    pub memory_address: u16,
+    pub message: String,
+}
+
+impl Session {
+    pub fn new(cpu: CPU, symbols: Symbols) -> Self {
+        Session {
+            cpu,
+            debugger: Debugger::new(),
+            symbols,
+            memory_address: 0x0000,
+            message: String::new(),
+        }
+    }
+
+    // returns true when the debugger should quit
+    pub fn execute(&mut self, line: &str) -> Result<bool, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (&command, args) = match words.split_first() {
+            Some(split) => split,
+            None => return Ok(false),
+        };
+
+        let arg = |index: usize| {
+            args.get(index)
+                .copied()
+                .ok_or_else(|| format!("Missing argument for {}", command))
+        };
+
+        match command {
+            "s" | "step" => {
+                let count = match args.first() {
+                    Some(text) => text
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid count '{}'", text))?,
+                    None => 1,
+                };
+                for _ in 0..count.max(1) {
+                    let reason = self.debugger.run(&mut self.cpu, RunMode::StepInto);
+                    if reason != StopReason::Done {
+                        self.report(reason);
+                        return Ok(false);
+                    }
+                }
+                self.message.clear();
+            }
+            "n" | "next" => self.run(RunMode::StepOver, None),
+            "finish" => self.run(RunMode::StepOut, None),
+            "c" | "continue" => {
+                let limit = match args.first() {
+                    Some(text) => text
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid count '{}'", text))?,
+                    None => DEFAULT_CONTINUE_LIMIT,
+                };
+                self.run(RunMode::Continue, Some(limit));
+            }
+            "scanline" => {
+                let scanline = arg(0)?
+                    .parse::<u16>()
+                    .map_err(|_| "Scanlines are 0 to 261".to_string())?;
+                if scanline >= 262 {
+                    return Err("Scanlines are 0 to 261".to_string());
+                }
+                self.run(RunMode::Scanline(scanline), None);
+            }
+            "frame" => {
+                let frame = self.cpu.bus.ppu.frame() + 1;
+                self.run(RunMode::Frame(frame), None);
+            }
+            "b" | "break" => {
+                let address = self.address(arg(0)?)?;
+                self.debugger.add_breakpoint(address);
+                self.message = format!("Breakpoint at ${:04X}", address);
+            }
+            "d" | "delete" => {
+                let address = self.address(arg(0)?)?;
+                if !self.debugger.remove_breakpoint(address) {
+                    return Err(format!("No breakpoint at ${:04X}", address));
+                }
+                self.message = format!("Deleted the breakpoint at ${:04X}", address);
+            }
+            "w" | "watch" => {
+                let address = self.address(arg(0)?)?;
+                let kind = match args.get(1).copied() {
+                    Some("r") => WatchKind::Read,
+                    Some("w") | None => WatchKind::Write,
+                    Some("rw") => WatchKind::Access,
+                    Some(kind) => return Err(format!("Unknown watch kind '{}'", kind)),
+                };
+                let value = args.get(2).map(|text| parse_byte(text)).transpose()?;
+                self.debugger.add_watchpoint(Watchpoint {
+                    value,
+                    ..Watchpoint::new(address, kind)
+                });
+                self.message = format!("Watching ${:04X}", address);
+            }
+            "unwatch" => {
+                let address = self.address(arg(0)?)?;
+                let watchpoints: Vec<Watchpoint> = self
+                    .debugger
+                    .watchpoints()
+                    .iter()
+                    .filter(|watchpoint| watchpoint.start == address)
+                    .copied()
+                    .collect();
+                if watchpoints.is_empty() {
+                    return Err(format!("No watchpoints at ${:04X}", address));
+                }
+                for watchpoint in watchpoints {
+                    self.debugger.remove_watchpoint(watchpoint);
+                }
+                self.message = format!("Stopped watching ${:04X}", address);
+            }
+            "m" | "mem" => self.memory_address = self.address(arg(0)?)?,
+            "poke" => {
+                let address = self.address(arg(0)?)?;
+                let data = args[1..]
+                    .iter()
+                    .map(|text| parse_byte(text))
+                    .collect::<Result<Vec<u8>, String>>()?;
+                if data.is_empty() {
+                    return Err("Missing bytes for poke".to_string());
+                }
+                for (offset, &byte) in data.iter().enumerate() {
+                    self.cpu.bus.poke(address.wrapping_add(offset as u16), byte);
+                }
+                self.message = format!("Wrote {} bytes at ${:04X}", data.len(), address);
+            }
+            "pc" => self.cpu.set_pc(self.address(arg(0)?)?),
+            "reset" => {
+                self.cpu.reset();
+                self.message = "Reset".to_string();
+            }
+            "h" | "help" => self.message = HELP.to_string(),
+            "q" | "quit" => return Ok(true),
+            _ => return Err(format!("Unknown command '{}', type help", command)),
+        }
+
+        Ok(false)
+    }
+
+    // labels take priority over hex so a label like "add" isn't read as $0ADD
+    pub fn address(&self, text: &str) -> Result<u16, String> {
+        match self.symbols.address_of(&self.cpu.bus, text) {
+            Some(address) => Ok(address),
+            None => parse_hex(text),
+        }
+    }
+
+    fn run(&mut self, mode: RunMode, limit: Option<u64>) {
+        self.debugger.instruction_limit = limit;
+        let reason = self.debugger.run(&mut self.cpu, mode);
+        self.debugger.instruction_limit = None;
+        self.report(reason);
+    }
+
+    fn report(&mut self, reason: StopReason) {
+        self.message = match reason {
+            StopReason::Done => String::new(),
+            StopReason::Breakpoint(pc) => format!("Breakpoint at ${:04X}", pc),
+            StopReason::Watchpoint {
+                kind,
+                address,
+                data,
+            } => format!("Watchpoint: {:?} ${:02X} at ${:04X}", kind, data, address),
+            StopReason::InstructionLimit => "Stopped at the instruction limit".to_string(),
+            StopReason::Error(error) => error.to_string(),
+        };
+    }
+
+    // finds where to start disassembling so the instructions line up with pc, going back as far
+    // as possible, then fills the rest of the count after it
+    pub fn disassembly(&self, before_pc: usize, count: usize) -> Vec<DisassembledInstruction> {
+        let pc = self.cpu.pc();
+        let bus = &self.cpu.bus;
+        let symbols = Some(&self.symbols);
+
+        let mut instructions = (1..=before_pc as u16 * 3)
+            .rev()
+            .filter_map(|back| {
+                let start = pc.checked_sub(back)?;
+                let instructions = disassemble_bus(bus, start, pc, symbols);
+                let synced = instructions.last()?.address == pc;
+                if synced {
+                    Some(instructions)
+                } else {
+                    None
+                }
+            })
+            .next()
+            .unwrap_or_else(|| disassemble_bus(bus, pc, pc, symbols));
+
+        let skip = instructions.len().saturating_sub(before_pc + 1);
+        instructions.drain(..skip);
+
+        while instructions.len() < count {
+            let last = instructions.last().map_or(pc, |instruction| {
+                instruction
+                    .address
+                    .wrapping_add(instruction.bytes.len() as u16)
+            });
+            instructions.extend(disassemble_bus(bus, last, last, symbols));
+        }
+
+        instructions.truncate(count);
+        instructions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the program goes at $0200 with its labels as symbols like the --asm option
+    fn session(source: &str) -> Session {
+        let program = nes_core::assemble(source).unwrap();
+        let mut cpu = CPU::new();
+        program.write_to_bus(&mut cpu.bus);
+        cpu.set_pc(0x0200);
+        cpu.set_sp(0xfd);
+
+        let mut symbols = Symbols::new();
+        for (name, &address) in &program.symbols {
+            symbols.add_cpu_label(address, name, 1);
+        }
+        Session::new(cpu, symbols)
+    }
+
+    const PROGRAM: &str = "
+        .org $0200
+    start:
+        jsr sub
+        lda #$01
+        sta $10
+    done:
+        jmp done
+    sub:
+        ldx #$05
+        inx
+        rts
+    ";
+
+    #[test]
+    fn steps_instructions() {
+        let mut session = session(PROGRAM);
+        session.execute("s").unwrap();
+        assert_eq!(session.cpu.pc(), session.address("sub").unwrap());
+        session.execute("s 2").unwrap();
+        assert_eq!(session.cpu.x(), 6);
+        assert_eq!(session.execute("s x"), Err("Invalid count 'x'".to_string()));
+    }
+
+    #[test]
+    fn steps_over_and_out_of_subroutines() {
+        let mut session = session(PROGRAM);
+        session.execute("n").unwrap();
+        assert_eq!(session.cpu.pc(), 0x0203);
+        assert_eq!(session.cpu.x(), 6);
+
+        let mut session = self::session(PROGRAM);
+        session.execute("s").unwrap();
+        session.execute("finish").unwrap();
+        assert_eq!(session.cpu.pc(), 0x0203);
+        assert_eq!(session.cpu.sp(), 0xfd);
+    }
+
+    #[test]
+    fn adds_and_removes_breakpoints() {
+        let mut session = session(PROGRAM);
+        session.execute("b done").unwrap();
+        assert_eq!(
+            session.debugger.breakpoints().collect::<Vec<u16>>(),
+            [0x0207]
+        );
+
+        session.execute("c").unwrap();
+        assert_eq!(session.cpu.pc(), 0x0207);
+        assert_eq!(session.message, "Breakpoint at $0207");
+
+        session.execute("d 207").unwrap();
+        assert_eq!(session.debugger.breakpoints().count(), 0);
+        assert_eq!(
+            session.execute("d done"),
+            Err("No breakpoint at $0207".to_string())
+        );
+    }
+
+    #[test]
+    fn adds_and_removes_watchpoints() {
+        let mut session = session(PROGRAM);
+        session.execute("w 10 w 01").unwrap();
+        session.execute("w $10 r").unwrap();
+        assert_eq!(session.debugger.watchpoints().len(), 2);
+        assert_eq!(session.debugger.watchpoints()[0].value, Some(0x01));
+        assert_eq!(
+            session.execute("w 10 x"),
+            Err("Unknown watch kind 'x'".to_string())
+        );
+
+        session.execute("c").unwrap();
+        assert_eq!(session.message, "Watchpoint: Write $01 at $0010");
+
+        session.execute("unwatch 10").unwrap();
+        assert!(session.debugger.watchpoints().is_empty());
+        assert_eq!(
+            session.execute("unwatch 10"),
+            Err("No watchpoints at $0010".to_string())
+        );
+    }
+
+    #[test]
+    fn pokes_memory() {
+        let mut session = session(PROGRAM);
+        session.execute("poke 300 de ad").unwrap();
+        assert_eq!(session.cpu.bus.peek(0x0300), 0xde);
+        assert_eq!(session.cpu.bus.peek(0x0301), 0xad);
+        assert_eq!(session.message, "Wrote 2 bytes at $0300");
+
+        assert_eq!(
+            session.execute("poke 300"),
+            Err("Missing bytes for poke".to_string())
+        );
+        assert_eq!(
+            session.execute("poke 300 100"),
+            Err("'100' doesn't fit in a byte".to_string())
+        );
+    }
+
+    #[test]
+    fn resolves_symbol_names_before_hex() {
+        let mut session = session(".org $0200\nadd: nop\n");
+        assert_eq!(session.address("add"), Ok(0x0200));
+        assert_eq!(session.address("$add"), Ok(0x0add));
+        assert_eq!(session.address("0x10"), Ok(0x0010));
+        assert_eq!(
+            session.address("nowhere"),
+            Err("Invalid number 'nowhere'".to_string())
+        );
+
+        session.execute("pc 300").unwrap();
+        session.execute("pc add").unwrap();
+        assert_eq!(session.cpu.pc(), 0x0200);
+        assert_eq!(session.execute("m add"), Ok(false));
+        assert_eq!(session.memory_address, 0x0200);
+    }
+
+    #[test]
+    fn handles_unknown_and_quit_commands() {
+        let mut session = session(PROGRAM);
+        assert_eq!(session.execute(""), Ok(false));
+        assert_eq!(session.execute("q"), Ok(true));
+        assert_eq!(
+            session.execute("jump"),
+            Err("Unknown command 'jump', type help".to_string())
+        );
+        assert_eq!(
+            session.execute("b"),
+            Err("Missing argument for b".to_string())
+        );
+    }
+
+    #[test]
+    fn lines_up_the_disassembly_with_pc() {
+        // the nops line up from any start so the instructions before pc are the real ones
+        let mut session = session(".org $0200\nnop\nnop\nnop\nnop\nloop: lda #$01\njmp loop\n");
+        session.execute("pc loop").unwrap();
+        let instructions = session.disassembly(2, 5);
+        let addresses: Vec<u16> = instructions
+            .iter()
+            .map(|instruction| instruction.address)
+            .collect();
+        assert_eq!(addresses, [0x0202, 0x0203, 0x0204, 0x0206, 0x0209]);
+        assert_eq!(instructions[2].label, Some("loop".to_string()));
+        assert_eq!(instructions[3].operand, "loop");
+
+        // nothing can come before $0000
+        session.execute("pc 0").unwrap();
+        assert_eq!(session.disassembly(2, 3)[0].address, 0x0000);
+    }
+}
@@ -0,0 +1,285 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use nes_core::{Symbols, CPU};
+
+use crate::session::Session;
+
+const USAGE: &str = "Usage: nes-ui debug <rom> [--symbols <file>]...
+       nes-ui debug --asm <file>
+
+Runs a terminal debugger on the rom, or on an assembly file that gets assembled into memory and
+started at its first .org. Symbol files can be ca65 .dbg, fceux .nl or mesen .mlb files. Type
+help at the prompt for the commands.";
+
+const DISASSEMBLY_LINES: usize = 16;
+const LINES_BEFORE_PC: usize = 5;
+const MEMORY_ROWS: usize = 8;
+const STACK_LINES: usize = 8;
+const LEFT_WIDTH: usize = 44;
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const REVERSE: &str = "\x1b[7m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+struct Tui {
+    session: Session,
+    last_command: String,
+}
+
+pub fn run(args: &[String]) {
+    let mut path = None;
+    let mut asm_path = None;
+    let mut symbols = Symbols::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--asm" => {
+                asm_path = Some(
+                    args.next()
+                        .unwrap_or_else(|| usage_error("Missing value for --asm"))
+                        .clone(),
+                )
+            }
+            "--symbols" => symbols.extend(crate::load_symbols(
+                args.next()
+                    .unwrap_or_else(|| usage_error("Missing value for --symbols")),
+            )),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => usage_error(&format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    let mut cpu = CPU::new();
+    match (path, asm_path) {
+        (Some(path), None) => {
+            cpu.bus.connect_catridge(crate::load_catridge(&path));
+            cpu.reset();
+        }
+        (None, Some(asm_path)) => symbols.extend(load_assembly(&mut cpu, &asm_path)),
+        (None, None) => usage_error("Missing rom path"),
+        (Some(_), Some(_)) => usage_error("Only one of a rom or --asm can be debugged"),
+    }
+
+    let mut tui = Tui {
+        session: Session::new(cpu, symbols),
+        last_command: String::new(),
+    };
+    tui.session.message = "Type help for the commands".to_string();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        tui.draw();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+
+        let line = if line.trim().is_empty() {
+            tui.last_command.clone()
+        } else {
+            line
+        };
+
+        match tui.session.execute(&line) {
+            Ok(true) => break,
+            Ok(false) => tui.last_command = line,
+            Err(message) => tui.session.message = message,
+        }
+    }
+}
+
+// the labels of the program are returned as symbols
+fn load_assembly(cpu: &mut CPU, path: &str) -> Symbols {
+    let source = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Failed to read {}: {}", path, error);
+        process::exit(1);
+    });
+
+    let program = nes_core::assemble(&source).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+    });
+
+    program.write_to_bus(&mut cpu.bus);
+    cpu.set_pc(program.segments.first().map_or(0, |segment| segment.origin));
+    cpu.set_sp(0xfd);
+
+    let mut symbols = Symbols::new();
+    for (name, &address) in &program.symbols {
+        symbols.add_cpu_label(address, name, 1);
+    }
+    symbols
+}
+
+impl Tui {
+    fn disassembly(&self) -> Vec<String> {
+        let session = &self.session;
+        let pc = session.cpu.pc();
+
+        // labels get a line of their own above the instruction
+        let mut lines = Vec::new();
+        for mut instruction in session.disassembly(LINES_BEFORE_PC, DISASSEMBLY_LINES) {
+            if let Some(label) = instruction.label.take() {
+                lines.push(format!("{:<width$}", label + ":", width = LEFT_WIDTH));
+            }
+
+            let marker = if session
+                .debugger
+                .breakpoints()
+                .any(|pc| pc == instruction.address)
+            {
+                '*'
+            } else {
+                ' '
+            };
+            let line = format!("{}{}", marker, instruction);
+            lines.push(if instruction.address == pc {
+                format!("{}{:<width$}{}", REVERSE, line, RESET, width = LEFT_WIDTH)
+            } else {
+                format!("{:<width$}", line, width = LEFT_WIDTH)
+            });
+        }
+
+        lines.truncate(DISASSEMBLY_LINES);
+        lines
+    }
+
+    fn registers(&self) -> Vec<String> {
+        let cpu = &self.session.cpu;
+        let ppu = &cpu.bus.ppu;
+        vec![
+            format!("{}Registers{}", BOLD, RESET),
+            format!(
+                "PC:{:04X} A:{:02X} X:{:02X} Y:{:02X} SP:{:02X}",
+                cpu.pc(),
+                cpu.a(),
+                cpu.x(),
+                cpu.y(),
+                cpu.sp()
+            ),
+            format!("P:{:02X} {}", cpu.flags().bits(), cpu.flags()),
+            format!("CYC:{}", cpu.bus.cycles_count),
+            format!(
+                "PPU: frame {} scanline {} dot {}",
+                ppu.frame(),
+                ppu.scanline(),
+                ppu.dot()
+            ),
+            String::new(),
+            format!("{}Stack{}", BOLD, RESET),
+        ]
+        .into_iter()
+        // the stack only goes up to the top of the page
+        .chain(
+            (cpu.sp() as u16 + 1..=0xff)
+                .take(STACK_LINES)
+                .map(|offset| {
+                    let address = 0x0100 | offset;
+                    format!("{:04X}: {:02X}", address, cpu.bus.peek(address))
+                }),
+        )
+        .collect()
+    }
+
+    fn memory(&self) -> Vec<String> {
+        (0..MEMORY_ROWS as u16)
+            .map(|row| {
+                let address = self.session.memory_address.wrapping_add(row * 16);
+                let bytes: Vec<u8> = (0..16)
+                    .map(|offset| self.session.cpu.bus.peek(address.wrapping_add(offset)))
+                    .collect();
+                let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                let text: String = bytes
+                    .iter()
+                    .map(|&byte| {
+                        if byte.is_ascii_graphic() || byte == b' ' {
+                            byte as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                format!("{:04X}: {}  {}", address, hex.join(" "), text)
+            })
+            .collect()
+    }
+
+    fn stop_points(&self) -> String {
+        let breakpoints: Vec<String> = self
+            .session
+            .debugger
+            .breakpoints()
+            .map(|pc| format!("${:04X}", pc))
+            .collect();
+        let watchpoints: Vec<String> = self
+            .session
+            .debugger
+            .watchpoints()
+            .iter()
+            .map(|watchpoint| format!("${:04X} {:?}", watchpoint.start, watchpoint.kind))
+            .collect();
+
+        format!(
+            "Breakpoints: {}  Watchpoints: {}",
+            if breakpoints.is_empty() {
+                "none".to_string()
+            } else {
+                breakpoints.join(" ")
+            },
+            if watchpoints.is_empty() {
+                "none".to_string()
+            } else {
+                watchpoints.join(", ")
+            }
+        )
+    }
+
+    fn draw(&self) {
+        let disassembly = self.disassembly();
+        let registers = self.registers();
+
+        let mut screen = String::from(CLEAR_SCREEN);
+        screen.push_str(&format!(
+            "{}{:<width$}{}\n",
+            BOLD,
+            "Disassembly",
+            RESET,
+            width = LEFT_WIDTH
+        ));
+        for (index, line) in disassembly.iter().enumerate() {
+            let right = registers.get(index).map_or("", String::as_str);
+            screen.push_str(&format!("{} {}\n", line, right));
+        }
+
+        screen.push_str(&format!("\n{}Memory{}\n", BOLD, RESET));
+        for line in self.memory() {
+            screen.push_str(&line);
+            screen.push('\n');
+        }
+
+        screen.push_str(&format!("\n{}\n", self.stop_points()));
+        if !self.session.message.is_empty() {
+            screen.push_str(&format!("{}\n", self.session.message));
+        }
+        screen.push_str("> ");
+
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let _ = out.write_all(screen.as_bytes());
+        let _ = out.flush();
+    }
+}